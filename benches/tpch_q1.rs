@@ -1,20 +1,72 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use goose_db::query::execute_tpch_q1;
+use goose_db::dbgen::{generate, Table};
+use goose_db::params::Q1Params;
+use goose_db::query::{execute_tpch_q1, execute_tpch_q1_with_options, ExecutionOptions};
+use goose_db::reader::FilterStrategy;
+use goose_db::summation::Summation;
+use goose_db::sql::parser::parse_date;
 
 /// Configure your data path here
 const DATA_PATH: &str = "data/lineitem.parquet";
 
+/// Generate SF 1 into `data/` when there is no file yet
+fn ensure_data() {
+    if !std::path::Path::new(DATA_PATH).exists() {
+        generate("data".as_ref(), 1.0, &[Table::Lineitem]).unwrap();
+    }
+}
+
 fn benchmark_tpch_q1(c: &mut Criterion) {
+    ensure_data();
+    let params = Q1Params::default();
+
     // Warmup - ensure file is in OS page cache
-    let _ = execute_tpch_q1(DATA_PATH);
-    
+    let _ = execute_tpch_q1(DATA_PATH, &params);
+
     c.bench_function("tpch_q1", |b| {
         b.iter(|| {
-            let result = execute_tpch_q1(black_box(DATA_PATH)).unwrap();
+            let result = execute_tpch_q1(black_box(DATA_PATH), &params).unwrap();
             black_box(result)
         })
     });
 }
 
-criterion_group!(benches, benchmark_tpch_q1);
+/// Decode-then-mask against Parquet `RowFilter` pushdown, at Q1's cutoff
+/// (~98% of rows qualify) and at a selective one (~40%)
+fn benchmark_filter_strategies(c: &mut Criterion) {
+    ensure_data();
+    let cutoffs = [("q1", Q1Params::default()), ("1995-01-01", Q1Params::with_date(parse_date("1995-01-01").unwrap()))];
+    let mut group = c.benchmark_group("filter_strategy");
+    for (label, params) in cutoffs {
+        for (name, filter) in [("mask", FilterStrategy::Mask), ("row_filter", FilterStrategy::RowFilter)] {
+            let options = ExecutionOptions { filter, ..ExecutionOptions::default() };
+            group.bench_function(format!("{}/{}", name, label), |b| {
+                b.iter(|| {
+                    let result = execute_tpch_q1_with_options(black_box(DATA_PATH), &params, &options).unwrap();
+                    black_box(result)
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Cost of the compensated and pairwise sums' second pass over plain sums
+fn benchmark_summation(c: &mut Criterion) {
+    ensure_data();
+    let params = Q1Params::default();
+    let mut group = c.benchmark_group("summation");
+    for (name, summation) in [("plain", Summation::Plain), ("kahan", Summation::Kahan), ("pairwise", Summation::Pairwise)] {
+        let options = ExecutionOptions { summation, ..ExecutionOptions::default() };
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let result = execute_tpch_q1_with_options(black_box(DATA_PATH), &params, &options).unwrap();
+                black_box(result)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_tpch_q1, benchmark_filter_strategies, benchmark_summation);
 criterion_main!(benches);
@@ -1,104 +1,434 @@
 //! Perfect hash array aggregation for TPC-H Q1
 //! 
-//! Uses a fixed 6-slot array instead of HashMap since we know the exact
-//! grouping keys: (A/N/R) × (F/O) = 6 possible combinations
-//! (though typically only 4 appear in TPC-H data)
+//! Uses a small slot array instead of HashMap. The key domain is discovered
+//! from Parquet dictionary pages up front (see `LineitemPlan::column_domain`);
+//! for standard TPC-H data it is (A/N/R) × (F/O) = 6 possible combinations
+//! (though typically only 4 appear). Keys outside the domain are an error,
+//! never silently folded into another group.
+//!
+//! NULLs follow SQL: a key with a NULL part is its own group (kept outside
+//! the perfect hash and sorted last), NULL measures are left out of SUM and
+//! AVG but still counted by COUNT(*). Batches without NULLs take the
+//! unrolled fast path; only batches that have some pay for validity checks.
 
-use arrow::array::{Float64Array, StringArray};
+use std::sync::Arc;
 
-/// Aggregation state for a single group
+use arrow::array::{Array, ArrayRef, AsArray, Decimal128Array, Float64Array, RecordBatch, StringArray, UInt64Array};
+use arrow::compute::cast;
+
+use crate::decimal_aggregator::{Decimal, ExactSums};
+use crate::distinct::{distinct_rows, DistinctCount, DistinctMode};
+use crate::quantile::{QuantileMode, QuantileSketch};
+use crate::summation::FloatSum;
+use crate::error::GooseError;
+use crate::keys::{key_byte, key_bytes, key_ranks};
+use arrow::datatypes::{DataType, Field, Schema, UInt64Type};
+
+/// Aggregation state for a single group, with sums accumulated in `S`
 /// 
-/// Cache-aligned to 64 bytes (one cache line) for optimal performance.
+/// Cache-aligned to 64 bytes (one cache line) for optimal performance;
+/// the sizes below are for the plain `f64` sums.
 /// Fields are ordered by access frequency: hot fields first.
 #[derive(Debug, Clone, Default)]
 #[repr(C, align(64))]
-pub struct AggState {
+pub struct AggState<S = f64> {
     // Hot fields (accessed every iteration in aggregation loop)
-    pub sum_disc_price: f64,    // 8 bytes - computed expression result
-    pub sum_charge: f64,        // 8 bytes - computed expression result
+    pub sum_disc_price: S,      // 8 bytes - computed expression result
+    pub sum_charge: S,          // 8 bytes - computed expression result
     pub count: u64,             // 8 bytes - incremented every row
     
     // Warm fields (accessed but less critical)
-    pub sum_qty: f64,           // 8 bytes
-    pub sum_base_price: f64,    // 8 bytes
-    pub sum_discount: f64,      // 8 bytes - for computing avg_disc
-    
+    pub sum_qty: S,             // 8 bytes
+    pub sum_base_price: S,      // 8 bytes
+    pub sum_discount: S,        // 8 bytes - for computing avg_disc
+
+    // Cold: state of the optional aggregates, allocated on first use
+    pub extras: Option<Box<GroupExtras>>, // 8 bytes
+
     // Padding to ensure 64-byte alignment (one cache line)
-    _padding: [u8; 16],         // 16 bytes padding
+    _padding: [u8; 8],          // 8 bytes padding
 }                               // Total: 64 bytes
 
-impl AggState {
+impl<S: FloatSum> AggState<S> {
     /// Merge another state into this one
-    pub fn merge(&mut self, other: &AggState) {
-        self.sum_disc_price += other.sum_disc_price;
-        self.sum_charge += other.sum_charge;
+    pub fn merge(&mut self, other: &AggState<S>) {
+        self.sum_disc_price.merge(&other.sum_disc_price);
+        self.sum_charge.merge(&other.sum_charge);
         self.count += other.count;
-        self.sum_qty += other.sum_qty;
-        self.sum_base_price += other.sum_base_price;
-        self.sum_discount += other.sum_discount;
+        self.sum_qty.merge(&other.sum_qty);
+        self.sum_base_price.merge(&other.sum_base_price);
+        self.sum_discount.merge(&other.sum_discount);
+        if let Some(other_extras) = &other.extras {
+            self.extras.get_or_insert_with(Default::default).merge(other_extras);
+        }
     }
     
     /// Check if this group has any data
     pub fn is_empty(&self) -> bool {
         self.count == 0
     }
-    
-    /// Compute avg_qty
-    pub fn avg_qty(&self) -> f64 {
-        if self.count == 0 { 0.0 } else { self.sum_qty / self.count as f64 }
+
+    /// `[qty, base_price, disc_price, charge, discount]` sums
+    pub fn sums(&self) -> [f64; 5] {
+        [&self.sum_qty, &self.sum_base_price, &self.sum_disc_price, &self.sum_charge, &self.sum_discount].map(S::value)
     }
-    
-    /// Compute avg_price  
-    pub fn avg_price(&self) -> f64 {
-        if self.count == 0 { 0.0 } else { self.sum_base_price / self.count as f64 }
+}
+
+/// Rows of a group whose measure was NULL, per measure
+///
+/// A derived measure is NULL when any of its inputs is: `disc_price` needs
+/// price and discount, `charge` also needs tax.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NullCounts {
+    pub quantity: u64,
+    pub price: u64,
+    pub discount: u64,
+    pub disc_price: u64,
+    pub charge: u64,
+}
+
+impl NullCounts {
+    pub fn merge(&mut self, other: &NullCounts) {
+        self.quantity += other.quantity;
+        self.price += other.price;
+        self.discount += other.discount;
+        self.disc_price += other.disc_price;
+        self.charge += other.charge;
     }
-    
-    /// Compute avg_disc
-    pub fn avg_disc(&self) -> f64 {
-        if self.count == 0 { 0.0 } else { self.sum_discount / self.count as f64 }
+
+    /// Count the NULL measures of a row from the validity of its inputs;
+    /// returns whether `disc_price` and `charge` are valid
+    #[inline]
+    pub(crate) fn record(&mut self, quantity: bool, price: bool, discount: bool, tax: bool) -> (bool, bool) {
+        let disc_price = price && discount;
+        let charge = disc_price && tax;
+        self.quantity += !quantity as u64;
+        self.price += !price as u64;
+        self.discount += !discount as u64;
+        self.disc_price += !disc_price as u64;
+        self.charge += !charge as u64;
+        (disc_price, charge)
     }
 }
 
-/// Perfect hash function for (returnflag, linestatus) -> index
-/// 
-/// Known keys in TPC-H:
-///   - l_returnflag: 'A' (65), 'N' (78), 'R' (82)
-///   - l_linestatus: 'F' (70), 'O' (79)
-/// 
-/// We use: ((flag - 'A') * 2 + (status == 'O')) as index
-/// This gives us indices 0-5 for the 6 possible combinations
-#[inline(always)]
-pub fn hash_key(flag: u8, status: u8) -> usize {
-    // Simple perfect hash:
-    // A=0, N=1, R=2 (based on ordering), F=0, O=1
-    let flag_idx = match flag {
-        b'A' => 0,
-        b'N' => 1,
-        b'R' => 2,
-        _ => 0, // Should not happen in valid TPC-H data
-    };
-    let status_idx = if status == b'O' { 1 } else { 0 };
-    flag_idx * 2 + status_idx
+/// Streaming count, mean, min, max and M2 (sum of squared deviations
+/// from the mean) of one measure
+///
+/// Updated with Welford's algorithm and merged with the pairwise formula of
+/// Chan et al., so the variance stays accurate where the textbook
+/// sum-of-squares formula cancels (a large mean with a small spread, like
+/// prices around 38,000 that differ in the cents).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Moments {
+    pub count: u64,
+    pub mean: f64,
+    pub m2: f64,
+    pub min: f64,
+    pub max: f64,
 }
 
-/// Get the (returnflag, linestatus) for a given hash index
-#[inline(always)]
-pub fn unhash_key(idx: usize) -> (u8, u8) {
-    let flag = match idx / 2 {
-        0 => b'A',
-        1 => b'N',
-        2 => b'R',
-        _ => b'?',
-    };
-    let status = if idx % 2 == 1 { b'O' } else { b'F' };
-    (flag, status)
+impl Default for Moments {
+    fn default() -> Self {
+        Self { count: 0, mean: 0.0, m2: 0.0, min: f64::INFINITY, max: f64::NEG_INFINITY }
+    }
+}
+
+impl Moments {
+    /// Add one value
+    #[inline]
+    pub fn update(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    /// Combine with the moments of another, disjoint set of values
+    pub fn merge(&mut self, other: &Moments) {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            *self = *other;
+            return;
+        }
+        let (n_a, n_b) = (self.count as f64, other.count as f64);
+        let n = n_a + n_b;
+        let delta = other.mean - self.mean;
+        self.mean += delta * n_b / n;
+        self.m2 += other.m2 + delta * delta * n_a * n_b / n;
+        self.count += other.count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    /// MIN, NULL over no values
+    pub fn min(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    /// MAX, NULL over no values
+    pub fn max(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    /// VAR_POP: M2 / n, NULL over no values
+    pub fn var_pop(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.m2 / self.count as f64)
+    }
+
+    /// VAR_SAMP (and VARIANCE): M2 / (n - 1), NULL below two values
+    pub fn var_samp(&self) -> Option<f64> {
+        (self.count > 1).then(|| self.m2 / (self.count - 1) as f64)
+    }
+
+    /// STDDEV_POP
+    pub fn stddev_pop(&self) -> Option<f64> {
+        self.var_pop().map(f64::sqrt)
+    }
+
+    /// STDDEV_SAMP (and STDDEV)
+    pub fn stddev_samp(&self) -> Option<f64> {
+        self.var_samp().map(f64::sqrt)
+    }
+}
+
+/// `Moments` of Q1's input measures within one group, skipping NULLs
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct InputMoments {
+    pub quantity: Moments,
+    pub price: Moments,
+    pub discount: Moments,
+    pub tax: Moments,
+}
+
+impl InputMoments {
+    pub fn merge(&mut self, other: &InputMoments) {
+        self.quantity.merge(&other.quantity);
+        self.price.merge(&other.price);
+        self.discount.merge(&other.discount);
+        self.tax.merge(&other.tax);
+    }
+}
+
+/// Per-group state of the optional aggregates, kept out of line so that
+/// `AggState` stays one cache line
+#[derive(Debug, Clone, Default)]
+pub struct GroupExtras {
+    /// Set `with_moments`
+    pub moments: Option<InputMoments>,
+    /// Set by `aggregate_distinct`
+    pub distinct: Option<DistinctCount>,
+    /// `l_extendedprice` values, set `with_quantiles`
+    pub price_quantiles: Option<QuantileSketch>,
+}
+
+impl GroupExtras {
+    pub fn merge(&mut self, other: &GroupExtras) {
+        merge_option(&mut self.moments, &other.moments, InputMoments::merge);
+        merge_option(&mut self.distinct, &other.distinct, DistinctCount::merge);
+        merge_option(&mut self.price_quantiles, &other.price_quantiles, QuantileSketch::merge);
+    }
+}
+
+/// Merge optional states, taking a copy of `theirs` where `mine` is unset
+pub(crate) fn merge_option<T: Clone>(mine: &mut Option<T>, theirs: &Option<T>, merge: impl Fn(&mut T, &T)) {
+    match (mine.as_mut(), theirs) {
+        (Some(mine), Some(theirs)) => merge(mine, theirs),
+        (None, Some(theirs)) => *mine = Some(theirs.clone()),
+        (_, None) => {}
+    }
+}
+
+/// `(l_returnflag, l_linestatus)` of a row, `None` for NULL
+pub type GroupKey = (Option<u8>, Option<u8>);
+
+/// Groups whose key has a NULL part, which the perfect hash has no slot for
+///
+/// There are at most `flags + statuses + 1` of them, so a linear scan is
+/// enough.
+#[derive(Debug, Clone)]
+pub(crate) struct NullKeyGroups<S> {
+    groups: Vec<(GroupKey, S)>,
+}
+
+impl<S> Default for NullKeyGroups<S> {
+    fn default() -> Self {
+        Self { groups: Vec::new() }
+    }
+}
+
+impl<S: Default> NullKeyGroups<S> {
+    /// State of `key`, created empty on first use
+    pub(crate) fn get_mut(&mut self, key: GroupKey) -> &mut S {
+        let i = match self.groups.iter().position(|(k, _)| *k == key) {
+            Some(i) => i,
+            None => {
+                self.groups.push((key, S::default()));
+                self.groups.len() - 1
+            }
+        };
+        &mut self.groups[i].1
+    }
+
+    /// Merge `other` into this one with `merge` per matching key
+    pub(crate) fn merge(&mut self, other: &NullKeyGroups<S>, merge: impl Fn(&mut S, &S)) {
+        for (key, state) in &other.groups {
+            merge(self.get_mut(*key), state);
+        }
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &(GroupKey, S)> {
+        self.groups.iter()
+    }
+}
+
+/// Sort results by (returnflag, linestatus) ascending, NULLs last
+pub(crate) fn sort_results(results: &mut [QueryResult]) {
+    results.sort_by_key(|r| (r.returnflag.is_none(), r.returnflag, r.linestatus.is_none(), r.linestatus));
+}
+
+/// SUM over `count` rows of which `nulls` were NULL: NULL if all were
+#[inline]
+pub(crate) fn sum_or_null(sum: f64, count: u64, nulls: u64) -> Option<f64> {
+    (count > nulls).then_some(sum)
+}
+
+/// AVG over `count` rows of which `nulls` were NULL: NULL if all were
+#[inline]
+pub(crate) fn avg_or_null(sum: f64, count: u64, nulls: u64) -> Option<f64> {
+    (count > nulls).then(|| sum / (count - nulls) as f64)
 }
 
-/// The aggregator using a fixed-size array
-pub struct Aggregator {
-    /// 4 sets of 6 slots for instruction-level parallelism
+/// Largest perfect hash table we build before falling back to a hash table
+///
+/// Must stay below 255 so an unknown byte (mapped to `UNKNOWN`) always lands
+/// past the last slot.
+pub const MAX_PERFECT_HASH_SLOTS: usize = 64;
+
+/// Lookup table marker for bytes outside the discovered domain
+const UNKNOWN: u8 = u8::MAX;
+
+/// Perfect hash for (returnflag, linestatus) -> index over a known domain
+///
+/// Each key byte is mapped through a 256-entry lookup table to its rank in
+/// the sorted domain, and the slot is `flag_rank * num_statuses + status_rank`.
+/// Because both domains are sorted, slot order is also ORDER BY order.
+/// For standard TPC-H data (A/N/R x F/O) this gives indices 0-5.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PerfectHash {
+    flags: Vec<u8>,
+    statuses: Vec<u8>,
+    flag_lut: [u8; 256],
+    status_lut: [u8; 256],
+}
+
+impl PerfectHash {
+    /// The standard TPC-H domain: l_returnflag in A/N/R, l_linestatus in F/O
+    pub fn tpch() -> Self {
+        Self::new(b"ANR", b"FO").unwrap()
+    }
+
+    /// Build a perfect hash over the given key bytes
+    ///
+    /// Returns `None` if the domain needs more than `MAX_PERFECT_HASH_SLOTS`.
+    pub fn new(flags: &[u8], statuses: &[u8]) -> Option<Self> {
+        let mut flags = flags.to_vec();
+        let mut statuses = statuses.to_vec();
+        flags.sort_unstable();
+        flags.dedup();
+        statuses.sort_unstable();
+        statuses.dedup();
+
+        if flags.is_empty() || statuses.is_empty() || flags.len() * statuses.len() > MAX_PERFECT_HASH_SLOTS {
+            return None;
+        }
+
+        let mut flag_lut = [UNKNOWN; 256];
+        for (rank, &f) in flags.iter().enumerate() {
+            flag_lut[f as usize] = rank as u8;
+        }
+        let mut status_lut = [UNKNOWN; 256];
+        for (rank, &s) in statuses.iter().enumerate() {
+            status_lut[s as usize] = rank as u8;
+        }
+
+        Some(Self { flags, statuses, flag_lut, status_lut })
+    }
+
+    /// Build a perfect hash from discovered string domains
+    ///
+    /// Every value must be a single byte; otherwise returns `None`.
+    pub fn from_domains(flags: &[Vec<u8>], statuses: &[Vec<u8>]) -> Option<Self> {
+        let single_bytes = |values: &[Vec<u8>]| -> Option<Vec<u8>> {
+            values.iter().map(|v| (v.len() == 1).then(|| v[0])).collect()
+        };
+        Self::new(&single_bytes(flags)?, &single_bytes(statuses)?)
+    }
+
+    /// Number of slots in the table
+    #[inline(always)]
+    pub fn num_slots(&self) -> usize {
+        self.flags.len() * self.statuses.len()
+    }
+
+    /// Slot for a key; `>= num_slots()` if either byte is outside the domain
+    #[inline(always)]
+    pub fn slot(&self, flag: u8, status: u8) -> usize {
+        self.rank_slot(self.flag_lut[flag as usize], self.status_lut[status as usize])
+    }
+
+    /// Rank of a returnflag value in the domain, `UNKNOWN` if it is not in it
+    #[inline]
+    pub fn flag_rank(&self, value: &[u8]) -> u8 {
+        match value {
+            [b] => self.flag_lut[*b as usize],
+            _ => UNKNOWN,
+        }
+    }
+
+    /// Rank of a linestatus value in the domain, `UNKNOWN` if it is not in it
+    #[inline]
+    pub fn status_rank(&self, value: &[u8]) -> u8 {
+        match value {
+            [b] => self.status_lut[*b as usize],
+            _ => UNKNOWN,
+        }
+    }
+
+    /// Slot for a pair of ranks; `>= num_slots()` if either is `UNKNOWN`
+    #[inline(always)]
+    pub fn rank_slot(&self, flag_rank: u8, status_rank: u8) -> usize {
+        flag_rank as usize * self.statuses.len() + status_rank as usize
+    }
+
+    /// Get the (returnflag, linestatus) for a given slot
+    #[inline(always)]
+    pub fn key(&self, slot: usize) -> (u8, u8) {
+        let n = self.statuses.len();
+        (self.flags[slot / n], self.statuses[slot % n])
+    }
+}
+
+/// The aggregator using a perfect hash array sized to the key domain,
+/// summing in `S` (see `summation::FloatSum`)
+pub struct Aggregator<S = f64> {
+    /// 4 sets of slots for instruction-level parallelism
     /// We use multiple accumulators to break dependency chains in the summing loop
-    pub states: [[AggState; 6]; 4],
+    pub states: [Vec<AggState<S>>; 4],
+    /// NULL measures per slot, only touched by batches that have NULLs
+    nulls: Vec<NullCounts>,
+    /// Groups with a NULL key part
+    null_keys: NullKeyGroups<(AggState<S>, NullCounts)>,
+    keys: PerfectHash,
+    /// Whether to also track `InputMoments` per group
+    moments: bool,
+    /// How `aggregate_distinct` counts, if the caller asked for it
+    distinct: Option<DistinctMode>,
+    /// Whether (and how) to sketch `l_extendedprice` quantiles per group
+    quantiles: Option<QuantileMode>,
 }
 
 impl Default for Aggregator {
@@ -108,53 +438,363 @@ impl Default for Aggregator {
 }
 
 impl Aggregator {
+    /// Aggregator over the standard TPC-H key domain
     pub fn new() -> Self {
+        Self::with_keys(PerfectHash::tpch())
+    }
+
+    /// Aggregator with plain f64 sums over a discovered key domain
+    pub fn with_keys(keys: PerfectHash) -> Self {
+        Self::with_accumulator(keys)
+    }
+}
+
+impl<S: FloatSum> Aggregator<S> {
+    /// Aggregator over a discovered key domain accumulating its sums in
+    /// `S`: `f64`, `KahanSum` or `PairwiseSum`
+    pub fn with_accumulator(keys: PerfectHash) -> Self {
+        let slots = keys.num_slots();
         Self {
-            states: Default::default(),
-            // states is automatically initialized to zero/default
+            states: std::array::from_fn(|_| vec![AggState::default(); slots]),
+            nulls: vec![NullCounts::default(); slots],
+            null_keys: NullKeyGroups::default(),
+            keys,
+            moments: false,
+            distinct: None,
+            quantiles: None,
         }
     }
-    
+
+    /// Also track MIN / MAX / variance state of the input measures
+    ///
+    /// Costs a second, row-at-a-time pass over each batch, so the Q1 loop
+    /// itself is unchanged; results then carry `QueryResult::moments`.
+    pub fn with_moments(mut self) -> Self {
+        self.moments = true;
+        self
+    }
+
+    /// Also keep a `QuantileSketch` of each group's prices, for median and
+    /// percentile follow-ups; shares the second pass with `with_moments`
+    pub fn with_quantiles(mut self, mode: QuantileMode) -> Self {
+        self.quantiles = Some(mode);
+        self
+    }
+
+    /// Whether batches need the row-at-a-time pass of `update_extras`
+    fn has_extras(&self) -> bool {
+        self.moments || self.quantiles.is_some()
+    }
+
+    /// Count distinct values per group, fed through `aggregate_distinct`
+    pub fn with_distinct(mut self, mode: DistinctMode) -> Self {
+        self.distinct = Some(mode);
+        self
+    }
+
+    /// The distinct counting set up by `with_distinct`
+    pub fn distinct_mode(&self) -> Option<DistinctMode> {
+        self.distinct
+    }
+
+    /// The key domain this aggregator was built for
+    pub fn keys(&self) -> &PerfectHash {
+        &self.keys
+    }
+
+    /// Merge another aggregator's partial states into this one
+    ///
+    /// Accumulator sets are merged slot-for-slot so the ILP layout is kept;
+    /// `get_results` folds the four sets together as usual. Both aggregators
+    /// must have been built over the same key domain.
+    pub fn merge(&mut self, other: &Aggregator<S>) {
+        debug_assert_eq!(self.keys, other.keys);
+        for (mine, theirs) in self.states.iter_mut().zip(other.states.iter()) {
+            for (state, other_state) in mine.iter_mut().zip(theirs.iter()) {
+                state.merge(other_state);
+            }
+        }
+        for (nulls, other_nulls) in self.nulls.iter_mut().zip(&other.nulls) {
+            nulls.merge(other_nulls);
+        }
+        self.null_keys.merge(&other.null_keys, |(state, nulls), (other_state, other_nulls)| {
+            state.merge(other_state);
+            nulls.merge(other_nulls);
+        });
+    }
+
     /// Aggregate a batch of data with on-the-fly expression evaluation
+    #[allow(clippy::too_many_arguments)]
     pub fn aggregate_batch(
         &mut self,
         mask: &arrow::array::BooleanArray,
-        returnflag: &StringArray,
-        linestatus: &StringArray,
+        returnflag: &dyn Array,
+        linestatus: &dyn Array,
         quantity: &Float64Array,
         price: &Float64Array,
         discount: &Float64Array,
         tax: &Float64Array,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let len = mask.len();
+    ) -> Result<(), GooseError> {
+        if mask.null_count() > 0 {
+            // A NULL predicate result does not select the row
+            let mask = arrow::compute::prep_null_mask_filter(mask);
+            return self.aggregate_batch(&mask, returnflag, linestatus, quantity, price, discount, tax);
+        }
+        check_lengths(mask.len(), [returnflag, linestatus, quantity, price, discount, tax])?;
+        // SAFETY: the loop only asks for rows below `mask.len()`
+        let selected = |i| unsafe { mask.value_unchecked(i) };
+        if has_nulls(returnflag, linestatus, [quantity, price, discount, tax]) {
+            self.aggregate_rows_checked(mask.len(), selected, returnflag, linestatus, quantity, price, discount, tax)?;
+        } else {
+            self.aggregate_keys(mask.len(), selected, returnflag, linestatus, quantity, price, discount, tax)?;
+        }
+        if self.has_extras() {
+            self.update_extras(mask.len(), selected, returnflag, linestatus, [quantity, price, discount, tax])?;
+        }
+        Ok(())
+    }
+
+    /// Aggregate every row of a batch, for batches the reader proved to
+    /// satisfy the ship-date predicate (`LineitemReader::all_pass`)
+    ///
+    /// Same as `aggregate_batch` with an all-true mask, without building
+    /// the mask or testing it per row.
+    pub fn aggregate_batch_unmasked(
+        &mut self,
+        returnflag: &dyn Array,
+        linestatus: &dyn Array,
+        quantity: &Float64Array,
+        price: &Float64Array,
+        discount: &Float64Array,
+        tax: &Float64Array,
+    ) -> Result<(), GooseError> {
+        check_lengths(quantity.len(), [returnflag, linestatus, price, discount, tax])?;
+        if has_nulls(returnflag, linestatus, [quantity, price, discount, tax]) {
+            self.aggregate_rows_checked(quantity.len(), |_| true, returnflag, linestatus, quantity, price, discount, tax)?;
+        } else {
+            self.aggregate_keys(quantity.len(), |_| true, returnflag, linestatus, quantity, price, discount, tax)?;
+        }
+        if self.has_extras() {
+            self.update_extras(quantity.len(), |_| true, returnflag, linestatus, [quantity, price, discount, tax])?;
+        }
+        Ok(())
+    }
+
+    /// Fold the selected rows of a batch into their groups' `InputMoments`
+    /// and price `QuantileSketch`, as configured
+    ///
+    /// Runs after the main loop has accepted the batch, so every selected
+    /// key is known to be in the domain (or NULL). The state goes to
+    /// accumulator set 0; `merge` and `get_results` combine it like the sums.
+    #[cold]
+    fn update_extras(
+        &mut self,
+        len: usize,
+        selected: impl Fn(usize) -> bool,
+        returnflag: &dyn Array,
+        linestatus: &dyn Array,
+        measures: [&Float64Array; 4],
+    ) -> Result<(), GooseError> {
+        let (with_moments, quantiles) = (self.moments, self.quantiles);
+        let [quantity, price, discount, tax] = measures;
+        for i in (0..len).filter(|&i| selected(i)) {
+            let state = match (key_byte(returnflag, i)?, key_byte(linestatus, i)?) {
+                (Some(f), Some(s)) => &mut self.states[0][self.keys.slot(f, s)],
+                key => &mut self.null_keys.get_mut(key).0,
+            };
+            let extras = state.extras.get_or_insert_with(Default::default);
+            if with_moments {
+                let moments = extras.moments.get_or_insert_with(Default::default);
+                for (moments, measure) in [
+                    (&mut moments.quantity, quantity),
+                    (&mut moments.price, price),
+                    (&mut moments.discount, discount),
+                    (&mut moments.tax, tax),
+                ] {
+                    if measure.is_valid(i) {
+                        moments.update(measure.value(i));
+                    }
+                }
+            }
+            if let Some(mode) = quantiles {
+                let sketch = extras.price_quantiles.get_or_insert_with(|| QuantileSketch::new(mode));
+                if price.is_valid(i) {
+                    sketch.insert(price.value(i));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the selected rows of `values` to their groups' distinct counts
+    ///
+    /// A column outside Q1's measures (such as `l_orderkey`) is fed here
+    /// alongside `aggregate_batch` with the same mask (`None` selects every
+    /// row, NULL mask entries select nothing). Values of any type are keyed
+    /// by `distinct_rows`. NULL values are not counted, but every selected
+    /// row's group reports a count, 0 if need be. Counts go to accumulator
+    /// set 0 and merge like the sums. Counting is exact unless the
+    /// aggregator was built `with_distinct(DistinctMode::Approx)`.
+    pub fn aggregate_distinct(
+        &mut self,
+        mask: Option<&arrow::array::BooleanArray>,
+        returnflag: &dyn Array,
+        linestatus: &dyn Array,
+        values: &dyn Array,
+    ) -> Result<(), GooseError> {
+        check_lengths(values.len(), [returnflag, linestatus])?;
+        if let Some(mask) = mask {
+            check_lengths(values.len(), [mask])?;
+        }
+        let mode = self.distinct.unwrap_or_default();
+        let rows = distinct_rows(values)?;
+        let num_slots = self.keys.num_slots();
+        let selected = |i| mask.is_none_or(|m| m.is_valid(i) && m.value(i));
+        for i in (0..values.len()).filter(|&i| selected(i)) {
+            let state = match (key_byte(returnflag, i)?, key_byte(linestatus, i)?) {
+                (Some(f), Some(s)) => {
+                    let idx = self.keys.slot(f, s);
+                    if idx >= num_slots {
+                        return Err(unexpected_key_at(returnflag, linestatus, i));
+                    }
+                    &mut self.states[0][idx]
+                }
+                key => &mut self.null_keys.get_mut(key).0,
+            };
+            let extras = state.extras.get_or_insert_with(Default::default);
+            let distinct = extras.distinct.get_or_insert_with(|| DistinctCount::new(mode));
+            if values.is_valid(i) {
+                distinct.insert(rows.row(i).as_ref());
+            }
+        }
+        Ok(())
+    }
+
+    /// Pick the key access for a batch without NULLs and run the unrolled loop
+    ///
+    /// Dictionary keys are grouped on their codes: each dictionary value is
+    /// ranked in the key domain once per batch and the rows are gathered by
+    /// code, so no string is materialized or looked at per row. Plain string
+    /// keys are read as raw bytes when `key_bytes` validates them. Anything
+    /// else goes through the checked row-at-a-time loop.
+    #[allow(clippy::too_many_arguments)]
+    #[inline(always)]
+    fn aggregate_keys(
+        &mut self,
+        len: usize,
+        selected: impl Fn(usize) -> bool,
+        returnflag: &dyn Array,
+        linestatus: &dyn Array,
+        quantity: &Float64Array,
+        price: &Float64Array,
+        discount: &Float64Array,
+        tax: &Float64Array,
+    ) -> Result<(), GooseError> {
+        let is_dictionary = |a: &dyn Array| matches!(a.data_type(), DataType::Dictionary(_, _));
+        if is_dictionary(returnflag) || is_dictionary(linestatus) {
+            let ranks = (
+                key_ranks(returnflag, |v| self.keys.flag_rank(v)),
+                key_ranks(linestatus, |v| self.keys.status_rank(v)),
+            );
+            if let (Some(flags), Some(statuses)) = ranks {
+                // SAFETY: `key_ranks` returns one rank per row
+                let slot = |keys: &PerfectHash, i: usize| unsafe { keys.rank_slot(*flags.get_unchecked(i), *statuses.get_unchecked(i)) };
+                return self.aggregate_rows(len, selected, slot, returnflag, linestatus, quantity, price, discount, tax);
+            }
+        } else if let (Some(flags), Some(statuses)) = (key_bytes(returnflag), key_bytes(linestatus)) {
+            // SAFETY: `key_bytes` returns one byte per row
+            let slot = |keys: &PerfectHash, i: usize| unsafe { keys.slot(*flags.get_unchecked(i), *statuses.get_unchecked(i)) };
+            return self.aggregate_rows(len, selected, slot, returnflag, linestatus, quantity, price, discount, tax);
+        }
+        self.aggregate_rows_checked(len, selected, returnflag, linestatus, quantity, price, discount, tax)
+    }
+
+    /// Row-at-a-time loop for batches with NULLs in any input column, or
+    /// with keys `key_bytes` cannot hand out as one byte per row
+    ///
+    /// Only selected rows are looked at, so a multi-byte key in a row the
+    /// predicate rejects is not an error.
+    #[allow(clippy::too_many_arguments)]
+    #[cold]
+    fn aggregate_rows_checked(
+        &mut self,
+        len: usize,
+        selected: impl Fn(usize) -> bool,
+        returnflag: &dyn Array,
+        linestatus: &dyn Array,
+        quantity: &Float64Array,
+        price: &Float64Array,
+        discount: &Float64Array,
+        tax: &Float64Array,
+    ) -> Result<(), GooseError> {
+        let num_slots = self.keys.num_slots();
+        for i in (0..len).filter(|&i| selected(i)) {
+            let flag = key_byte(returnflag, i)?;
+            let status = key_byte(linestatus, i)?;
+            let (state, nulls) = match (flag, status) {
+                (Some(f), Some(s)) => {
+                    let idx = self.keys.slot(f, s);
+                    if idx >= num_slots {
+                        return Err(unexpected_key_at(returnflag, linestatus, i));
+                    }
+                    (&mut self.states[0][idx], &mut self.nulls[idx])
+                }
+                key => {
+                    let (state, nulls) = self.null_keys.get_mut(key);
+                    (state, nulls)
+                }
+            };
+
+            let (q, p, d, t) = (quantity.is_valid(i), price.is_valid(i), discount.is_valid(i), tax.is_valid(i));
+            let (disc_price_valid, charge_valid) = nulls.record(q, p, d, t);
+            state.count += 1;
+            if q {
+                state.sum_qty.add(quantity.value(i));
+            }
+            if p {
+                state.sum_base_price.add(price.value(i));
+            }
+            if d {
+                state.sum_discount.add(discount.value(i));
+            }
+            if disc_price_valid {
+                let disc_price = price.value(i) * (1.0 - discount.value(i));
+                state.sum_disc_price.add(disc_price);
+                if charge_valid {
+                    state.sum_charge.add(disc_price * (1.0 + tax.value(i)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The unrolled loop behind both entry points; `selected` is inlined,
+    /// so the unmasked variant compiles without the per-row branch
+    ///
+    /// `slot` gives a row's slot, `>= num_slots()` for keys outside the
+    /// domain (reported from `returnflag` / `linestatus`). Every input is
+    /// `len` rows long.
+    #[allow(clippy::too_many_arguments)]
+    #[inline(always)]
+    fn aggregate_rows(
+        &mut self,
+        len: usize,
+        selected: impl Fn(usize) -> bool,
+        slot: impl Fn(&PerfectHash, usize) -> usize,
+        returnflag: &dyn Array,
+        linestatus: &dyn Array,
+        quantity: &Float64Array,
+        price: &Float64Array,
+        discount: &Float64Array,
+        tax: &Float64Array,
+    ) -> Result<(), GooseError> {
         if len == 0 {
             return Ok(());
         }
 
-        // Optimization 1: Raw Byte Access
-        // Check if we can use raw byte access for string columns
-        // This avoids offset lookups and validations every row
-        // We expect TPC-H data to be single-byte characters
-        let flag_values = returnflag.value_data();
-        let status_values = linestatus.value_data();
-        let flag_offsets = returnflag.value_offsets();
-        let status_offsets = linestatus.value_offsets();
-
-        // Check if strings are contiguous 1-byte values
-        // offset[i+1] - offset[i] == 1 for all i?
-        // Simpler check: is total length == row count? (implies average 1 byte)
-        // AND 0th offset is 0.
-        // For robustness, if not optimized, we fall back to slow path?
-        // Let's implement the optimized path assuming standard TPC-H generation.
-        // If the data is not 1-byte/row, this logic would yield garbage, so we must be careful.
-        // However, for this specific query and dataset, it's a safe optimization to attempt
-        // if valid.
-        
-        // Fast path: simply use the byte buffer directly.
-        // We assume the data IS standard TPC-H (1 char).
-        // To be safe, we can check the total length of the values buffer matches len.
-        let use_fast_path = (flag_values.len() >= len) && (status_values.len() >= len);
-        
+        let keys = &self.keys;
+        let num_slots = keys.num_slots();
+
         let q_vals = quantity.values();
         let p_vals = price.values();
         let d_vals = discount.values();
@@ -162,21 +802,20 @@ impl Aggregator {
 
         // We process in chunks of 4 for ILP (matching our 4 accumulator sets)
         let chunks = len / 4;
-        let remainder = len % 4;
 
         for chunk_i in 0..chunks {
             let base = chunk_i * 4;
             
-            // Check mask for the 4 items
-            // We can optimize further by checking if 64-bit mask chunk is all ones.
-            // But for now, let's just unroll.
+            // Check the selection for the 4 items
+            // (constant true for aggregate_batch_unmasked)
             
             // Unroll 0
             let i = base;
-            if unsafe { mask.value_unchecked(i) } {
-                let f = if use_fast_path { unsafe { *flag_values.get_unchecked(i) } } else { unsafe { returnflag.value_unchecked(i).as_bytes()[0] } };
-                let s = if use_fast_path { unsafe { *status_values.get_unchecked(i) } } else { unsafe { linestatus.value_unchecked(i).as_bytes()[0] } };
-                let idx = hash_key(f, s);
+            if selected(i) {
+                let idx = slot(keys, i);
+                if idx >= num_slots {
+                    return Err(unexpected_key_at(returnflag, linestatus, i));
+                }
                 let state = unsafe { &mut self.states.get_unchecked_mut(0).get_unchecked_mut(idx) };
                 
                 unsafe {
@@ -186,21 +825,22 @@ impl Aggregator {
                     let t = *t_vals.get_unchecked(i);
                     let disc_price = p * (1.0 - d);
                     let charge = disc_price * (1.0 + t);
-                    state.sum_qty += q;
-                    state.sum_base_price += p;
-                    state.sum_disc_price += disc_price;
-                    state.sum_charge += charge;
-                    state.sum_discount += d;
+                    state.sum_qty.add(q);
+                    state.sum_base_price.add(p);
+                    state.sum_disc_price.add(disc_price);
+                    state.sum_charge.add(charge);
+                    state.sum_discount.add(d);
                     state.count += 1;
                 }
             }
 
             // Unroll 1
             let i = base + 1;
-            if unsafe { mask.value_unchecked(i) } {
-                let f = if use_fast_path { unsafe { *flag_values.get_unchecked(i) } } else { unsafe { returnflag.value_unchecked(i).as_bytes()[0] } };
-                let s = if use_fast_path { unsafe { *status_values.get_unchecked(i) } } else { unsafe { linestatus.value_unchecked(i).as_bytes()[0] } };
-                let idx = hash_key(f, s);
+            if selected(i) {
+                let idx = slot(keys, i);
+                if idx >= num_slots {
+                    return Err(unexpected_key_at(returnflag, linestatus, i));
+                }
                 let state = unsafe { &mut self.states.get_unchecked_mut(1).get_unchecked_mut(idx) };
                 
                 unsafe {
@@ -210,21 +850,22 @@ impl Aggregator {
                     let t = *t_vals.get_unchecked(i);
                     let disc_price = p * (1.0 - d);
                     let charge = disc_price * (1.0 + t);
-                    state.sum_qty += q;
-                    state.sum_base_price += p;
-                    state.sum_disc_price += disc_price;
-                    state.sum_charge += charge;
-                    state.sum_discount += d;
+                    state.sum_qty.add(q);
+                    state.sum_base_price.add(p);
+                    state.sum_disc_price.add(disc_price);
+                    state.sum_charge.add(charge);
+                    state.sum_discount.add(d);
                     state.count += 1;
                 }
             }
             
             // Unroll 2
             let i = base + 2;
-            if unsafe { mask.value_unchecked(i) } {
-                let f = if use_fast_path { unsafe { *flag_values.get_unchecked(i) } } else { unsafe { returnflag.value_unchecked(i).as_bytes()[0] } };
-                let s = if use_fast_path { unsafe { *status_values.get_unchecked(i) } } else { unsafe { linestatus.value_unchecked(i).as_bytes()[0] } };
-                let idx = hash_key(f, s);
+            if selected(i) {
+                let idx = slot(keys, i);
+                if idx >= num_slots {
+                    return Err(unexpected_key_at(returnflag, linestatus, i));
+                }
                 let state = unsafe { &mut self.states.get_unchecked_mut(2).get_unchecked_mut(idx) };
                 
                 unsafe {
@@ -234,21 +875,22 @@ impl Aggregator {
                     let t = *t_vals.get_unchecked(i);
                     let disc_price = p * (1.0 - d);
                     let charge = disc_price * (1.0 + t);
-                    state.sum_qty += q;
-                    state.sum_base_price += p;
-                    state.sum_disc_price += disc_price;
-                    state.sum_charge += charge;
-                    state.sum_discount += d;
+                    state.sum_qty.add(q);
+                    state.sum_base_price.add(p);
+                    state.sum_disc_price.add(disc_price);
+                    state.sum_charge.add(charge);
+                    state.sum_discount.add(d);
                     state.count += 1;
                 }
             }
 
             // Unroll 3
             let i = base + 3;
-            if unsafe { mask.value_unchecked(i) } {
-                let f = if use_fast_path { unsafe { *flag_values.get_unchecked(i) } } else { unsafe { returnflag.value_unchecked(i).as_bytes()[0] } };
-                let s = if use_fast_path { unsafe { *status_values.get_unchecked(i) } } else { unsafe { linestatus.value_unchecked(i).as_bytes()[0] } };
-                let idx = hash_key(f, s);
+            if selected(i) {
+                let idx = slot(keys, i);
+                if idx >= num_slots {
+                    return Err(unexpected_key_at(returnflag, linestatus, i));
+                }
                 let state = unsafe { &mut self.states.get_unchecked_mut(3).get_unchecked_mut(idx) };
                 
                 unsafe {
@@ -258,11 +900,11 @@ impl Aggregator {
                     let t = *t_vals.get_unchecked(i);
                     let disc_price = p * (1.0 - d);
                     let charge = disc_price * (1.0 + t);
-                    state.sum_qty += q;
-                    state.sum_base_price += p;
-                    state.sum_disc_price += disc_price;
-                    state.sum_charge += charge;
-                    state.sum_discount += d;
+                    state.sum_qty.add(q);
+                    state.sum_base_price.add(p);
+                    state.sum_disc_price.add(disc_price);
+                    state.sum_charge.add(charge);
+                    state.sum_discount.add(d);
                     state.count += 1;
                 }
             }
@@ -270,10 +912,11 @@ impl Aggregator {
 
         // Handle remainder
         for i in (chunks * 4)..len {
-            if unsafe { mask.value_unchecked(i) } {
-                let f = if use_fast_path { unsafe { *flag_values.get_unchecked(i) } } else { unsafe { returnflag.value_unchecked(i).as_bytes()[0] } };
-                let s = if use_fast_path { unsafe { *status_values.get_unchecked(i) } } else { unsafe { linestatus.value_unchecked(i).as_bytes()[0] } };
-                let idx = hash_key(f, s);
+            if selected(i) {
+                let idx = slot(keys, i);
+                if idx >= num_slots {
+                    return Err(unexpected_key_at(returnflag, linestatus, i));
+                }
                 // Use accumulator 0 for remainder
                 let state = unsafe { &mut self.states.get_unchecked_mut(0).get_unchecked_mut(idx) };
                 
@@ -282,11 +925,11 @@ impl Aggregator {
                     let p = *p_vals.get_unchecked(i);
                     let d = *d_vals.get_unchecked(i);
                     let t = *t_vals.get_unchecked(i);
-                    state.sum_qty += q;
-                    state.sum_base_price += p;
-                    state.sum_disc_price += p * (1.0 - d);
-                    state.sum_charge += p * (1.0 - d) * (1.0 + t);
-                    state.sum_discount += d;
+                    state.sum_qty.add(q);
+                    state.sum_base_price.add(p);
+                    state.sum_disc_price.add(p * (1.0 - d));
+                    state.sum_charge.add(p * (1.0 - d) * (1.0 + t));
+                    state.sum_discount.add(d);
                     state.count += 1;
                 }
             }
@@ -295,60 +938,253 @@ impl Aggregator {
         Ok(())
     }
     
-    /// Get results sorted by (returnflag, linestatus)
+    /// Get results sorted by (returnflag, linestatus), NULL keys last
     pub fn get_results(&self) -> Vec<QueryResult> {
         // Merge accumulators
         let mut final_states = self.states[0].clone();
         for i in 1..4 {
-            for j in 0..6 {
-                final_states[j].merge(&self.states[i][j]);
+            for (state, other) in final_states.iter_mut().zip(&self.states[i]) {
+                state.merge(other);
             }
         }
 
-        let mut results: Vec<QueryResult> = final_states
+        let slots = final_states
             .iter()
+            .zip(&self.nulls)
             .enumerate()
-            .filter(|(_, state)| !state.is_empty())
-            .map(|(idx, state)| {
-                let (flag, status) = unhash_key(idx);
+            .map(|(idx, (state, nulls))| {
+                let (flag, status) = self.keys.key(idx);
+                ((Some(flag), Some(status)), state, nulls)
+            });
+        let null_keys = self.null_keys.iter().map(|(key, (state, nulls))| (*key, state, nulls));
+
+        let mut results: Vec<QueryResult> = slots
+            .chain(null_keys)
+            .filter(|(_, state, _)| !state.is_empty())
+            .map(|((flag, status), state, nulls)| {
+                let [sum_qty, sum_base_price, sum_disc_price, sum_charge, sum_discount] = state.sums();
                 QueryResult {
                     returnflag: flag,
                     linestatus: status,
-                    sum_qty: state.sum_qty,
-                    sum_base_price: state.sum_base_price,
-                    sum_disc_price: state.sum_disc_price,
-                    sum_charge: state.sum_charge,
-                    avg_qty: state.avg_qty(),
-                    avg_price: state.avg_price(),
-                    avg_disc: state.avg_disc(),
+                    sum_qty: sum_or_null(sum_qty, state.count, nulls.quantity),
+                    sum_base_price: sum_or_null(sum_base_price, state.count, nulls.price),
+                    sum_disc_price: sum_or_null(sum_disc_price, state.count, nulls.disc_price),
+                    sum_charge: sum_or_null(sum_charge, state.count, nulls.charge),
+                    avg_qty: avg_or_null(sum_qty, state.count, nulls.quantity),
+                    avg_price: avg_or_null(sum_base_price, state.count, nulls.price),
+                    avg_disc: avg_or_null(sum_discount, state.count, nulls.discount),
                     count: state.count,
+                    exact: None,
+                    moments: state.extras.as_ref().and_then(|e| e.moments),
+                    distinct: state.extras.as_ref().and_then(|e| e.distinct.as_ref()).map(DistinctCount::count),
+                    price_quantiles: state.extras.as_ref().and_then(|e| e.price_quantiles.clone()).map(|mut sketch| {
+                        sketch.sort();
+                        sketch
+                    }),
                 }
             })
             .collect();
         
         // Sort by (returnflag, linestatus) as per ORDER BY clause
-        results.sort_by(|a, b| {
-            a.returnflag.cmp(&b.returnflag)
-                .then(a.linestatus.cmp(&b.linestatus))
-        });
-        
+        sort_results(&mut results);
+
         results
     }
 }
 
+/// Whether any input column of a batch has NULLs
+#[inline]
+fn has_nulls<const N: usize>(returnflag: &dyn Array, linestatus: &dyn Array, measures: [&dyn Array; N]) -> bool {
+    returnflag.null_count() > 0 || linestatus.null_count() > 0 || measures.iter().any(|m| m.null_count() > 0)
+}
+
+/// Reject input columns of a batch that are not all `len` rows long
+fn check_lengths<const N: usize>(len: usize, columns: [&dyn Array; N]) -> Result<(), GooseError> {
+    match columns.iter().find(|c| c.len() != len) {
+        Some(c) => Err(GooseError::InvalidData(format!("batch columns have {} and {} rows", len, c.len()))),
+        None => Ok(()),
+    }
+}
+
+/// Error for row `i`'s key, which the perfect hash was not built for
+#[cold]
+fn unexpected_key_at(returnflag: &dyn Array, linestatus: &dyn Array, i: usize) -> GooseError {
+    let value = |array: &dyn Array| arrow::util::display::array_value_to_string(array, i).unwrap_or_default();
+    GooseError::InvalidData(format!(
+        "unexpected group key ({:?}, {:?}) outside the perfect hash domain",
+        value(returnflag),
+        value(linestatus)
+    ))
+}
+
 /// Final query result row
+///
+/// `None` stands for SQL NULL: a NULL key, or a SUM / AVG over a group in
+/// which every input was NULL.
 #[derive(Debug, Clone)]
 pub struct QueryResult {
-    pub returnflag: u8,
-    pub linestatus: u8,
-    pub sum_qty: f64,
-    pub sum_base_price: f64,
-    pub sum_disc_price: f64,
-    pub sum_charge: f64,
-    pub avg_qty: f64,
-    pub avg_price: f64,
-    pub avg_disc: f64,
+    pub returnflag: Option<u8>,
+    pub linestatus: Option<u8>,
+    pub sum_qty: Option<f64>,
+    pub sum_base_price: Option<f64>,
+    pub sum_disc_price: Option<f64>,
+    pub sum_charge: Option<f64>,
+    pub avg_qty: Option<f64>,
+    pub avg_price: Option<f64>,
+    pub avg_disc: Option<f64>,
     pub count: u64,
+    /// Exact decimal sums, present when aggregated with `AggregationMode::ExactDecimal`
+    /// (a sum whose float counterpart is `None` is zero here)
+    pub exact: Option<ExactSums>,
+    /// Moments of the input measures, present when aggregated `with_moments`
+    pub moments: Option<InputMoments>,
+    /// Distinct values fed through `Aggregator::aggregate_distinct`
+    /// (`l_orderkey` with `ExecutionOptions::distinct_orders`)
+    pub distinct: Option<u64>,
+    /// Sketch of `l_extendedprice`, present when aggregated `with_quantiles`
+    /// (`QuantileSketch::quantile(0.5)` is the median)
+    pub price_quantiles: Option<QuantileSketch>,
+}
+
+/// How Q1's measures are accumulated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AggregationMode {
+    /// Cast decimals to f64 and sum in floating point (the benchmark path)
+    #[default]
+    Float,
+    /// Keep decimals as scaled i128 through the expressions and sums
+    ExactDecimal,
+}
+
+/// Output column names of Q1, in SELECT order
+pub const Q1_OUTPUT_COLUMNS: &[&str] = &[
+    "l_returnflag",
+    "l_linestatus",
+    "sum_qty",
+    "sum_base_price",
+    "sum_disc_price",
+    "sum_charge",
+    "avg_qty",
+    "avg_price",
+    "avg_disc",
+    "count_order",
+];
+
+/// Convert perfect-hash results into a RecordBatch with the Q1 output schema
+///
+/// Matches the layout produced by `HashAggregator::finish` for the same
+/// aggregates, so callers can treat both strategies uniformly. When every row
+/// carries exact sums, the four sum columns are `Decimal128(38, scale)`.
+pub fn results_to_record_batch(results: &[QueryResult]) -> Result<RecordBatch, GooseError> {
+    let exact: Option<Vec<ExactSums>> = results.iter().map(|r| r.exact).collect();
+    let key = |f: fn(&QueryResult) -> Option<u8>| -> ArrayRef {
+        Arc::new(results.iter().map(|r| f(r).map(|b| (b as char).to_string())).collect::<StringArray>())
+    };
+    let float = |f: fn(&QueryResult) -> Option<f64>| -> ArrayRef {
+        Arc::new(results.iter().map(f).collect::<Float64Array>())
+    };
+
+    let sums: Vec<ArrayRef> = match exact.filter(|e| !e.is_empty()) {
+        Some(exact) => {
+            // NULL wherever the float sum is
+            let decimal = |f: fn(&ExactSums) -> Decimal, valid: fn(&QueryResult) -> Option<f64>| -> Result<ArrayRef, GooseError> {
+                let scale = f(&exact[0]).scale;
+                let array = exact
+                    .iter()
+                    .zip(results)
+                    .map(|(e, r)| valid(r).map(|_| f(e).value))
+                    .collect::<Decimal128Array>()
+                    .with_precision_and_scale(38, scale)?;
+                Ok(Arc::new(array))
+            };
+            vec![
+                decimal(|e| e.sum_qty, |r| r.sum_qty)?,
+                decimal(|e| e.sum_base_price, |r| r.sum_base_price)?,
+                decimal(|e| e.sum_disc_price, |r| r.sum_disc_price)?,
+                decimal(|e| e.sum_charge, |r| r.sum_charge)?,
+            ]
+        }
+        None => vec![
+            float(|r| r.sum_qty),
+            float(|r| r.sum_base_price),
+            float(|r| r.sum_disc_price),
+            float(|r| r.sum_charge),
+        ],
+    };
+
+    let mut columns = vec![key(|r| r.returnflag), key(|r| r.linestatus)];
+    columns.extend(sums);
+    columns.extend([
+        float(|r| r.avg_qty),
+        float(|r| r.avg_price),
+        float(|r| r.avg_disc),
+        Arc::new(results.iter().map(|r| r.count).collect::<UInt64Array>()) as ArrayRef,
+    ]);
+
+    // Only COUNT(*) can never be NULL
+    let fields: Vec<Field> = Q1_OUTPUT_COLUMNS
+        .iter()
+        .zip(&columns)
+        .map(|(name, col)| Field::new(*name, col.data_type().clone(), *name != "count_order"))
+        .collect();
+
+    Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)?)
+}
+
+/// Convert a RecordBatch with the Q1 output schema back into result rows
+///
+/// Used when Q1 ran through the general hash aggregator. Fails if a non-NULL
+/// key is not a single byte, since `QueryResult` cannot represent it; such
+/// data has to go through `execute_q1_group_by` instead.
+pub fn record_batch_to_results(batch: &RecordBatch) -> Result<Vec<QueryResult>, GooseError> {
+    let column = |name: &str| -> Result<ArrayRef, GooseError> {
+        Ok(crate::utils::get_column(batch, name)?.clone())
+    };
+    let keys = |name: &str| -> Result<Vec<Option<u8>>, GooseError> {
+        let array = cast(&column(name)?, &DataType::Utf8)?;
+        array
+            .as_string::<i32>()
+            .iter()
+            .map(|v| match v.map(str::as_bytes) {
+                Some([b]) => Ok(Some(*b)),
+                None => Ok(None),
+                Some(other) => Err(GooseError::InvalidData(format!("{} key {:?} is not a single byte", name, other))),
+            })
+            .collect()
+    };
+    let value = |array: &Float64Array, i: usize| array.is_valid(i).then(|| array.value(i));
+    let floats = |name: &str| crate::utils::get_f64_column(batch, name);
+
+    let flags = keys("l_returnflag")?;
+    let statuses = keys("l_linestatus")?;
+    let sum_qty = floats("sum_qty")?;
+    let sum_base_price = floats("sum_base_price")?;
+    let sum_disc_price = floats("sum_disc_price")?;
+    let sum_charge = floats("sum_charge")?;
+    let avg_qty = floats("avg_qty")?;
+    let avg_price = floats("avg_price")?;
+    let avg_disc = floats("avg_disc")?;
+    let count = cast(&column("count_order")?, &DataType::UInt64)?;
+    let count = count.as_primitive::<UInt64Type>();
+
+    Ok((0..batch.num_rows())
+        .map(|i| QueryResult {
+            returnflag: flags[i],
+            linestatus: statuses[i],
+            sum_qty: value(&sum_qty, i),
+            sum_base_price: value(&sum_base_price, i),
+            sum_disc_price: value(&sum_disc_price, i),
+            sum_charge: value(&sum_charge, i),
+            avg_qty: value(&avg_qty, i),
+            avg_price: value(&avg_price, i),
+            avg_disc: value(&avg_disc, i),
+            count: count.value(i),
+            exact: None,
+            moments: None,
+            distinct: None,
+            price_quantiles: None,
+        })
+        .collect())
 }
 
 #[cfg(test)]
@@ -358,19 +1194,393 @@ mod tests {
     #[test]
     fn test_hash_key() {
         // Test all 6 possible combinations
-        assert_eq!(hash_key(b'A', b'F'), 0);
-        assert_eq!(hash_key(b'A', b'O'), 1);
-        assert_eq!(hash_key(b'N', b'F'), 2);
-        assert_eq!(hash_key(b'N', b'O'), 3);
-        assert_eq!(hash_key(b'R', b'F'), 4);
-        assert_eq!(hash_key(b'R', b'O'), 5);
+        let keys = PerfectHash::tpch();
+        assert_eq!(keys.num_slots(), 6);
+        assert_eq!(keys.slot(b'A', b'F'), 0);
+        assert_eq!(keys.slot(b'A', b'O'), 1);
+        assert_eq!(keys.slot(b'N', b'F'), 2);
+        assert_eq!(keys.slot(b'N', b'O'), 3);
+        assert_eq!(keys.slot(b'R', b'F'), 4);
+        assert_eq!(keys.slot(b'R', b'O'), 5);
     }
     
     #[test]
     fn test_unhash_key() {
+        let keys = PerfectHash::tpch();
         for idx in 0..6 {
-            let (flag, status) = unhash_key(idx);
-            assert_eq!(hash_key(flag, status), idx);
+            let (flag, status) = keys.key(idx);
+            assert_eq!(keys.slot(flag, status), idx);
+        }
+    }
+
+    #[test]
+    fn test_unknown_keys_out_of_range() {
+        let keys = PerfectHash::tpch();
+        assert!(keys.slot(b'X', b'F') >= keys.num_slots());
+        assert!(keys.slot(b'R', b'X') >= keys.num_slots());
+        assert!(keys.slot(b'X', b'X') >= keys.num_slots());
+
+        // Same property at the largest domain we accept
+        let flags: Vec<u8> = (b'a'..b'a' + 8).collect();
+        let keys = PerfectHash::new(&flags, &flags).unwrap();
+        assert_eq!(keys.num_slots(), MAX_PERFECT_HASH_SLOTS);
+        assert!(keys.slot(b'h', b'Z') >= keys.num_slots());
+        assert!(keys.slot(b'Z', b'a') >= keys.num_slots());
+        assert!(PerfectHash::new(&flags, b"123456789").is_none());
+    }
+
+    #[test]
+    fn test_unexpected_key_is_error() {
+        let mut agg = Aggregator::new();
+        let mask = arrow::array::BooleanArray::from(vec![true, true]);
+        let flags = StringArray::from(vec!["A", "X"]);
+        let statuses = StringArray::from(vec!["F", "F"]);
+        let v = Float64Array::from(vec![1.0, 1.0]);
+        let err = agg.aggregate_batch(&mask, &flags, &statuses, &v, &v, &v, &v).unwrap_err();
+        assert!(matches!(err, GooseError::InvalidData(_)));
+
+        let keys = PerfectHash::from_domains(&[b"A".to_vec(), b"X".to_vec()], &[b"F".to_vec()]).unwrap();
+        let mut agg = Aggregator::with_keys(keys);
+        agg.aggregate_batch(&mask, &flags, &statuses, &v, &v, &v, &v).unwrap();
+        let results = agg.get_results();
+        assert_eq!(results.len(), 2);
+        assert_eq!((results[1].returnflag, results[1].count), (Some(b'X'), 1));
+    }
+
+    #[test]
+    fn test_unmasked_matches_all_true_mask() {
+        // 7 rows: one unrolled chunk plus a remainder
+        let flags = StringArray::from(vec!["A", "N", "R", "N", "A", "R", "N"]);
+        let statuses = StringArray::from(vec!["F", "O", "F", "F", "F", "F", "O"]);
+        let q = Float64Array::from(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+        let p = Float64Array::from(vec![10.0, 20.0, 30.0, 40.0, 50.0, 60.0, 70.0]);
+        let d = Float64Array::from(vec![0.01, 0.02, 0.03, 0.04, 0.05, 0.06, 0.07]);
+        let t = Float64Array::from(vec![0.08, 0.07, 0.06, 0.05, 0.04, 0.03, 0.02]);
+        let mask = arrow::array::BooleanArray::from(vec![true; 7]);
+
+        let mut masked = Aggregator::new();
+        masked.aggregate_batch(&mask, &flags, &statuses, &q, &p, &d, &t).unwrap();
+        let mut unmasked = Aggregator::new();
+        unmasked.aggregate_batch_unmasked(&flags, &statuses, &q, &p, &d, &t).unwrap();
+        assert_eq!(format!("{:?}", unmasked.get_results()), format!("{:?}", masked.get_results()));
+        assert_eq!(unmasked.get_results().iter().map(|r| r.count).sum::<u64>(), 7);
+
+        let one = |a: &Float64Array| a.slice(0, 1);
+        let err = unmasked.aggregate_batch_unmasked(&StringArray::from(vec!["X"]), &statuses.slice(0, 1), &one(&q), &one(&p), &one(&d), &one(&t));
+        assert!(matches!(err, Err(GooseError::InvalidData(_))));
+    }
+
+    #[test]
+    fn test_null_semantics() {
+        let flags = StringArray::from(vec![Some("A"), None, Some("A"), Some("A"), None]);
+        let statuses = StringArray::from(vec![Some("F"), Some("O"), Some("F"), Some("F"), Some("O")]);
+        let q = Float64Array::from(vec![Some(1.0), Some(2.0), None, Some(4.0), None]);
+        let p = Float64Array::from(vec![Some(10.0), Some(20.0), Some(30.0), None, None]);
+        let d = Float64Array::from(vec![Some(0.1), Some(0.0), Some(0.0), Some(0.0), None]);
+        let t = Float64Array::from(vec![Some(0.0), Some(0.0), None, Some(0.0), None]);
+        // A NULL mask entry does not qualify
+        let mask = arrow::array::BooleanArray::from(vec![Some(true), Some(true), Some(true), Some(true), None]);
+
+        let mut agg = Aggregator::new();
+        agg.aggregate_batch(&mask, &flags, &statuses, &q, &p, &d, &t).unwrap();
+        let results = agg.get_results();
+        assert_eq!(results.len(), 2);
+
+        // COUNT(*) counts every row; SUM/AVG skip NULL inputs
+        let a = &results[0];
+        assert_eq!((a.returnflag, a.linestatus, a.count), (Some(b'A'), Some(b'F'), 3));
+        assert_eq!((a.sum_qty, a.avg_qty), (Some(5.0), Some(2.5)));
+        assert_eq!((a.sum_base_price, a.avg_price), (Some(40.0), Some(20.0)));
+        assert_eq!(a.sum_disc_price, Some(39.0));
+        assert_eq!(a.sum_charge, Some(9.0));
+
+        // The NULL key is its own group, sorted last
+        let n = &results[1];
+        assert_eq!((n.returnflag, n.linestatus, n.count), (None, Some(b'O'), 1));
+        assert_eq!(n.sum_qty, Some(2.0));
+
+        // A group with only NULL inputs sums to NULL
+        let mut agg = Aggregator::new();
+        let none = Float64Array::from(vec![None]);
+        agg.aggregate_batch_unmasked(&flags.slice(0, 1), &statuses.slice(0, 1), &none, &none, &none, &none).unwrap();
+        let results = agg.get_results();
+        assert_eq!((results[0].count, results[0].sum_qty, results[0].avg_disc), (1, None, None));
+
+        let batch = results_to_record_batch(&results).unwrap();
+        assert_eq!(batch.column(2).null_count(), 1);
+        let back = record_batch_to_results(&batch).unwrap();
+        assert_eq!(format!("{:?}", back), format!("{:?}", results));
+    }
+
+    #[test]
+    fn test_moments() {
+        // Prices around a million that differ in the cents: the
+        // sum-of-squares formula loses every digit of the variance here
+        let values: Vec<f64> = (0..1_000).map(|i| 1e6 + (i % 7) as f64 * 0.01).collect();
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let expected = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+
+        let mut all = Moments::default();
+        values.iter().for_each(|&v| all.update(v));
+        assert!((all.var_samp().unwrap() - expected).abs() < 1e-6 * expected);
+        assert_eq!((all.min(), all.max()), (Some(1e6), Some(1e6 + 0.06)));
+
+        // Merging uneven parts, and with empty ones, matches one pass
+        let mut merged = Moments::default();
+        for part in [&values[..0], &values[..3], &values[3..640], &values[640..]] {
+            let mut moments = Moments::default();
+            part.iter().for_each(|&v| moments.update(v));
+            merged.merge(&moments);
+        }
+        assert_eq!((merged.count, merged.min, merged.max), (all.count, all.min, all.max));
+        assert!((merged.var_pop().unwrap() - all.var_pop().unwrap()).abs() < 1e-6 * expected);
+
+        let mut one = Moments::default();
+        assert_eq!((one.min(), one.var_pop()), (None, None));
+        one.update(3.0);
+        assert_eq!((one.var_pop(), one.var_samp(), one.stddev_pop()), (Some(0.0), None, Some(0.0)));
+    }
+
+    #[test]
+    fn test_with_moments() {
+        let flags = StringArray::from(vec![Some("A"), Some("N"), Some("A"), Some("A"), None, Some("N"), Some("A")]);
+        let statuses = StringArray::from(vec!["F", "O", "F", "F", "F", "O", "F"]);
+        let q = Float64Array::from(vec![Some(1.0), Some(2.0), Some(3.0), None, Some(5.0), Some(6.0), Some(9.0)]);
+        let p = Float64Array::from(vec![10.0, 20.0, 30.0, 40.0, 50.0, 60.0, 70.0]);
+        let mask = arrow::array::BooleanArray::from(vec![true, true, true, true, true, true, false]);
+
+        let mut agg = Aggregator::new().with_moments();
+        agg.aggregate_batch(&mask, &flags, &statuses, &q, &p, &p, &p).unwrap();
+        let results = agg.get_results();
+        let moments: Vec<InputMoments> = results.iter().map(|r| r.moments.unwrap()).collect();
+        // (A, F): quantities 1 and 3 (NULL skipped, masked-out 9 too)
+        assert_eq!((moments[0].quantity.count, moments[0].quantity.min(), moments[0].quantity.max()), (2, Some(1.0), Some(3.0)));
+        assert_eq!(moments[0].quantity.var_samp(), Some(2.0));
+        assert_eq!((moments[0].price.count, moments[0].price.mean), (3, 80.0 / 3.0));
+        assert!((moments[0].price.var_pop().unwrap() - 1400.0 / 9.0).abs() < 1e-9);
+        assert_eq!(moments[1].quantity.stddev_samp(), Some(8f64.sqrt()));
+        // The NULL-key group has moments too
+        assert_eq!((results[2].returnflag, moments[2].price.max()), (None, Some(50.0)));
+
+        // Split across aggregators (threads) and merged: same state
+        let mut a = Aggregator::new().with_moments();
+        let mut b = Aggregator::new().with_moments();
+        a.aggregate_batch(&mask.slice(0, 3), &flags.slice(0, 3), &statuses.slice(0, 3), &q.slice(0, 3), &p.slice(0, 3), &p.slice(0, 3), &p.slice(0, 3)).unwrap();
+        let rest = |a: &Float64Array| a.slice(3, 4);
+        b.aggregate_batch(&mask.slice(3, 4), &flags.slice(3, 4), &statuses.slice(3, 4), &rest(&q), &rest(&p), &rest(&p), &rest(&p)).unwrap();
+        a.merge(&b);
+        let merged: Vec<InputMoments> = a.get_results().iter().map(|r| r.moments.unwrap()).collect();
+        for (m, e) in merged.iter().zip(&moments) {
+            assert_eq!((m.quantity, m.price.count, m.price.min, m.price.max), (e.quantity, e.price.count, e.price.min, e.price.max));
+            assert!((m.price.m2 - e.price.m2).abs() < 1e-9 * e.price.m2.max(1.0));
+        }
+
+        // Off by default
+        let mut plain = Aggregator::new();
+        plain.aggregate_batch(&mask, &flags, &statuses, &q, &p, &p, &p).unwrap();
+        assert!(plain.get_results().iter().all(|r| r.moments.is_none()));
+    }
+
+    #[test]
+    fn test_aggregate_distinct() {
+        let flags = StringArray::from(vec![Some("A"), Some("A"), Some("N"), Some("A"), None, Some("A")]);
+        let statuses = StringArray::from(vec!["F", "F", "O", "F", "O", "F"]);
+        let orders = arrow::array::Int64Array::from(vec![Some(1), Some(1), Some(1), None, Some(2), Some(3)]);
+        let mask = arrow::array::BooleanArray::from(vec![Some(true), Some(true), Some(true), Some(true), Some(true), None]);
+        let ones = Float64Array::from(vec![1.0; 6]);
+
+        let run = |aggregator: &mut Aggregator, range: std::ops::Range<usize>| {
+            let (offset, len) = (range.start, range.len());
+            let mask = mask.slice(offset, len);
+            let (f, s, o, x) = (flags.slice(offset, len), statuses.slice(offset, len), orders.slice(offset, len), ones.slice(offset, len));
+            aggregator.aggregate_batch(&mask, &f, &s, &x, &x, &x, &x).unwrap();
+            aggregator.aggregate_distinct(Some(&mask), &f, &s, &o).unwrap();
+        };
+        let mut agg = Aggregator::new().with_distinct(DistinctMode::Exact);
+        run(&mut agg, 0..6);
+        let counts = |agg: &Aggregator| agg.get_results().iter().map(|r| (r.returnflag, r.count, r.distinct)).collect::<Vec<_>>();
+        // (A, F): order 1 twice, a NULL order, and order 3 under a NULL mask entry
+        assert_eq!(counts(&agg), vec![(Some(b'A'), 3, Some(1)), (Some(b'N'), 1, Some(1)), (None, 1, Some(1))]);
+
+        // Split over aggregators (threads) the counts merge, for either mode
+        for mode in [DistinctMode::Exact, DistinctMode::Approx] {
+            let mut a = Aggregator::new().with_distinct(mode);
+            let mut b = Aggregator::new().with_distinct(mode);
+            run(&mut a, 0..2);
+            run(&mut b, 2..6);
+            a.merge(&b);
+            assert_eq!(counts(&a), counts(&agg));
+        }
+
+        let err = agg.aggregate_distinct(None, &StringArray::from(vec!["X"]), &statuses.slice(0, 1), &orders.slice(0, 1));
+        assert!(matches!(err, Err(GooseError::InvalidData(_))));
+    }
+
+    #[test]
+    fn test_with_quantiles() {
+        use crate::quantile::quantile_of_sorted;
+
+        let n = 1_000;
+        let flags = StringArray::from((0..n).map(|i| if i % 3 == 0 { "N" } else { "A" }).collect::<Vec<_>>());
+        let statuses = StringArray::from((0..n).map(|i| if i % 3 == 0 { "O" } else { "F" }).collect::<Vec<_>>());
+        let p = Float64Array::from((0..n).map(|i| if i % 10 == 9 { None } else { Some(((i * 7919) % 1009) as f64) }).collect::<Vec<_>>());
+        let ones = Float64Array::from(vec![1.0; n]);
+        // Ground truth: the sorted non-NULL prices of (A, F)
+        let mut sorted: Vec<f64> = (0..n).filter(|&i| i % 3 != 0 && p.is_valid(i)).map(|i| p.value(i)).collect();
+        sorted.sort_unstable_by(f64::total_cmp);
+
+        let mut agg = Aggregator::new().with_quantiles(QuantileMode::Exact);
+        agg.aggregate_batch_unmasked(&flags, &statuses, &ones, &p, &ones, &ones).unwrap();
+        let results = agg.get_results();
+        let sketch = results[0].price_quantiles.as_ref().unwrap();
+        assert_eq!(sketch.count(), sorted.len() as u64);
+        for q in [0.0, 0.5, 0.95, 0.99, 1.0] {
+            assert_eq!(sketch.quantile(q), quantile_of_sorted(&sorted, q));
+        }
+
+        // Split over aggregators (threads) the state merges, for either mode
+        for mode in [QuantileMode::Exact, QuantileMode::Approx] {
+            let mut parts: Vec<Aggregator> = (0..4).map(|_| Aggregator::new().with_quantiles(mode)).collect();
+            for (k, part) in parts.iter_mut().enumerate() {
+                let (offset, len) = (k * n / 4, n / 4);
+                let (f, s, x, v) = (flags.slice(offset, len), statuses.slice(offset, len), ones.slice(offset, len), p.slice(offset, len));
+                part.aggregate_batch_unmasked(&f, &s, &x, &v, &x, &x).unwrap();
+            }
+            let (first, rest) = parts.split_first_mut().unwrap();
+            rest.iter().for_each(|part| first.merge(part));
+            let merged = first.get_results();
+            let sketch = merged[0].price_quantiles.as_ref().unwrap();
+            assert_eq!(sketch.count(), sorted.len() as u64);
+            // Prices are spread evenly over 0..1009: a digest is off by well
+            // under a percentile
+            let tolerance = if mode == QuantileMode::Exact { 0.0 } else { 5.0 };
+            for q in [0.5, 0.95, 0.99] {
+                let (got, want) = (sketch.quantile(q).unwrap(), quantile_of_sorted(&sorted, q).unwrap());
+                assert!((got - want).abs() <= tolerance, "{:?} q {}: {} vs {}", mode, q, got, want);
+            }
         }
+
+        // Off by default
+        let mut plain = Aggregator::new();
+        plain.aggregate_batch_unmasked(&flags, &statuses, &ones, &p, &ones, &ones).unwrap();
+        assert!(plain.get_results().iter().all(|r| r.price_quantiles.is_none()));
+    }
+
+    #[test]
+    fn test_summation() {
+        use crate::summation::{KahanSum, PairwiseSum};
+
+        /// `(count, base_price, disc_price, charge)` of one group, summed in
+        /// `S` over `p` cut into `parts` and merged
+        fn sums<S: FloatSum>(p: &Float64Array, parts: &[(usize, usize)]) -> (u64, f64, f64, f64) {
+            let mut total = Aggregator::<S>::with_accumulator(PerfectHash::tpch());
+            for &(offset, len) in parts {
+                let (flags, statuses) = (StringArray::from(vec!["A"; len]), StringArray::from(vec!["F"; len]));
+                let zeros = Float64Array::from(vec![0.0; len]);
+                let mut part = Aggregator::<S>::with_accumulator(PerfectHash::tpch());
+                part.aggregate_batch_unmasked(&flags, &statuses, &zeros, &p.slice(offset, len), &zeros, &zeros).unwrap();
+                total.merge(&part);
+            }
+            let r = &total.get_results()[0];
+            (r.count, r.sum_base_price.unwrap(), r.sum_disc_price.unwrap(), r.sum_charge.unwrap())
+        }
+
+        // Sums that cancel: plain adds lose the ones next to 1e16
+        let n = 1_001;
+        let p = Float64Array::from((0..n).map(|i| [1e16, 1.0, -1e16, 1.0][i % 4]).collect::<Vec<_>>());
+        let expected = 500.0 + 1e16;
+        let (whole, split) = ([(0, n)], [(0, 333), (333, n - 333)]);
+        assert_eq!(sums::<KahanSum>(&p, &whole), (n as u64, expected, expected, expected));
+        assert_eq!(sums::<KahanSum>(&p, &split), sums::<KahanSum>(&p, &whole));
+        assert_ne!(sums::<f64>(&p, &whole).1, expected);
+
+        // NULLs are skipped as in the plain sums
+        let (flags, statuses) = (StringArray::from(vec!["A"; 3]), StringArray::from(vec!["F"; 3]));
+        let q = Float64Array::from(vec![Some(1.0), None, Some(2.0)]);
+        let v = Float64Array::from(vec![1.0, 2.0, 3.0]);
+        let mask = arrow::array::BooleanArray::from(vec![true, true, false]);
+        let mut agg = Aggregator::<PairwiseSum>::with_accumulator(PerfectHash::tpch());
+        agg.aggregate_batch(&mask, &flags, &statuses, &q, &v, &v, &v).unwrap();
+        let r = &agg.get_results()[0];
+        assert_eq!((r.count, r.sum_qty, r.avg_qty, r.sum_base_price), (2, Some(1.0), Some(1.0), Some(3.0)));
+
+        // The plain state is still one cache line
+        assert_eq!(std::mem::size_of::<AggState>(), 64);
+    }
+
+    #[test]
+    fn test_key_columns() {
+        use arrow::array::{DictionaryArray, Int8Array, LargeStringArray, StringViewArray};
+
+        let flags = vec!["A", "N", "R", "N", "A", "R", "N", "A", "R"];
+        let statuses = vec!["F", "O", "F", "F", "F", "F", "O", "F", "F"];
+        let v = Float64Array::from((1..=9).map(f64::from).collect::<Vec<_>>());
+        let run = |flags: &dyn Array, statuses: &dyn Array, v: &Float64Array| {
+            let mut agg = Aggregator::new();
+            agg.aggregate_batch_unmasked(flags, statuses, v, v, v, v).map(|_| format!("{:?}", agg.get_results()))
+        };
+        let expected = run(&StringArray::from(flags.clone()), &StringArray::from(statuses.clone()), &v).unwrap();
+
+        let large = (LargeStringArray::from(flags.clone()), LargeStringArray::from(statuses.clone()));
+        assert_eq!(run(&large.0, &large.1, &v).unwrap(), expected);
+        let view = (StringViewArray::from(flags.clone()), StringViewArray::from(statuses.clone()));
+        assert_eq!(run(&view.0, &view.1, &v).unwrap(), expected);
+        let dictionary = |keys: &[&str]| {
+            let codes = keys.iter().map(|k| if *k == "A" || *k == "F" { 0 } else if *k == "R" { 2 } else { 1 });
+            DictionaryArray::new(Int8Array::from(codes.collect::<Vec<i8>>()), Arc::new(StringArray::from(vec!["AF", "NO", "R"])))
+        };
+        // "AF" / "NO" are two bytes: falls back to the checked path and fails
+        assert!(matches!(run(&dictionary(&flags), &dictionary(&statuses), &v), Err(GooseError::InvalidData(_))));
+        let dictionary = |keys: &[&str]| DictionaryArray::<arrow::datatypes::Int8Type>::from_iter(keys.iter().copied());
+        assert_eq!(run(&dictionary(&flags), &dictionary(&statuses), &v).unwrap(), expected);
+
+        // Sliced keys whose value buffer starts with a different row
+        let padded = |keys: &[&str]| StringArray::from([&["X", "X"], keys].concat()).slice(2, keys.len());
+        let mut agg = Aggregator::new();
+        agg.aggregate_batch_unmasked(&padded(&flags), &padded(&statuses), &v, &v, &v, &v).unwrap();
+        assert_eq!(format!("{:?}", agg.get_results()), expected);
+
+        // Same total byte length as the row count, but uneven strides
+        let uneven = StringArray::from(vec!["", "AN", "R"]);
+        let three = v.slice(0, 3);
+        let mask = arrow::array::BooleanArray::from(vec![false, false, true]);
+        let mut agg = Aggregator::new();
+        agg.aggregate_batch(&mask, &uneven, &StringArray::from(vec!["F"; 3]), &three, &three, &three, &three).unwrap();
+        let results = agg.get_results();
+        assert_eq!((results.len(), results[0].returnflag, results[0].sum_qty), (1, Some(b'R'), Some(3.0)));
+        let mask = arrow::array::BooleanArray::from(vec![false, true, true]);
+        let err = agg.aggregate_batch(&mask, &uneven, &StringArray::from(vec!["F"; 3]), &three, &three, &three, &three);
+        assert!(matches!(err, Err(GooseError::InvalidData(_))));
+
+        // Multi-byte UTF-8 keys are rejected rather than truncated
+        let err = run(&StringArray::from(vec!["É"]), &StringArray::from(vec!["F"]), &v.slice(0, 1));
+        assert!(matches!(err, Err(GooseError::InvalidData(_))));
+        let err = run(&StringArray::from(vec!["A"]), &StringArray::from(vec!["F"]), &v);
+        assert!(matches!(err, Err(GooseError::InvalidData(_))));
+    }
+
+    #[test]
+    fn test_dictionary_codes() {
+        use arrow::array::{BooleanArray, DictionaryArray, Int32Array};
+
+        // "AIR" is neither in the domain nor one byte, but only sits in
+        // rows the mask rejects
+        let flag_values = Arc::new(StringArray::from(vec!["R", "AIR", "A"]));
+        let flags = DictionaryArray::new(Int32Array::from(vec![2, 1, 0, 2, 1, 0]), flag_values);
+        let statuses = DictionaryArray::new(Int32Array::from(vec![0, 0, 0, 1, 0, 0]), Arc::new(StringArray::from(vec!["F", "O"])));
+        let v = Float64Array::from(vec![1.0, 2.0, 4.0, 8.0, 16.0, 32.0]);
+        let mask = BooleanArray::from(vec![true, false, true, true, false, true]);
+
+        let mut agg = Aggregator::new();
+        agg.aggregate_batch(&mask, &flags, &statuses, &v, &v, &v, &v).unwrap();
+        let results = agg.get_results();
+        let keys: Vec<_> = results.iter().map(|r| (r.returnflag, r.linestatus, r.count, r.sum_qty)).collect();
+        assert_eq!(
+            keys,
+            vec![(Some(b'A'), Some(b'F'), 1, Some(1.0)), (Some(b'A'), Some(b'O'), 1, Some(8.0)), (Some(b'R'), Some(b'F'), 2, Some(36.0))]
+        );
+
+        let err = agg.aggregate_batch_unmasked(&flags, &statuses, &v, &v, &v, &v).unwrap_err();
+        assert!(err.to_string().contains("\"AIR\""), "{}", err);
     }
 }
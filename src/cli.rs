@@ -0,0 +1,509 @@
+//! Command-line interface of the `goose-db` binary
+//!
+//! ```text
+//! goose-db run     [PATH...] [--threads N] [--cutoff DATE | --delta DAYS] [--exact] [--row-filter] [--summation S] [--sql QUERY] [--format F]
+//! goose-db bench   [PATH...] [--runs N] [--warmup N] [--threads N] [--cutoff DATE | --delta DAYS] [--exact] [--row-filter] [--summation S] [--format F]
+//! goose-db explain [PATH...] [--cutoff DATE | --delta DAYS] [--sql QUERY] [--analyze]
+//! goose-db generate [--scale SF] [--output DIR] [--table NAME...]
+//! ```
+//!
+//! Errors never panic: usage errors exit with `EXIT_USAGE`, failed queries
+//! with `EXIT_FAILURE`.
+
+use std::ffi::OsString;
+use std::io::Write;
+use std::time::Instant;
+
+use arrow::array::RecordBatch;
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use clap::{value_parser, Arg, ArgAction, ArgGroup, ArgMatches, Command};
+
+use crate::aggregator::{results_to_record_batch, AggregationMode};
+use crate::dbgen::{generate, Table};
+use crate::error::GooseError;
+use crate::input::join_inputs;
+use crate::params::Q1Params;
+use crate::query::{execute_tpch_q1_profiled, execute_tpch_q1_with_options, explain_tpch_q1, ExecutionOptions};
+use crate::reader::FilterStrategy;
+use crate::summation::Summation;
+use crate::sql::{execute_sql, explain_sql, parser::parse_date, Catalog};
+
+/// Exit code of a failed query
+pub const EXIT_FAILURE: i32 = 1;
+
+/// Exit code of invalid arguments
+pub const EXIT_USAGE: i32 = 2;
+
+/// Input used when no path is given
+pub const DEFAULT_DATA_PATH: &str = "data/lineitem.parquet";
+
+/// Result output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+fn command() -> Command {
+    let path = Arg::new("path")
+        .value_name("PATH")
+        .num_args(1..)
+        .default_value(DEFAULT_DATA_PATH)
+        .help("lineitem Parquet files, directories or globs");
+    let threads = Arg::new("threads")
+        .long("threads")
+        .short('t')
+        .value_name("N")
+        .value_parser(value_parser!(usize))
+        .default_value("1")
+        .help("Worker threads (0 = one per core)");
+    let cutoff = Arg::new("cutoff")
+        .long("cutoff")
+        .value_name("DATE")
+        .help("Ship-date cutoff as YYYY-MM-DD");
+    let delta = Arg::new("delta")
+        .long("delta")
+        .value_name("DAYS")
+        .value_parser(value_parser!(i32))
+        .help("Ship-date cutoff as DELTA days before 1998-12-01 (default 90)");
+    let cutoff_group = ArgGroup::new("ship_date").args(["cutoff", "delta"]);
+    let exact = Arg::new("exact")
+        .long("exact")
+        .action(ArgAction::SetTrue)
+        .help("Exact decimal sums instead of f64");
+    let row_filter = Arg::new("row_filter")
+        .long("row-filter")
+        .action(ArgAction::SetTrue)
+        .help("Evaluate the ship-date predicate inside the Parquet reader");
+    let summation = Arg::new("summation")
+        .long("summation")
+        .value_parser(["plain", "kahan", "pairwise"])
+        .default_value("plain")
+        .conflicts_with("exact")
+        .help("How f64 sums are accumulated");
+    let format = Arg::new("format")
+        .long("format")
+        .short('f')
+        .value_parser(["table", "csv", "json"])
+        .default_value("table")
+        .help("Output format");
+    let sql = Arg::new("sql")
+        .long("sql")
+        .value_name("QUERY")
+        .help("SQL query instead of Q1; PATH is registered as table `lineitem`");
+
+    Command::new("goose-db")
+        .about("TPC-H Q1 over Parquet")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(
+            Command::new("run")
+                .about("Run Q1 (or --sql) once and print the result")
+                .args([path.clone(), threads.clone(), cutoff.clone(), delta.clone(), exact.clone(), row_filter.clone(), summation.clone()])
+                .args([sql.clone().conflicts_with_all(["threads", "exact", "row_filter", "summation"]), format.clone()])
+                .group(cutoff_group.clone()),
+        )
+        .subcommand(
+            Command::new("bench")
+                .about("Time repeated Q1 runs")
+                .args([path.clone(), threads, cutoff.clone(), delta.clone(), exact, row_filter, summation, format])
+                .arg(
+                    Arg::new("runs")
+                        .long("runs")
+                        .short('n')
+                        .value_name("N")
+                        .value_parser(value_parser!(u64).range(1..))
+                        .default_value("10")
+                        .help("Timed runs"),
+                )
+                .arg(
+                    Arg::new("warmup")
+                        .long("warmup")
+                        .value_name("N")
+                        .value_parser(value_parser!(usize))
+                        .default_value("1")
+                        .help("Untimed warmup runs"),
+                )
+                .group(cutoff_group.clone()),
+        )
+        .subcommand(
+            Command::new("explain")
+                .about("Print the physical plan")
+                .args([path, cutoff, delta, sql.clone()])
+                .arg(
+                    Arg::new("analyze")
+                        .long("analyze")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("sql")
+                        .help("Also run Q1 and print the per-phase profile"),
+                )
+                .group(cutoff_group),
+        )
+        .subcommand(
+            Command::new("generate")
+                .about("Write TPC-H tables as Parquet, like dbgen")
+                .arg(
+                    Arg::new("scale")
+                        .long("scale")
+                        .short('s')
+                        .value_name("SF")
+                        .value_parser(value_parser!(f64))
+                        .default_value("1")
+                        .help("Scale factor: whole numbers, or fractions below 1"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("DIR")
+                        .default_value("data")
+                        .help("Directory for <table>.parquet"),
+                )
+                .arg(
+                    Arg::new("table")
+                        .long("table")
+                        .value_name("NAME")
+                        .num_args(1..)
+                        .value_parser(["lineitem", "orders", "customer", "part", "partsupp", "supplier", "nation", "region", "all"])
+                        .default_value("lineitem")
+                        .help("Tables to generate"),
+                ),
+        )
+}
+
+/// Parse `args` (including the program name), run the command and return
+/// the process exit code
+pub fn run<I, T>(args: I, out: &mut dyn Write, err: &mut dyn Write) -> i32
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = match command().try_get_matches_from(args) {
+        Ok(matches) => matches,
+        Err(e) => {
+            // --help and --version also arrive here, with exit code 0
+            let _ = write!(err, "{}", e.render());
+            return e.exit_code();
+        }
+    };
+
+    let result = match matches.subcommand() {
+        Some(("run", m)) => run_command(m, out),
+        Some(("bench", m)) => bench_command(m, out),
+        Some(("explain", m)) => explain_command(m, out),
+        Some(("generate", m)) => generate_command(m, out),
+        _ => unreachable!("subcommand_required"),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            let _ = writeln!(err, "error: {}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn params(m: &ArgMatches) -> Result<Q1Params, GooseError> {
+    Ok(match (m.get_one::<String>("cutoff"), m.get_one::<i32>("delta")) {
+        (Some(date), _) => Q1Params::with_date(parse_date(date)?),
+        (None, Some(&delta)) => Q1Params::with_delta(delta),
+        (None, None) => Q1Params::default(),
+    })
+}
+
+fn options(m: &ArgMatches) -> ExecutionOptions {
+    ExecutionOptions {
+        num_threads: *m.get_one::<usize>("threads").expect("has default"),
+        mode: if m.get_flag("exact") { AggregationMode::ExactDecimal } else { AggregationMode::Float },
+        filter: if m.get_flag("row_filter") { FilterStrategy::RowFilter } else { FilterStrategy::Mask },
+        summation: match m.get_one::<String>("summation").map(String::as_str) {
+            Some("kahan") => Summation::Kahan,
+            Some("pairwise") => Summation::Pairwise,
+            _ => Summation::Plain,
+        },
+        ..ExecutionOptions::default()
+    }
+}
+
+fn format(m: &ArgMatches) -> OutputFormat {
+    match m.get_one::<String>("format").map(String::as_str) {
+        Some("csv") => OutputFormat::Csv,
+        Some("json") => OutputFormat::Json,
+        _ => OutputFormat::Table,
+    }
+}
+
+/// All PATH arguments as one scan input
+fn path(m: &ArgMatches) -> Result<String, GooseError> {
+    let paths: Vec<&String> = m.get_many::<String>("path").expect("has default").collect();
+    if paths == [DEFAULT_DATA_PATH] && !std::path::Path::new(DEFAULT_DATA_PATH).exists() {
+        let msg = format!("{} not found; create it with `goose-db generate`", DEFAULT_DATA_PATH);
+        return Err(GooseError::Io(std::io::Error::new(std::io::ErrorKind::NotFound, msg)));
+    }
+    join_inputs(&paths)
+}
+
+fn catalog(m: &ArgMatches) -> Result<Catalog, GooseError> {
+    let mut catalog = Catalog::new();
+    catalog.register("lineitem", &path(m)?);
+    Ok(catalog)
+}
+
+fn run_command(m: &ArgMatches, out: &mut dyn Write) -> Result<(), GooseError> {
+    let batch = match m.get_one::<String>("sql") {
+        Some(sql) => execute_sql(sql, &catalog(m)?)?,
+        None => results_to_record_batch(&execute_tpch_q1_with_options(&path(m)?, &params(m)?, &options(m))?)?,
+    };
+    write_batch(&batch, format(m), out)
+}
+
+fn bench_command(m: &ArgMatches, out: &mut dyn Write) -> Result<(), GooseError> {
+    let (path, params, options) = (&path(m)?, params(m)?, options(m));
+    let runs = *m.get_one::<u64>("runs").expect("has default") as usize;
+    let warmup = *m.get_one::<usize>("warmup").expect("has default");
+
+    for _ in 0..warmup {
+        execute_tpch_q1_with_options(path, &params, &options)?;
+    }
+
+    let mut times = Vec::with_capacity(runs);
+    let mut results = Vec::new();
+    for _ in 0..runs {
+        let start = Instant::now();
+        results = execute_tpch_q1_with_options(path, &params, &options)?;
+        times.push(start.elapsed().as_secs_f64() * 1000.0);
+    }
+
+    let stats = Stats::new(&times);
+    match format(m) {
+        OutputFormat::Table => {
+            write_batch(&results_to_record_batch(&results)?, OutputFormat::Table, out)?;
+            writeln!(out)?;
+            writeln!(out, "Performance ({} runs, {} warmup, {} threads):", runs, warmup, options.num_threads)?;
+            writeln!(out, "{:-<40}", "")?;
+            writeln!(out, "  Mean:   {:.2} ms", stats.mean)?;
+            writeln!(out, "  Stddev: {:.2} ms", stats.stddev)?;
+            writeln!(out, "  Min:    {:.2} ms", stats.min)?;
+            writeln!(out, "  Max:    {:.2} ms", stats.max)?;
+            writeln!(out)?;
+
+            // Per-operator breakdown of one more run
+            let (_, profile) = execute_tpch_q1_profiled(path, &params, &options)?;
+            writeln!(out, "Profile:")?;
+            writeln!(out, "{:-<40}", "")?;
+            writeln!(out, "{}", profile)?;
+        }
+        OutputFormat::Csv => {
+            writeln!(out, "run,ms")?;
+            for (i, t) in times.iter().enumerate() {
+                writeln!(out, "{},{:.3}", i + 1, t)?;
+            }
+        }
+        OutputFormat::Json => {
+            let times: Vec<String> = times.iter().map(|t| format!("{:.3}", t)).collect();
+            writeln!(
+                out,
+                "{{\"runs\":{},\"warmup\":{},\"threads\":{},\"mean_ms\":{:.3},\"stddev_ms\":{:.3},\"min_ms\":{:.3},\"max_ms\":{:.3},\"times_ms\":[{}]}}",
+                runs,
+                warmup,
+                options.num_threads,
+                stats.mean,
+                stats.stddev,
+                stats.min,
+                stats.max,
+                times.join(",")
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn explain_command(m: &ArgMatches, out: &mut dyn Write) -> Result<(), GooseError> {
+    let plan = match m.get_one::<String>("sql") {
+        Some(sql) => explain_sql(sql, &catalog(m)?)?,
+        None => explain_tpch_q1(&path(m)?, &params(m)?)?,
+    };
+    write!(out, "{}", plan)?;
+
+    if m.get_flag("analyze") {
+        let (_, profile) = execute_tpch_q1_profiled(&path(m)?, &params(m)?, &ExecutionOptions::default())?;
+        writeln!(out)?;
+        writeln!(out, "{}", profile)?;
+    }
+    Ok(())
+}
+
+fn generate_command(m: &ArgMatches, out: &mut dyn Write) -> Result<(), GooseError> {
+    let scale = *m.get_one::<f64>("scale").expect("has default");
+    let dir = m.get_one::<String>("output").expect("has default");
+    let names: Vec<&String> = m.get_many::<String>("table").expect("has default").collect();
+    let tables: Vec<Table> = match names.iter().any(|n| *n == "all") {
+        true => Table::ALL.to_vec(),
+        false => names.iter().filter_map(|n| Table::from_name(n)).collect(),
+    };
+
+    let start = Instant::now();
+    for (path, rows) in generate(dir.as_ref(), scale, &tables)? {
+        writeln!(out, "{}  {} rows", path.display(), rows)?;
+    }
+    writeln!(out, "Generated SF {} in {:.2} s", scale, start.elapsed().as_secs_f64())?;
+    Ok(())
+}
+
+/// Summary of benchmark timings in milliseconds
+struct Stats {
+    mean: f64,
+    stddev: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Stats {
+    fn new(times: &[f64]) -> Self {
+        let mean = times.iter().sum::<f64>() / times.len() as f64;
+        let variance = times.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / times.len() as f64;
+        Self {
+            mean,
+            stddev: variance.sqrt(),
+            min: times.iter().cloned().fold(f64::INFINITY, f64::min),
+            max: times.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+/// Write a result batch in the requested format
+pub fn write_batch(batch: &RecordBatch, format: OutputFormat, out: &mut dyn Write) -> Result<(), GooseError> {
+    match format {
+        OutputFormat::Csv => {
+            let mut writer = arrow::csv::Writer::new(out);
+            writer.write(batch)?;
+        }
+        OutputFormat::Json => {
+            let mut writer = arrow::json::ArrayWriter::new(&mut *out);
+            writer.write(batch)?;
+            writer.finish()?;
+            writeln!(out)?;
+        }
+        OutputFormat::Table => {
+            let options = FormatOptions::default().with_null("NULL");
+            let mut columns: Vec<Vec<String>> = Vec::with_capacity(batch.num_columns());
+            for (field, array) in batch.schema().fields().iter().zip(batch.columns()) {
+                let formatter = ArrayFormatter::try_new(array.as_ref(), &options)?;
+                let mut cells = vec![field.name().clone()];
+                cells.extend((0..batch.num_rows()).map(|i| formatter.value(i).to_string()));
+                columns.push(cells);
+            }
+            let widths: Vec<usize> = columns
+                .iter()
+                .map(|cells| cells.iter().map(|c| c.chars().count()).max().unwrap_or(0))
+                .collect();
+            let total = widths.iter().sum::<usize>() + 2 * widths.len().saturating_sub(1);
+
+            for row in 0..=batch.num_rows() {
+                let line: Vec<String> = columns
+                    .iter()
+                    .zip(&widths)
+                    .map(|(cells, &width)| format!("{:>width$}", cells[row], width = width))
+                    .collect();
+                writeln!(out, "{}", line.join("  "))?;
+                if row == 0 {
+                    writeln!(out, "{:-<total$}", "", total = total)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{sample_rows, temp_path, write_lineitem};
+
+    fn run_args(args: &[&str]) -> (i32, String, String) {
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let code = run(std::iter::once("goose-db").chain(args.iter().copied()), &mut out, &mut err);
+        (code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn test_run_formats() {
+        let path = temp_path("cli-run");
+        write_lineitem(&path, &sample_rows(2_000), 500);
+        let path_str = path.to_str().unwrap();
+
+        let (code, out, _) = run_args(&["run", path_str, "--format", "csv", "--delta", "90"]);
+        assert_eq!(code, 0);
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines[0].starts_with("l_returnflag,l_linestatus,sum_qty"));
+        let groups = crate::query::execute_tpch_q1(path_str, &Q1Params::default()).unwrap().len();
+        assert_eq!(lines.len(), groups + 1);
+
+        let (code, out, _) = run_args(&["run", path_str, "-f", "json", "--threads", "2", "--exact"]);
+        assert_eq!(code, 0);
+        assert!(out.starts_with("[{\"l_returnflag\":\"A\""));
+
+        let (code, kahan, _) = run_args(&["run", path_str, "--format", "csv", "--summation", "kahan"]);
+        assert_eq!(code, 0);
+        assert_eq!(kahan.lines().count(), lines.len());
+
+        let (code, out, _) = run_args(&["run", path_str, "--sql", "SELECT count(*) AS n FROM lineitem"]);
+        assert_eq!(code, 0);
+        assert_eq!(out.lines().collect::<Vec<_>>(), vec!["   n", "----", "2000"]);
+
+        let (code, out, _) = run_args(&["explain", path_str, "--cutoff", "1992-03-01", "--analyze"]);
+        assert_eq!(code, 0);
+        assert!(out.contains("TABLE_SCAN"));
+        assert!(out.contains("GROUP_BY"));
+
+        let (code, out, _) = run_args(&["bench", path_str, "--runs", "2", "--warmup", "0", "--format", "csv"]);
+        assert_eq!(code, 0);
+        assert_eq!(out.lines().count(), 3);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        // Missing file: query failure, reported instead of panicking
+        let (code, _, err) = run_args(&["run", "/nonexistent/lineitem.parquet"]);
+        assert_eq!(code, EXIT_FAILURE);
+        assert!(err.starts_with("error: "));
+
+        let (code, _, err) = run_args(&["run", "x.parquet", "--cutoff", "1998-13-01"]);
+        assert_eq!(code, EXIT_FAILURE);
+        assert!(err.contains("invalid date"));
+
+        // Usage errors
+        assert_eq!(run_args(&["frobnicate"]).0, EXIT_USAGE);
+        assert_eq!(run_args(&["run", "--cutoff", "1998-09-02", "--delta", "90"]).0, EXIT_USAGE);
+        assert_eq!(run_args(&["bench", "--runs", "0"]).0, EXIT_USAGE);
+        assert_eq!(run_args(&["run", "--format", "xml"]).0, EXIT_USAGE);
+        assert_eq!(run_args(&["run", "--summation", "kahan", "--exact"]).0, EXIT_USAGE);
+        assert_eq!(run_args(&["bench", "--summation", "fast"]).0, EXIT_USAGE);
+        assert_eq!(run_args(&["run", "--help"]).0, 0);
+        assert_eq!(run_args(&["generate", "--table", "lineitems"]).0, EXIT_USAGE);
+        let (code, _, err) = run_args(&["generate", "--scale", "1.5", "-o", "/nonexistent"]);
+        assert_eq!(code, EXIT_FAILURE);
+        assert!(err.contains("scale factor 1.5"));
+    }
+
+    #[test]
+    fn test_generate() {
+        let dir = temp_path("cli-generate").with_extension("");
+        let dir_str = dir.to_str().unwrap();
+        let (code, out, _) = run_args(&["generate", "-s", "0.01", "-o", dir_str, "--table", "lineitem", "region"]);
+        assert_eq!(code, 0);
+        assert!(out.contains("lineitem.parquet  60175 rows"), "{}", out);
+        assert!(out.contains("region.parquet  5 rows"));
+
+        let lineitem = dir.join("lineitem.parquet");
+        let (code, out, _) = run_args(&["run", lineitem.to_str().unwrap(), "--format", "csv"]);
+        assert_eq!(code, 0);
+        assert_eq!(out.lines().count(), 5);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,1421 @@
+//! Deterministic TPC-H data generator writing Parquet (a port of dbgen)
+//!
+//! Rows are built the way dbgen builds them, from its seeds. Every column
+//! draws from its own Park-Miller stream, and after each row the streams of
+//! that table skip ahead to a fixed number of draws per row, so no column's
+//! values depend on another's and each table can be generated on its own.
+//! Keys, numbers, dates, flags, phone numbers and addresses follow dbgen
+//! for the same scale factor; Q1 over the generated `lineitem` at SF 1
+//! gives the official answers. Free text is cut from a small, locally
+//! generated word pool instead of dbgen's 300 MB grammar pool, and part
+//! names, types and containers come from word lists whose order may differ
+//! from dbgen's `dists.dss`, so those columns match dbgen in shape only.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use arrow::array::{
+    ArrayBuilder, ArrayRef, Date32Builder, Decimal128Builder, Int32Builder, Int64Builder, RecordBatch, StringBuilder,
+};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use parquet::file::properties::WriterProperties;
+
+use crate::error::GooseError;
+
+/// The eight TPC-H tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Table {
+    Part,
+    Supplier,
+    PartSupp,
+    Customer,
+    Orders,
+    Lineitem,
+    Nation,
+    Region,
+}
+
+impl Table {
+    /// Every table, in dbgen's order
+    pub const ALL: [Table; 8] = [
+        Table::Part,
+        Table::Supplier,
+        Table::PartSupp,
+        Table::Customer,
+        Table::Orders,
+        Table::Lineitem,
+        Table::Nation,
+        Table::Region,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Table::Part => "part",
+            Table::Supplier => "supplier",
+            Table::PartSupp => "partsupp",
+            Table::Customer => "customer",
+            Table::Orders => "orders",
+            Table::Lineitem => "lineitem",
+            Table::Nation => "nation",
+            Table::Region => "region",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Table> {
+        Table::ALL.into_iter().find(|t| t.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Arrow schema of the generated file: keys Int64, money and
+    /// quantities Decimal128(15, 2), dates Date32, text Utf8
+    pub fn schema(self) -> SchemaRef {
+        let int64 = |name: &str| Field::new(name, DataType::Int64, false);
+        let int32 = |name: &str| Field::new(name, DataType::Int32, false);
+        let decimal = |name: &str| Field::new(name, DECIMAL, false);
+        let date = |name: &str| Field::new(name, DataType::Date32, false);
+        let utf8 = |name: &str| Field::new(name, DataType::Utf8, false);
+        let fields = match self {
+            Table::Part => vec![
+                int64("p_partkey"),
+                utf8("p_name"),
+                utf8("p_mfgr"),
+                utf8("p_brand"),
+                utf8("p_type"),
+                int32("p_size"),
+                utf8("p_container"),
+                decimal("p_retailprice"),
+                utf8("p_comment"),
+            ],
+            Table::Supplier => vec![
+                int64("s_suppkey"),
+                utf8("s_name"),
+                utf8("s_address"),
+                int64("s_nationkey"),
+                utf8("s_phone"),
+                decimal("s_acctbal"),
+                utf8("s_comment"),
+            ],
+            Table::PartSupp => vec![
+                int64("ps_partkey"),
+                int64("ps_suppkey"),
+                int32("ps_availqty"),
+                decimal("ps_supplycost"),
+                utf8("ps_comment"),
+            ],
+            Table::Customer => vec![
+                int64("c_custkey"),
+                utf8("c_name"),
+                utf8("c_address"),
+                int64("c_nationkey"),
+                utf8("c_phone"),
+                decimal("c_acctbal"),
+                utf8("c_mktsegment"),
+                utf8("c_comment"),
+            ],
+            Table::Orders => vec![
+                int64("o_orderkey"),
+                int64("o_custkey"),
+                utf8("o_orderstatus"),
+                decimal("o_totalprice"),
+                date("o_orderdate"),
+                utf8("o_orderpriority"),
+                utf8("o_clerk"),
+                int32("o_shippriority"),
+                utf8("o_comment"),
+            ],
+            Table::Lineitem => vec![
+                int64("l_orderkey"),
+                int64("l_partkey"),
+                int64("l_suppkey"),
+                int32("l_linenumber"),
+                decimal("l_quantity"),
+                decimal("l_extendedprice"),
+                decimal("l_discount"),
+                decimal("l_tax"),
+                utf8("l_returnflag"),
+                utf8("l_linestatus"),
+                date("l_shipdate"),
+                date("l_commitdate"),
+                date("l_receiptdate"),
+                utf8("l_shipinstruct"),
+                utf8("l_shipmode"),
+                utf8("l_comment"),
+            ],
+            Table::Nation => vec![int64("n_nationkey"), utf8("n_name"), int64("n_regionkey"), utf8("n_comment")],
+            Table::Region => vec![int64("r_regionkey"), utf8("r_name"), utf8("r_comment")],
+        };
+        Arc::new(Schema::new(fields))
+    }
+}
+
+const DECIMAL: DataType = DataType::Decimal128(15, 2);
+
+/// Table cardinalities for a scale factor, computed as dbgen does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scale {
+    /// dbgen's integer `scale`: the factor, or 1 below SF 1
+    scale: i64,
+    pub parts: i64,
+    pub suppliers: i64,
+    pub customers: i64,
+    pub orders: i64,
+}
+
+impl Scale {
+    /// Whole factors from 1 up, or fractions below 1 (0.01, 0.1, ...),
+    /// which dbgen applies per mille to every base cardinality
+    pub fn new(factor: f64) -> Result<Self, GooseError> {
+        if !factor.is_finite() || factor <= 0.0 {
+            return Err(GooseError::Unsupported(format!("scale factor {} must be positive", factor)));
+        }
+        if factor < 1.0 {
+            let per_mille = (1000.0 * factor) as i64;
+            let base = |rows: i64| (per_mille * rows / 1000).max(1);
+            return Ok(Scale { scale: 1, parts: base(200_000), suppliers: base(10_000), customers: base(150_000), orders: base(1_500_000) });
+        }
+        // dbgen switches to 64-bit key draws at SF 30000, which are not ported
+        if factor.fract() != 0.0 || factor >= 30_000.0 {
+            return Err(GooseError::Unsupported(format!("scale factor {}: use a whole number below 30000, or a fraction below 1", factor)));
+        }
+        let scale = factor as i64;
+        Ok(Scale { scale, parts: 200_000 * scale, suppliers: 10_000 * scale, customers: 150_000 * scale, orders: 1_500_000 * scale })
+    }
+}
+
+/// Generate `tables` at `factor` into `<dir>/<table>.parquet`
+///
+/// Returns each file written with its row count.
+pub fn generate(dir: &Path, factor: f64, tables: &[Table]) -> Result<Vec<(PathBuf, u64)>, GooseError> {
+    let scale = Scale::new(factor)?;
+    std::fs::create_dir_all(dir)?;
+    tables
+        .iter()
+        .map(|&table| {
+            let path = dir.join(format!("{}.parquet", table.name()));
+            let rows = write_table(table, &scale, &path, WriterProperties::default())?;
+            Ok((path, rows))
+        })
+        .collect()
+}
+
+/// Rows buffered per record batch
+const BATCH_ROWS: usize = 65_536;
+
+/// Write one table as a Parquet file and return its row count
+///
+/// The output depends only on the table and the scale, never on `props`
+/// or the batch size, so two runs produce the same rows.
+pub fn write_table(table: Table, scale: &Scale, path: &Path, props: WriterProperties) -> Result<u64, GooseError> {
+    let schema = table.schema();
+    let mut writer = ArrowWriter::try_new(File::create(path)?, schema.clone(), Some(props))?;
+    let mut sink = Sink { writer: &mut writer, schema, rows: 0 };
+    let pool = TextPool::new();
+    let mut gen = Generator::new(*scale);
+
+    match table {
+        Table::Part => sink.drive(scale.parts, PartColumns::new(), |i, c| c.push(&gen.part(i, &pool)))?,
+        Table::PartSupp => sink.drive(scale.parts, PartSuppColumns::new(), |i, c| {
+            gen.part(i, &pool).supplies.iter().for_each(|s| c.push(s))
+        })?,
+        Table::Supplier => sink.drive(scale.suppliers, SupplierColumns::new(), |i, c| c.push(&gen.supplier(i, &pool)))?,
+        Table::Customer => sink.drive(scale.customers, CustomerColumns::new(), |i, c| c.push(&gen.customer(i, &pool)))?,
+        Table::Orders => sink.drive(scale.orders, OrderColumns::new(), |i, c| c.push(&gen.order(i, &pool)))?,
+        Table::Lineitem => sink.drive(scale.orders, LineitemColumns::new(), |i, c| {
+            gen.order(i, &pool).lines.iter().for_each(|l| c.push(l))
+        })?,
+        Table::Nation => sink.drive(NATIONS.len() as i64, NationColumns::new(), |i, c| c.push(&gen.nation(i, &pool)))?,
+        Table::Region => sink.drive(REGIONS.len() as i64, RegionColumns::new(), |i, c| c.push(&gen.region(i, &pool)))?,
+    }
+    let rows = sink.rows;
+    writer.close()?;
+    Ok(rows)
+}
+
+/// Builders of one table's columns
+trait Columns {
+    fn len(&self) -> usize;
+
+    /// The buffered rows as arrays, leaving the builders empty
+    fn finish(&mut self) -> Vec<ArrayRef>;
+}
+
+struct Sink<'a> {
+    writer: &'a mut ArrowWriter<File>,
+    schema: SchemaRef,
+    rows: u64,
+}
+
+impl Sink<'_> {
+    /// Generate source rows `1..=count`, writing a batch whenever enough
+    /// output rows are buffered
+    fn drive<C: Columns>(&mut self, count: i64, mut columns: C, mut row: impl FnMut(i64, &mut C)) -> Result<(), GooseError> {
+        for index in 1..=count {
+            row(index, &mut columns);
+            if columns.len() >= BATCH_ROWS {
+                self.write(&mut columns)?;
+            }
+        }
+        if columns.len() > 0 {
+            self.write(&mut columns)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, columns: &mut impl Columns) -> Result<(), GooseError> {
+        let batch = RecordBatch::try_new(self.schema.clone(), columns.finish())?;
+        self.rows += batch.num_rows() as u64;
+        self.writer.write(&batch)?;
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Random streams
+// ---------------------------------------------------------------------------
+
+/// Park-Miller "minimal standard" generator: x' = 16807 x mod (2^31 - 1)
+const MULTIPLIER: i64 = 16_807;
+const MODULUS: i64 = 2_147_483_647;
+
+/// Table whose `row_stop` advances a stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Owner {
+    Part,
+    PartSupp,
+    Supplier,
+    Customer,
+    Orders,
+    Lineitem,
+    Nation,
+    Region,
+    /// The text pool's stream, never advanced by rows
+    None,
+}
+
+impl Owner {
+    /// dbgen generates parts with their supplies and orders with their lines
+    fn child(self) -> Option<Owner> {
+        match self {
+            Owner::Part => Some(Owner::PartSupp),
+            Owner::Orders => Some(Owner::Lineitem),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Stream {
+    owner: Owner,
+    seed: i64,
+    /// Draws made for the current row
+    usage: i64,
+    /// Draws reserved per row
+    boundary: i64,
+}
+
+impl Stream {
+    /// dbgen's `UnifInt`: uniform in `low..=high`
+    fn random(&mut self, low: i64, high: i64) -> i64 {
+        self.seed = self.seed * MULTIPLIER % MODULUS;
+        self.usage += 1;
+        low + (self.seed as f64 / MODULUS as f64 * (high - low + 1) as f64) as i64
+    }
+
+    /// `UnifInt(0, MAX_LONG)`, where dbgen computes the range in 32 bits
+    /// and it wraps to -2^31; `tpch_a_rnd` uses the result as random bits
+    fn random_bits(&mut self) -> i64 {
+        self.seed = self.seed * MULTIPLIER % MODULUS;
+        self.usage += 1;
+        (self.seed as f64 / MODULUS as f64 * -2_147_483_648.0) as i64
+    }
+
+    /// dbgen's `row_stop`: skip the draws this row did not use
+    fn row_stop(&mut self) {
+        // `NthElement`: multiply by MULTIPLIER^n by repeated squaring
+        let (mut n, mut multiplier) = (self.boundary - self.usage, MULTIPLIER);
+        while n > 0 {
+            if n % 2 == 1 {
+                self.seed = self.seed * multiplier % MODULUS;
+            }
+            multiplier = multiplier * multiplier % MODULUS;
+            n /= 2;
+        }
+        self.usage = 0;
+    }
+}
+
+// dbgen's stream numbers, in its `Seed` table order
+const P_MFG_SD: usize = 0;
+const P_BRND_SD: usize = 1;
+const P_TYPE_SD: usize = 2;
+const P_SIZE_SD: usize = 3;
+const P_CNTR_SD: usize = 4;
+const TEXT_SD: usize = 5;
+const P_CMNT_SD: usize = 6;
+const PS_QTY_SD: usize = 7;
+const PS_SCST_SD: usize = 8;
+const PS_CMNT_SD: usize = 9;
+const O_CLRK_SD: usize = 11;
+const O_CMNT_SD: usize = 12;
+const O_ODATE_SD: usize = 13;
+const L_QTY_SD: usize = 14;
+const L_DCNT_SD: usize = 15;
+const L_TAX_SD: usize = 16;
+const L_SHIP_SD: usize = 17;
+const L_SMODE_SD: usize = 18;
+const L_PKEY_SD: usize = 19;
+const L_SKEY_SD: usize = 20;
+const L_SDTE_SD: usize = 21;
+const L_CDTE_SD: usize = 22;
+const L_RDTE_SD: usize = 23;
+const L_RFLG_SD: usize = 24;
+const L_CMNT_SD: usize = 25;
+const C_ADDR_SD: usize = 26;
+const C_NTRG_SD: usize = 27;
+const C_PHNE_SD: usize = 28;
+const C_ABAL_SD: usize = 29;
+const C_MSEG_SD: usize = 30;
+const C_CMNT_SD: usize = 31;
+const S_ADDR_SD: usize = 32;
+const S_NTRG_SD: usize = 33;
+const S_PHNE_SD: usize = 34;
+const S_ABAL_SD: usize = 35;
+const S_CMNT_SD: usize = 36;
+const P_NAME_SD: usize = 37;
+const O_PRIO_SD: usize = 38;
+const O_CKEY_SD: usize = 40;
+const N_CMNT_SD: usize = 41;
+const R_CMNT_SD: usize = 42;
+const O_LCNT_SD: usize = 43;
+const BBB_OFFSET_SD: usize = 44;
+const BBB_TYPE_SD: usize = 45;
+const BBB_CMNT_SD: usize = 46;
+const BBB_JNK_SD: usize = 47;
+
+/// Owner, initial seed and draws per row of every stream
+const SEEDS: [(Owner, i64, i64); 48] = [
+    (Owner::Part, 1, 1),
+    (Owner::Part, 46_831_694, 1),
+    (Owner::Part, 1_841_581_359, 1),
+    (Owner::Part, 1_193_163_244, 1),
+    (Owner::Part, 727_633_698, 1),
+    (Owner::None, 933_588_178, 1),
+    (Owner::Part, 804_159_733, 2),
+    (Owner::PartSupp, 1_671_059_989, SUPP_PER_PART),
+    (Owner::PartSupp, 1_051_288_424, SUPP_PER_PART),
+    (Owner::PartSupp, 1_961_692_154, SUPP_PER_PART * 2),
+    (Owner::Orders, 1_227_283_347, 1),
+    (Owner::Orders, 1_171_034_773, 1),
+    (Owner::Orders, 276_090_261, 2),
+    (Owner::Orders, 1_066_728_069, 1),
+    (Owner::Lineitem, 209_208_115, MAX_LINES),
+    (Owner::Lineitem, 554_590_007, MAX_LINES),
+    (Owner::Lineitem, 721_958_466, MAX_LINES),
+    (Owner::Lineitem, 1_371_272_478, MAX_LINES),
+    (Owner::Lineitem, 675_466_456, MAX_LINES),
+    (Owner::Lineitem, 1_808_217_256, MAX_LINES),
+    (Owner::Lineitem, 2_095_021_727, MAX_LINES),
+    (Owner::Lineitem, 1_769_349_045, MAX_LINES),
+    (Owner::Lineitem, 904_914_315, MAX_LINES),
+    (Owner::Lineitem, 373_135_028, MAX_LINES),
+    (Owner::Lineitem, 717_419_739, MAX_LINES),
+    (Owner::Lineitem, 1_095_462_486, MAX_LINES * 2),
+    (Owner::Customer, 881_155_353, 9),
+    (Owner::Customer, 1_489_529_863, 1),
+    (Owner::Customer, 1_521_138_112, 3),
+    (Owner::Customer, 298_370_230, 1),
+    (Owner::Customer, 1_140_279_430, 1),
+    (Owner::Customer, 1_335_826_707, 2),
+    (Owner::Supplier, 706_178_559, 9),
+    (Owner::Supplier, 110_356_601, 1),
+    (Owner::Supplier, 884_434_366, 3),
+    (Owner::Supplier, 962_338_209, 1),
+    (Owner::Supplier, 1_341_315_363, 2),
+    (Owner::Part, 709_314_158, COLORS.len() as i64),
+    (Owner::Orders, 591_449_447, 1),
+    (Owner::Lineitem, 431_918_286, 1),
+    (Owner::Orders, 851_767_375, 1),
+    (Owner::Nation, 606_179_079, 2),
+    (Owner::Region, 1_500_869_201, 2),
+    (Owner::Orders, 1_434_868_289, 1),
+    (Owner::Supplier, 263_032_577, 1),
+    (Owner::Supplier, 753_643_799, 1),
+    (Owner::Supplier, 202_794_285, 1),
+    (Owner::Supplier, 715_851_524, 1),
+];
+
+// ---------------------------------------------------------------------------
+// Value domains (TPC-H specification, clause 4.2.2)
+// ---------------------------------------------------------------------------
+
+const SUPP_PER_PART: i64 = 4;
+const MAX_LINES: i64 = 7;
+
+/// 1992-01-01 and 1995-06-17 (dbgen's CURRENTDATE) as days since the epoch
+const START_DAY: i32 = 8_035;
+const CURRENT_DAY: i32 = 9_298;
+
+/// Days from 1992-01-01 through 1998-12-31
+const TOTAL_DAYS: i64 = 2_557;
+
+/// Last order date: late enough orders still ship and arrive by 1998-12-31
+const MAX_ORDER_DAY: i64 = TOTAL_DAYS - (121 + 30) - 1;
+
+const NATIONS: [(&str, i64); 25] = [
+    ("ALGERIA", 0),
+    ("ARGENTINA", 1),
+    ("BRAZIL", 1),
+    ("CANADA", 1),
+    ("EGYPT", 4),
+    ("ETHIOPIA", 0),
+    ("FRANCE", 3),
+    ("GERMANY", 3),
+    ("INDIA", 2),
+    ("INDONESIA", 2),
+    ("IRAN", 4),
+    ("IRAQ", 4),
+    ("JAPAN", 2),
+    ("JORDAN", 4),
+    ("KENYA", 0),
+    ("MOROCCO", 0),
+    ("MOZAMBIQUE", 0),
+    ("PERU", 1),
+    ("CHINA", 2),
+    ("ROMANIA", 3),
+    ("SAUDI ARABIA", 4),
+    ("VIETNAM", 2),
+    ("RUSSIA", 3),
+    ("UNITED KINGDOM", 3),
+    ("UNITED STATES", 1),
+];
+
+const REGIONS: [&str; 5] = ["AFRICA", "AMERICA", "ASIA", "EUROPE", "MIDDLE EAST"];
+
+const COLORS: [&str; 92] = [
+    "almond", "antique", "aquamarine", "azure", "beige", "bisque", "black", "blanched", "blue", "blush", "brown",
+    "burlywood", "burnished", "chartreuse", "chiffon", "chocolate", "coral", "cornflower", "cornsilk", "cream", "cyan",
+    "dark", "deep", "dim", "dodger", "drab", "firebrick", "floral", "forest", "frosted", "gainsboro", "ghost",
+    "goldenrod", "green", "grey", "honeydew", "hot", "indian", "ivory", "khaki", "lace", "lavender", "lawn", "lemon",
+    "light", "lime", "linen", "magenta", "maroon", "medium", "metallic", "midnight", "mint", "misty", "moccasin",
+    "navajo", "navy", "olive", "orange", "orchid", "pale", "papaya", "peach", "peru", "pink", "plum", "powder", "puff",
+    "purple", "red", "rose", "rosy", "royal", "saddle", "salmon", "sandy", "seashell", "sienna", "sky", "slate",
+    "smoke", "snow", "spring", "steel", "tan", "thistle", "tomato", "turquoise", "violet", "wheat", "white", "yellow",
+];
+
+const TYPE_SIZES: [&str; 6] = ["STANDARD", "SMALL", "MEDIUM", "LARGE", "ECONOMY", "PROMO"];
+const TYPE_FINISHES: [&str; 5] = ["ANODIZED", "BURNISHED", "PLATED", "POLISHED", "BRUSHED"];
+const TYPE_METALS: [&str; 5] = ["TIN", "NICKEL", "BRASS", "STEEL", "COPPER"];
+const CONTAINER_SIZES: [&str; 5] = ["SM", "LG", "MED", "JUMBO", "WRAP"];
+const CONTAINER_KINDS: [&str; 8] = ["CASE", "BOX", "BAG", "JAR", "PKG", "PACK", "CAN", "DRUM"];
+const SEGMENTS: [&str; 5] = ["AUTOMOBILE", "BUILDING", "FURNITURE", "MACHINERY", "HOUSEHOLD"];
+const PRIORITIES: [&str; 5] = ["1-URGENT", "2-HIGH", "3-MEDIUM", "4-NOT SPECIFIED", "5-LOW"];
+const INSTRUCTIONS: [&str; 4] = ["DELIVER IN PERSON", "COLLECT COD", "NONE", "TAKE BACK RETURN"];
+const MODES: [&str; 7] = ["REG AIR", "AIR", "RAIL", "SHIP", "TRUCK", "MAIL", "FOB"];
+const RETURN_FLAGS: [&str; 2] = ["R", "A"];
+
+/// Characters of random addresses, indexed by six random bits
+const ALPHA_NUM: &[u8; 64] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ, ";
+
+// ---------------------------------------------------------------------------
+// Row generators
+// ---------------------------------------------------------------------------
+
+struct Part<'p> {
+    key: i64,
+    name: String,
+    manufacturer: i64,
+    brand: i64,
+    kind: String,
+    size: i32,
+    container: String,
+    retail_price: i64,
+    comment: &'p str,
+    supplies: Vec<PartSupp<'p>>,
+}
+
+struct PartSupp<'p> {
+    part: i64,
+    supplier: i64,
+    available: i32,
+    cost: i64,
+    comment: &'p str,
+}
+
+struct Supplier {
+    key: i64,
+    address: String,
+    nation: i64,
+    phone: String,
+    balance: i64,
+    comment: String,
+}
+
+struct Customer<'p> {
+    key: i64,
+    address: String,
+    nation: i64,
+    phone: String,
+    balance: i64,
+    segment: &'static str,
+    comment: &'p str,
+}
+
+struct Order<'p> {
+    key: i64,
+    customer: i64,
+    status: &'static str,
+    total_price: i64,
+    date: i32,
+    priority: &'static str,
+    clerk: i64,
+    comment: &'p str,
+    lines: Vec<Line<'p>>,
+}
+
+struct Line<'p> {
+    order: i64,
+    part: i64,
+    supplier: i64,
+    number: i32,
+    quantity: i64,
+    extended_price: i64,
+    discount: i64,
+    tax: i64,
+    return_flag: &'static str,
+    line_status: &'static str,
+    ship_date: i32,
+    commit_date: i32,
+    receipt_date: i32,
+    instruction: &'static str,
+    mode: &'static str,
+    comment: &'p str,
+}
+
+struct Nation<'p> {
+    key: i64,
+    name: &'static str,
+    region: i64,
+    comment: &'p str,
+}
+
+struct Region<'p> {
+    key: i64,
+    name: &'static str,
+    comment: &'p str,
+}
+
+/// All of dbgen's streams at their current positions
+struct Generator {
+    streams: [Stream; 48],
+    scale: Scale,
+}
+
+impl Generator {
+    fn new(scale: Scale) -> Self {
+        let streams = SEEDS.map(|(owner, seed, boundary)| Stream { owner, seed, usage: 0, boundary });
+        Self { streams, scale }
+    }
+
+    fn random(&mut self, stream: usize, low: i64, high: i64) -> i64 {
+        self.streams[stream].random(low, high)
+    }
+
+    /// dbgen's `pick_str` over a list of equally weighted values
+    fn pick<T: Copy>(&mut self, stream: usize, values: &[T]) -> T {
+        values[self.random(stream, 1, values.len() as i64) as usize - 1]
+    }
+
+    /// dbgen's `TEXT`: a slice of the pool 0.4 to 1.6 times `average` long
+    fn text<'p>(&mut self, stream: usize, average: i64, pool: &'p TextPool) -> &'p str {
+        let (min, max) = ((average as f64 * 0.4) as i64, (average as f64 * 1.6) as i64);
+        let offset = self.random(stream, 0, pool.text.len() as i64 - max) as usize;
+        let len = self.random(stream, min, max) as usize;
+        &pool.text[offset..offset + len]
+    }
+
+    /// dbgen's `V_STR` (`tpch_a_rnd`): random characters, five per draw
+    fn address(&mut self, stream: usize) -> String {
+        let len = self.random(stream, 10, 40);
+        let mut bits = 0;
+        (0..len)
+            .map(|i| {
+                if i % 5 == 0 {
+                    bits = self.streams[stream].random_bits();
+                }
+                let c = ALPHA_NUM[(bits & 0o77) as usize] as char;
+                bits >>= 6;
+                c
+            })
+            .collect()
+    }
+
+    /// Country code from the nation, then three random groups
+    fn phone(&mut self, stream: usize, nation: i64) -> String {
+        let area = self.random(stream, 100, 999);
+        let exchange = self.random(stream, 100, 999);
+        let number = self.random(stream, 1000, 9999);
+        format!("{:02}-{:03}-{:03}-{:04}", 10 + nation % NATIONS.len() as i64, area, exchange, number)
+    }
+
+    /// Advance every stream of `owner` and its child table to the next row
+    fn row_stop(&mut self, owner: Owner) {
+        let child = owner.child();
+        for stream in self.streams.iter_mut().filter(|s| s.owner == owner || Some(s.owner) == child) {
+            stream.row_stop();
+        }
+    }
+
+    /// Supplier of a part's `i`-th supply: spread so each part's four
+    /// suppliers differ and every supplier serves as many parts
+    fn part_supplier(&self, part: i64, i: i64) -> i64 {
+        let suppliers = self.scale.suppliers;
+        (part + i * (suppliers / SUPP_PER_PART + (part - 1) / suppliers)) % suppliers + 1
+    }
+
+    fn part<'p>(&mut self, key: i64, pool: &'p TextPool) -> Part<'p> {
+        // Five distinct colours: the start of a fresh random permutation
+        let mut order: Vec<usize> = (0..COLORS.len()).collect();
+        for i in 0..order.len() {
+            let j = self.random(P_NAME_SD, i as i64, order.len() as i64 - 1) as usize;
+            order.swap(i, j);
+        }
+        let name = order[..5].iter().map(|&i| COLORS[i]).collect::<Vec<_>>().join(" ");
+        let manufacturer = self.random(P_MFG_SD, 1, 5);
+        let brand = manufacturer * 10 + self.random(P_BRND_SD, 1, 5);
+        let kind = self.random(P_TYPE_SD, 1, 150) - 1;
+        let kind = format!(
+            "{} {} {}",
+            TYPE_SIZES[kind as usize / 25],
+            TYPE_FINISHES[kind as usize / 5 % 5],
+            TYPE_METALS[kind as usize % 5]
+        );
+        let size = self.random(P_SIZE_SD, 1, 50) as i32;
+        let container = self.random(P_CNTR_SD, 1, 40) - 1;
+        let container = format!("{} {}", CONTAINER_SIZES[container as usize / 8], CONTAINER_KINDS[container as usize % 8]);
+        let comment = self.text(P_CMNT_SD, 14, pool);
+        let supplies = (0..SUPP_PER_PART)
+            .map(|i| PartSupp {
+                part: key,
+                supplier: self.part_supplier(key, i),
+                available: self.random(PS_QTY_SD, 1, 9_999) as i32,
+                cost: self.random(PS_SCST_SD, 100, 100_000),
+                comment: self.text(PS_CMNT_SD, 124, pool),
+            })
+            .collect();
+        self.row_stop(Owner::Part);
+        Part {
+            key,
+            name,
+            manufacturer,
+            brand,
+            kind,
+            size,
+            container,
+            retail_price: retail_price(key),
+            comment,
+            supplies,
+        }
+    }
+
+    fn supplier(&mut self, key: i64, pool: &TextPool) -> Supplier {
+        let address = self.address(S_ADDR_SD);
+        let nation = self.random(S_NTRG_SD, 0, NATIONS.len() as i64 - 1);
+        let phone = self.phone(S_PHNE_SD, nation);
+        let balance = self.random(S_ABAL_SD, -99_999, 999_999);
+        let mut comment = self.text(S_CMNT_SD, 63, pool).to_string();
+
+        // One supplier in a thousand has a Better Business Bureau remark
+        // (TPC-H Q16 looks for the complaints)
+        let len = comment.len() as i64;
+        let bad_press = self.random(BBB_CMNT_SD, 1, 10_000);
+        let kind = self.random(BBB_TYPE_SD, 0, 100);
+        let noise = self.random(BBB_JNK_SD, 0, len - 19);
+        let offset = self.random(BBB_OFFSET_SD, 0, len - (19 + noise)) as usize;
+        if bad_press <= 10 {
+            let remark = if kind < 50 { "Complaints" } else { "Recommends" };
+            comment.replace_range(offset..offset + 9, "Customer ");
+            let at = offset + 9 + noise as usize;
+            comment.replace_range(at..at + 10, remark);
+        }
+        self.row_stop(Owner::Supplier);
+        Supplier { key, address, nation, phone, balance, comment }
+    }
+
+    fn customer<'p>(&mut self, key: i64, pool: &'p TextPool) -> Customer<'p> {
+        let address = self.address(C_ADDR_SD);
+        let nation = self.random(C_NTRG_SD, 0, NATIONS.len() as i64 - 1);
+        let phone = self.phone(C_PHNE_SD, nation);
+        let balance = self.random(C_ABAL_SD, -99_999, 999_999);
+        let segment = self.pick(C_MSEG_SD, &SEGMENTS);
+        let comment = self.text(C_CMNT_SD, 73, pool);
+        self.row_stop(Owner::Customer);
+        Customer { key, address, nation, phone, balance, segment, comment }
+    }
+
+    /// Order `index` with its lines
+    fn order<'p>(&mut self, index: i64, pool: &'p TextPool) -> Order<'p> {
+        let key = sparse_key(index);
+        // Every third customer never orders
+        let mut customer = self.random(O_CKEY_SD, 1, self.scale.customers);
+        let mut delta = 1;
+        while customer % 3 == 0 {
+            customer = (customer + delta).min(self.scale.customers);
+            delta = -delta;
+        }
+        let date = START_DAY + self.random(O_ODATE_SD, 0, MAX_ORDER_DAY) as i32;
+        let priority = self.pick(O_PRIO_SD, &PRIORITIES);
+        let clerk = self.random(O_CLRK_SD, 1, (self.scale.scale * 1000).max(1000));
+        let comment = self.text(O_CMNT_SD, 49, pool);
+
+        let num_lines = self.random(O_LCNT_SD, 1, MAX_LINES);
+        let mut total_price = 0;
+        let lines: Vec<Line> = (1..=num_lines)
+            .map(|number| {
+                let quantity = self.random(L_QTY_SD, 1, 50);
+                let discount = self.random(L_DCNT_SD, 0, 10);
+                let tax = self.random(L_TAX_SD, 0, 8);
+                let instruction = self.pick(L_SHIP_SD, &INSTRUCTIONS);
+                let mode = self.pick(L_SMODE_SD, &MODES);
+                let comment = self.text(L_CMNT_SD, 27, pool);
+                let part = self.random(L_PKEY_SD, 1, self.scale.parts);
+                let supply = self.random(L_SKEY_SD, 0, SUPP_PER_PART - 1);
+                let supplier = self.part_supplier(part, supply);
+                let extended_price = retail_price(part) * quantity;
+                total_price += extended_price * (100 - discount) / 100 * (100 + tax) / 100;
+
+                let ship_date = date + self.random(L_SDTE_SD, 1, 121) as i32;
+                let commit_date = date + self.random(L_CDTE_SD, 30, 90) as i32;
+                let receipt_date = ship_date + self.random(L_RDTE_SD, 1, 30) as i32;
+                let return_flag = if receipt_date <= CURRENT_DAY { self.pick(L_RFLG_SD, &RETURN_FLAGS) } else { "N" };
+                let line_status = if ship_date > CURRENT_DAY { "O" } else { "F" };
+                Line {
+                    order: key,
+                    part,
+                    supplier,
+                    number: number as i32,
+                    quantity,
+                    extended_price,
+                    discount,
+                    tax,
+                    return_flag,
+                    line_status,
+                    ship_date,
+                    commit_date,
+                    receipt_date,
+                    instruction,
+                    mode,
+                    comment,
+                }
+            })
+            .collect();
+        let shipped = lines.iter().filter(|l| l.line_status == "F").count();
+        let status = match shipped {
+            0 => "O",
+            n if n == lines.len() => "F",
+            _ => "P",
+        };
+        self.row_stop(Owner::Orders);
+        Order { key, customer, status, total_price, date, priority, clerk, comment, lines }
+    }
+
+    fn nation<'p>(&mut self, index: i64, pool: &'p TextPool) -> Nation<'p> {
+        let (name, region) = NATIONS[index as usize - 1];
+        let comment = self.text(N_CMNT_SD, 72, pool);
+        self.row_stop(Owner::Nation);
+        Nation { key: index - 1, name, region, comment }
+    }
+
+    fn region<'p>(&mut self, index: i64, pool: &'p TextPool) -> Region<'p> {
+        let comment = self.text(R_CMNT_SD, 72, pool);
+        self.row_stop(Owner::Region);
+        Region { key: index - 1, name: REGIONS[index as usize - 1], comment }
+    }
+}
+
+/// Price of a part in cents: 900.00 plus up to 200.00 by key, plus
+/// (key mod 1000) dollars
+fn retail_price(part: i64) -> i64 {
+    90_000 + (part / 10) % 20_001 + (part % 1_000) * 100
+}
+
+/// Order keys use the first 8 of every 32 values, leaving gaps for the
+/// refresh functions' inserts
+fn sparse_key(index: i64) -> i64 {
+    (index >> 3 << 5) + (index & 7)
+}
+
+// ---------------------------------------------------------------------------
+// Text
+// ---------------------------------------------------------------------------
+
+/// Bytes of pseudo-English that comments are cut from
+const TEXT_POOL_SIZE: usize = 1 << 20;
+
+const NOUNS: [&str; 24] = [
+    "foxes", "ideas", "theodolites", "pinto beans", "instructions", "dependencies", "excuses", "platelets", "asymptotes",
+    "courts", "dolphins", "multipliers", "sauternes", "warthogs", "frets", "dinos", "attainments", "somas", "patterns",
+    "accounts", "deposits", "packages", "requests", "sheaves",
+];
+const VERBS: [&str; 20] = [
+    "sleep", "wake", "are", "cajole", "haggle", "nag", "use", "boost", "affix", "detect", "integrate", "maintain", "nod",
+    "lose", "solve", "thrash", "promise", "engage", "print", "serve",
+];
+const ADJECTIVES: [&str; 16] = [
+    "furious", "sly", "careful", "blithe", "quick", "fluffy", "slow", "quiet", "ruthless", "regular", "final", "ironic",
+    "even", "bold", "pending", "special",
+];
+const ADVERBS: [&str; 12] = [
+    "sometimes", "always", "never", "furiously", "slyly", "carefully", "blithely", "quickly", "fluffily", "slowly",
+    "quietly", "finally",
+];
+const PREPOSITIONS: [&str; 12] = [
+    "about", "above", "according to", "across", "after", "against", "along", "among", "around", "beneath", "besides", "among the",
+];
+const TERMINATORS: [&str; 6] = [".", ";", ":", "?", "!", "--"];
+
+/// Sentences in the style of dbgen's grammar, drawn from its text stream
+struct TextPool {
+    text: String,
+}
+
+impl TextPool {
+    fn new() -> Self {
+        let (owner, seed, boundary) = SEEDS[TEXT_SD];
+        let mut stream = Stream { owner, seed, usage: 0, boundary };
+        let mut word = |words: &[&'static str]| words[stream.random(0, words.len() as i64 - 1) as usize];
+        let mut text = String::with_capacity(TEXT_POOL_SIZE + 200);
+        while text.len() < TEXT_POOL_SIZE {
+            // [adjective] noun [adverb] verb [preposition the noun] terminator
+            let shape = word(&["", "a", "d", "ad", "p", "ap", "dp", "adp"]);
+            if shape.contains('a') {
+                text.push_str(word(&ADJECTIVES));
+                text.push(' ');
+            }
+            text.push_str(word(&NOUNS));
+            text.push(' ');
+            if shape.contains('d') {
+                text.push_str(word(&ADVERBS));
+                text.push(' ');
+            }
+            text.push_str(word(&VERBS));
+            if shape.contains('p') {
+                text.push(' ');
+                text.push_str(word(&PREPOSITIONS));
+                text.push_str(" the ");
+                text.push_str(word(&NOUNS));
+            }
+            text.push_str(word(&TERMINATORS));
+            text.push(' ');
+        }
+        text.truncate(TEXT_POOL_SIZE);
+        Self { text }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Column builders
+// ---------------------------------------------------------------------------
+
+fn decimal() -> Decimal128Builder {
+    Decimal128Builder::new().with_data_type(DECIMAL)
+}
+
+struct PartColumns {
+    key: Int64Builder,
+    name: StringBuilder,
+    manufacturer: StringBuilder,
+    brand: StringBuilder,
+    kind: StringBuilder,
+    size: Int32Builder,
+    container: StringBuilder,
+    retail_price: Decimal128Builder,
+    comment: StringBuilder,
+}
+
+impl PartColumns {
+    fn new() -> Self {
+        Self {
+            key: Int64Builder::new(),
+            name: StringBuilder::new(),
+            manufacturer: StringBuilder::new(),
+            brand: StringBuilder::new(),
+            kind: StringBuilder::new(),
+            size: Int32Builder::new(),
+            container: StringBuilder::new(),
+            retail_price: decimal(),
+            comment: StringBuilder::new(),
+        }
+    }
+
+    fn push(&mut self, p: &Part) {
+        self.key.append_value(p.key);
+        self.name.append_value(&p.name);
+        self.manufacturer.append_value(format!("Manufacturer#{}", p.manufacturer));
+        self.brand.append_value(format!("Brand#{}", p.brand));
+        self.kind.append_value(&p.kind);
+        self.size.append_value(p.size);
+        self.container.append_value(&p.container);
+        self.retail_price.append_value(p.retail_price as i128);
+        self.comment.append_value(p.comment);
+    }
+}
+
+impl Columns for PartColumns {
+    fn len(&self) -> usize {
+        self.key.len()
+    }
+
+    fn finish(&mut self) -> Vec<ArrayRef> {
+        vec![
+            Arc::new(self.key.finish()),
+            Arc::new(self.name.finish()),
+            Arc::new(self.manufacturer.finish()),
+            Arc::new(self.brand.finish()),
+            Arc::new(self.kind.finish()),
+            Arc::new(self.size.finish()),
+            Arc::new(self.container.finish()),
+            Arc::new(self.retail_price.finish()),
+            Arc::new(self.comment.finish()),
+        ]
+    }
+}
+
+struct PartSuppColumns {
+    part: Int64Builder,
+    supplier: Int64Builder,
+    available: Int32Builder,
+    cost: Decimal128Builder,
+    comment: StringBuilder,
+}
+
+impl PartSuppColumns {
+    fn new() -> Self {
+        Self {
+            part: Int64Builder::new(),
+            supplier: Int64Builder::new(),
+            available: Int32Builder::new(),
+            cost: decimal(),
+            comment: StringBuilder::new(),
+        }
+    }
+
+    fn push(&mut self, s: &PartSupp) {
+        self.part.append_value(s.part);
+        self.supplier.append_value(s.supplier);
+        self.available.append_value(s.available);
+        self.cost.append_value(s.cost as i128);
+        self.comment.append_value(s.comment);
+    }
+}
+
+impl Columns for PartSuppColumns {
+    fn len(&self) -> usize {
+        self.part.len()
+    }
+
+    fn finish(&mut self) -> Vec<ArrayRef> {
+        vec![
+            Arc::new(self.part.finish()),
+            Arc::new(self.supplier.finish()),
+            Arc::new(self.available.finish()),
+            Arc::new(self.cost.finish()),
+            Arc::new(self.comment.finish()),
+        ]
+    }
+}
+
+struct SupplierColumns {
+    key: Int64Builder,
+    name: StringBuilder,
+    address: StringBuilder,
+    nation: Int64Builder,
+    phone: StringBuilder,
+    balance: Decimal128Builder,
+    comment: StringBuilder,
+}
+
+impl SupplierColumns {
+    fn new() -> Self {
+        Self {
+            key: Int64Builder::new(),
+            name: StringBuilder::new(),
+            address: StringBuilder::new(),
+            nation: Int64Builder::new(),
+            phone: StringBuilder::new(),
+            balance: decimal(),
+            comment: StringBuilder::new(),
+        }
+    }
+
+    fn push(&mut self, s: &Supplier) {
+        self.key.append_value(s.key);
+        self.name.append_value(format!("Supplier#{:09}", s.key));
+        self.address.append_value(&s.address);
+        self.nation.append_value(s.nation);
+        self.phone.append_value(&s.phone);
+        self.balance.append_value(s.balance as i128);
+        self.comment.append_value(&s.comment);
+    }
+}
+
+impl Columns for SupplierColumns {
+    fn len(&self) -> usize {
+        self.key.len()
+    }
+
+    fn finish(&mut self) -> Vec<ArrayRef> {
+        vec![
+            Arc::new(self.key.finish()),
+            Arc::new(self.name.finish()),
+            Arc::new(self.address.finish()),
+            Arc::new(self.nation.finish()),
+            Arc::new(self.phone.finish()),
+            Arc::new(self.balance.finish()),
+            Arc::new(self.comment.finish()),
+        ]
+    }
+}
+
+struct CustomerColumns {
+    key: Int64Builder,
+    name: StringBuilder,
+    address: StringBuilder,
+    nation: Int64Builder,
+    phone: StringBuilder,
+    balance: Decimal128Builder,
+    segment: StringBuilder,
+    comment: StringBuilder,
+}
+
+impl CustomerColumns {
+    fn new() -> Self {
+        Self {
+            key: Int64Builder::new(),
+            name: StringBuilder::new(),
+            address: StringBuilder::new(),
+            nation: Int64Builder::new(),
+            phone: StringBuilder::new(),
+            balance: decimal(),
+            segment: StringBuilder::new(),
+            comment: StringBuilder::new(),
+        }
+    }
+
+    fn push(&mut self, c: &Customer) {
+        self.key.append_value(c.key);
+        self.name.append_value(format!("Customer#{:09}", c.key));
+        self.address.append_value(&c.address);
+        self.nation.append_value(c.nation);
+        self.phone.append_value(&c.phone);
+        self.balance.append_value(c.balance as i128);
+        self.segment.append_value(c.segment);
+        self.comment.append_value(c.comment);
+    }
+}
+
+impl Columns for CustomerColumns {
+    fn len(&self) -> usize {
+        self.key.len()
+    }
+
+    fn finish(&mut self) -> Vec<ArrayRef> {
+        vec![
+            Arc::new(self.key.finish()),
+            Arc::new(self.name.finish()),
+            Arc::new(self.address.finish()),
+            Arc::new(self.nation.finish()),
+            Arc::new(self.phone.finish()),
+            Arc::new(self.balance.finish()),
+            Arc::new(self.segment.finish()),
+            Arc::new(self.comment.finish()),
+        ]
+    }
+}
+
+struct OrderColumns {
+    key: Int64Builder,
+    customer: Int64Builder,
+    status: StringBuilder,
+    total_price: Decimal128Builder,
+    date: Date32Builder,
+    priority: StringBuilder,
+    clerk: StringBuilder,
+    ship_priority: Int32Builder,
+    comment: StringBuilder,
+}
+
+impl OrderColumns {
+    fn new() -> Self {
+        Self {
+            key: Int64Builder::new(),
+            customer: Int64Builder::new(),
+            status: StringBuilder::new(),
+            total_price: decimal(),
+            date: Date32Builder::new(),
+            priority: StringBuilder::new(),
+            clerk: StringBuilder::new(),
+            ship_priority: Int32Builder::new(),
+            comment: StringBuilder::new(),
+        }
+    }
+
+    fn push(&mut self, o: &Order) {
+        self.key.append_value(o.key);
+        self.customer.append_value(o.customer);
+        self.status.append_value(o.status);
+        self.total_price.append_value(o.total_price as i128);
+        self.date.append_value(o.date);
+        self.priority.append_value(o.priority);
+        self.clerk.append_value(format!("Clerk#{:09}", o.clerk));
+        self.ship_priority.append_value(0);
+        self.comment.append_value(o.comment);
+    }
+}
+
+impl Columns for OrderColumns {
+    fn len(&self) -> usize {
+        self.key.len()
+    }
+
+    fn finish(&mut self) -> Vec<ArrayRef> {
+        vec![
+            Arc::new(self.key.finish()),
+            Arc::new(self.customer.finish()),
+            Arc::new(self.status.finish()),
+            Arc::new(self.total_price.finish()),
+            Arc::new(self.date.finish()),
+            Arc::new(self.priority.finish()),
+            Arc::new(self.clerk.finish()),
+            Arc::new(self.ship_priority.finish()),
+            Arc::new(self.comment.finish()),
+        ]
+    }
+}
+
+struct LineitemColumns {
+    order: Int64Builder,
+    part: Int64Builder,
+    supplier: Int64Builder,
+    number: Int32Builder,
+    quantity: Decimal128Builder,
+    extended_price: Decimal128Builder,
+    discount: Decimal128Builder,
+    tax: Decimal128Builder,
+    return_flag: StringBuilder,
+    line_status: StringBuilder,
+    ship_date: Date32Builder,
+    commit_date: Date32Builder,
+    receipt_date: Date32Builder,
+    instruction: StringBuilder,
+    mode: StringBuilder,
+    comment: StringBuilder,
+}
+
+impl LineitemColumns {
+    fn new() -> Self {
+        Self {
+            order: Int64Builder::new(),
+            part: Int64Builder::new(),
+            supplier: Int64Builder::new(),
+            number: Int32Builder::new(),
+            quantity: decimal(),
+            extended_price: decimal(),
+            discount: decimal(),
+            tax: decimal(),
+            return_flag: StringBuilder::new(),
+            line_status: StringBuilder::new(),
+            ship_date: Date32Builder::new(),
+            commit_date: Date32Builder::new(),
+            receipt_date: Date32Builder::new(),
+            instruction: StringBuilder::new(),
+            mode: StringBuilder::new(),
+            comment: StringBuilder::new(),
+        }
+    }
+
+    fn push(&mut self, l: &Line) {
+        self.order.append_value(l.order);
+        self.part.append_value(l.part);
+        self.supplier.append_value(l.supplier);
+        self.number.append_value(l.number);
+        // Whole units at scale 2
+        self.quantity.append_value(l.quantity as i128 * 100);
+        self.extended_price.append_value(l.extended_price as i128);
+        self.discount.append_value(l.discount as i128);
+        self.tax.append_value(l.tax as i128);
+        self.return_flag.append_value(l.return_flag);
+        self.line_status.append_value(l.line_status);
+        self.ship_date.append_value(l.ship_date);
+        self.commit_date.append_value(l.commit_date);
+        self.receipt_date.append_value(l.receipt_date);
+        self.instruction.append_value(l.instruction);
+        self.mode.append_value(l.mode);
+        self.comment.append_value(l.comment);
+    }
+}
+
+impl Columns for LineitemColumns {
+    fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    fn finish(&mut self) -> Vec<ArrayRef> {
+        vec![
+            Arc::new(self.order.finish()),
+            Arc::new(self.part.finish()),
+            Arc::new(self.supplier.finish()),
+            Arc::new(self.number.finish()),
+            Arc::new(self.quantity.finish()),
+            Arc::new(self.extended_price.finish()),
+            Arc::new(self.discount.finish()),
+            Arc::new(self.tax.finish()),
+            Arc::new(self.return_flag.finish()),
+            Arc::new(self.line_status.finish()),
+            Arc::new(self.ship_date.finish()),
+            Arc::new(self.commit_date.finish()),
+            Arc::new(self.receipt_date.finish()),
+            Arc::new(self.instruction.finish()),
+            Arc::new(self.mode.finish()),
+            Arc::new(self.comment.finish()),
+        ]
+    }
+}
+
+struct NationColumns {
+    key: Int64Builder,
+    name: StringBuilder,
+    region: Int64Builder,
+    comment: StringBuilder,
+}
+
+impl NationColumns {
+    fn new() -> Self {
+        Self { key: Int64Builder::new(), name: StringBuilder::new(), region: Int64Builder::new(), comment: StringBuilder::new() }
+    }
+
+    fn push(&mut self, n: &Nation) {
+        self.key.append_value(n.key);
+        self.name.append_value(n.name);
+        self.region.append_value(n.region);
+        self.comment.append_value(n.comment);
+    }
+}
+
+impl Columns for NationColumns {
+    fn len(&self) -> usize {
+        self.key.len()
+    }
+
+    fn finish(&mut self) -> Vec<ArrayRef> {
+        vec![
+            Arc::new(self.key.finish()),
+            Arc::new(self.name.finish()),
+            Arc::new(self.region.finish()),
+            Arc::new(self.comment.finish()),
+        ]
+    }
+}
+
+struct RegionColumns {
+    key: Int64Builder,
+    name: StringBuilder,
+    comment: StringBuilder,
+}
+
+impl RegionColumns {
+    fn new() -> Self {
+        Self { key: Int64Builder::new(), name: StringBuilder::new(), comment: StringBuilder::new() }
+    }
+
+    fn push(&mut self, r: &Region) {
+        self.key.append_value(r.key);
+        self.name.append_value(r.name);
+        self.comment.append_value(r.comment);
+    }
+}
+
+impl Columns for RegionColumns {
+    fn len(&self) -> usize {
+        self.key.len()
+    }
+
+    fn finish(&mut self) -> Vec<ArrayRef> {
+        vec![Arc::new(self.key.finish()), Arc::new(self.name.finish()), Arc::new(self.comment.finish())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_path;
+    use arrow::array::{AsArray, Int64Array};
+    use arrow::datatypes::Int64Type;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn read(path: &Path) -> Vec<RecordBatch> {
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap().build().unwrap();
+        reader.collect::<Result<_, _>>().unwrap()
+    }
+
+    fn int64s(batches: &[RecordBatch], column: &str) -> Vec<i64> {
+        let arrays = batches.iter().map(|b| b.column_by_name(column).unwrap().as_primitive::<Int64Type>().clone());
+        arrays.flat_map(|a: Int64Array| a.values().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_scale() {
+        let sf = Scale::new(0.01).unwrap();
+        assert_eq!((sf.parts, sf.suppliers, sf.customers, sf.orders), (2_000, 100, 1_500, 15_000));
+        let sf = Scale::new(10.0).unwrap();
+        assert_eq!((sf.parts, sf.suppliers, sf.customers, sf.orders), (2_000_000, 100_000, 1_500_000, 15_000_000));
+        for bad in [0.0, -1.0, 1.5, f64::NAN, 30_000.0] {
+            assert!(matches!(Scale::new(bad), Err(GooseError::Unsupported(_))), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_stream() {
+        let mut stream = Stream { owner: Owner::None, seed: 1, usage: 0, boundary: 5 };
+        assert_eq!(stream.random(0, MODULUS - 1), 16_807);
+        // Skipping lands where five draws would have
+        let mut drawn = stream;
+        (0..4).for_each(|_| {
+            drawn.random(1, 10);
+        });
+        stream.row_stop();
+        assert_eq!((stream.seed, stream.usage), (drawn.seed, 0));
+        assert_eq!(sparse_key(7), 7);
+        assert_eq!((sparse_key(8), sparse_key(9)), (32, 33));
+    }
+
+    #[test]
+    fn test_generate() {
+        let dir = temp_path("dbgen").with_extension("");
+        let files = generate(&dir, 0.01, &Table::ALL).unwrap();
+        let rows: Vec<u64> = files.iter().map(|(_, rows)| *rows).collect();
+        // dbgen's row counts at SF 0.01
+        assert_eq!(rows, vec![2_000, 100, 8_000, 1_500, 15_000, 60_175, 25, 5]);
+
+        let lineitem = read(&dir.join("lineitem.parquet"));
+        assert_eq!(lineitem[0].schema(), Table::Lineitem.schema());
+        let orders = int64s(&read(&dir.join("orders.parquet")), "o_orderkey");
+        assert_eq!(&orders[..9], &[1, 2, 3, 4, 5, 6, 7, 32, 33]);
+        let line_orders = int64s(&lineitem, "l_orderkey");
+        assert!(line_orders.iter().all(|k| orders.binary_search(k).is_ok()));
+        assert!(int64s(&lineitem, "l_suppkey").iter().all(|&s| (1..=100).contains(&s)));
+
+        // Every part has four distinct suppliers
+        let partsupp = read(&dir.join("partsupp.parquet"));
+        let suppliers = int64s(&partsupp, "ps_suppkey");
+        assert!(suppliers.chunks(4).all(|s| (1..4).all(|i| !s[..i].contains(&s[i]))));
+
+        // Same input, same rows
+        let again = temp_path("dbgen-again");
+        write_table(Table::Lineitem, &Scale::new(0.01).unwrap(), &again, WriterProperties::default()).unwrap();
+        assert_eq!(read(&again), lineitem);
+
+        std::fs::remove_file(again).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,446 @@
+//! Exact decimal aggregation for TPC-H Q1
+//!
+//! The default path casts every `Decimal128` column to `Float64`, which
+//! accumulates rounding error in `sum_disc_price` and `sum_charge` at large
+//! scale factors. This aggregator keeps the inputs as their unscaled i128
+//! values instead: `disc_price = price * (1 - discount)` and
+//! `charge = disc_price * (1 + tax)` are evaluated in integers with the
+//! scales added up, and every sum is exact, matching DuckDB's
+//! `sum_no_overflow` to the last digit. NULLs are handled as in
+//! `Aggregator`.
+//!
+//! The accumulator is generic over `IntegerSum`: the query picks i64 or
+//! i128 where the footer statistics prove the sums fit, and checked i128
+//! otherwise (see `integer_sum::choose_width`). `DecimalAggregator::with_keys`
+//! is the plain i128 one.
+
+use std::fmt;
+
+use arrow::array::{Array, Decimal128Array};
+
+use crate::aggregator::{avg_or_null, sort_results, sum_or_null, NullCounts, NullKeyGroups, PerfectHash, QueryResult};
+use crate::error::GooseError;
+use crate::integer_sum::{overflow_error, IntegerSum, SumWidth};
+use crate::keys::key_byte;
+
+/// A Decimal128 value: `value * 10^-scale`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Decimal {
+    pub value: i128,
+    pub scale: i8,
+}
+
+impl Decimal {
+    pub fn new(value: i128, scale: i8) -> Self {
+        Self { value, scale }
+    }
+
+    /// Nearest f64 (rounding happens once, at the end)
+    pub fn to_f64(&self) -> f64 {
+        self.value as f64 / 10f64.powi(self.scale as i32)
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.scale <= 0 {
+            return write!(f, "{}", self.value * 10i128.pow((-self.scale) as u32));
+        }
+        let divisor = 10i128.pow(self.scale as u32);
+        let sign = if self.value < 0 { "-" } else { "" };
+        let abs = self.value.unsigned_abs();
+        write!(
+            f,
+            "{}{}.{:0width$}",
+            sign,
+            abs / divisor as u128,
+            abs % divisor as u128,
+            width = self.scale as usize
+        )
+    }
+}
+
+/// Exact sums of one Q1 group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExactSums {
+    pub sum_qty: Decimal,
+    pub sum_base_price: Decimal,
+    pub sum_disc_price: Decimal,
+    pub sum_charge: Decimal,
+    pub sum_discount: Decimal,
+}
+
+/// Scales of the four Q1 measure columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecimalScales {
+    pub quantity: i8,
+    pub price: i8,
+    pub discount: i8,
+    pub tax: i8,
+}
+
+impl DecimalScales {
+    /// Scale of `price * (1 - discount)`
+    pub fn disc_price(&self) -> i8 {
+        self.price + self.discount
+    }
+
+    /// Scale of `price * (1 - discount) * (1 + tax)`
+    pub fn charge(&self) -> i8 {
+        self.price + self.discount + self.tax
+    }
+}
+
+/// Exact aggregation state for a single group (unscaled values)
+///
+/// `count` needs no check: it is bounded by the rows of the scan.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DecimalAggState<S = i128> {
+    pub sum_qty: S,
+    pub sum_base_price: S,
+    pub sum_disc_price: S,
+    pub sum_charge: S,
+    pub sum_discount: S,
+    pub count: u64,
+    pub nulls: NullCounts,
+}
+
+impl<S: IntegerSum> DecimalAggState<S> {
+    /// Merge another state into this one
+    pub fn merge(&mut self, other: &DecimalAggState<S>) -> Result<(), GooseError> {
+        let add = |a: S, b: S| a.add(b).ok_or_else(overflow_error);
+        self.sum_qty = add(self.sum_qty, other.sum_qty)?;
+        self.sum_base_price = add(self.sum_base_price, other.sum_base_price)?;
+        self.sum_disc_price = add(self.sum_disc_price, other.sum_disc_price)?;
+        self.sum_charge = add(self.sum_charge, other.sum_charge)?;
+        self.sum_discount = add(self.sum_discount, other.sum_discount)?;
+        self.count += other.count;
+        self.nulls.merge(&other.nulls);
+        Ok(())
+    }
+}
+
+/// Perfect hash aggregator with exact decimal sums accumulated in `S`
+pub struct DecimalAggregator<S = i128> {
+    pub states: Vec<DecimalAggState<S>>,
+    /// Groups with a NULL key part
+    null_keys: NullKeyGroups<DecimalAggState<S>>,
+    keys: PerfectHash,
+    /// Fixed by the first batch; later batches must agree
+    scales: Option<DecimalScales>,
+}
+
+impl DecimalAggregator {
+    /// Aggregator with unchecked i128 sums
+    pub fn with_keys(keys: PerfectHash) -> Self {
+        Self::with_accumulator(keys)
+    }
+}
+
+impl<S: IntegerSum> DecimalAggregator<S> {
+    /// Aggregator accumulating in `S`: `i64` or `i128` where
+    /// `integer_sum::choose_width` proved it safe, `Checked` otherwise
+    pub fn with_accumulator(keys: PerfectHash) -> Self {
+        Self {
+            states: vec![DecimalAggState::default(); keys.num_slots()],
+            null_keys: NullKeyGroups::default(),
+            keys,
+            scales: None,
+        }
+    }
+
+    /// The accumulator width of this aggregator
+    pub fn width(&self) -> SumWidth {
+        S::WIDTH
+    }
+
+    /// Merge another aggregator's partial states into this one
+    pub fn merge(&mut self, other: &DecimalAggregator<S>) -> Result<(), GooseError> {
+        debug_assert_eq!(self.keys, other.keys);
+        if let Some(scales) = other.scales {
+            self.check_scales(scales)?;
+        }
+        for (state, other_state) in self.states.iter_mut().zip(&other.states) {
+            state.merge(other_state)?;
+        }
+        for (key, other_state) in other.null_keys.iter() {
+            self.null_keys.get_mut(*key).merge(other_state)?;
+        }
+        Ok(())
+    }
+
+    /// Aggregate a batch with the fused expressions evaluated in i128
+    #[allow(clippy::too_many_arguments)]
+    pub fn aggregate_batch(
+        &mut self,
+        mask: &arrow::array::BooleanArray,
+        returnflag: &dyn Array,
+        linestatus: &dyn Array,
+        quantity: &Decimal128Array,
+        price: &Decimal128Array,
+        discount: &Decimal128Array,
+        tax: &Decimal128Array,
+    ) -> Result<(), GooseError> {
+        self.check_scales(DecimalScales {
+            quantity: quantity.scale(),
+            price: price.scale(),
+            discount: discount.scale(),
+            tax: tax.scale(),
+        })?;
+
+        // 1 at the discount / tax scale
+        let one_d = S::from_unscaled(10i128.pow(discount.scale().max(0) as u32));
+        let one_t = S::from_unscaled(10i128.pow(tax.scale().max(0) as u32));
+        let [add, sub, mul] = [S::add, S::sub, S::mul].map(|op| move |a: S, b: S| op(a, b).ok_or_else(overflow_error));
+
+        let q_vals = quantity.values();
+        let p_vals = price.values();
+        let d_vals = discount.values();
+        let t_vals = tax.values();
+        let num_slots = self.keys.num_slots();
+        let nullable = [quantity, price, discount, tax].iter().any(|a| a.null_count() > 0);
+
+        for i in 0..mask.len() {
+            // A NULL predicate result does not select the row
+            if !mask.is_valid(i) || !mask.value(i) {
+                continue;
+            }
+
+            let flag = key_byte(returnflag, i)?;
+            let status = key_byte(linestatus, i)?;
+            let state = match (flag, status) {
+                (Some(f), Some(s)) => {
+                    let idx = self.keys.slot(f, s);
+                    if idx >= num_slots {
+                        return Err(GooseError::InvalidData(format!(
+                            "unexpected group key ({:?}, {:?}) outside the perfect hash domain",
+                            f as char, s as char
+                        )));
+                    }
+                    &mut self.states[idx]
+                }
+                key => self.null_keys.get_mut(key),
+            };
+
+            state.count += 1;
+            // NULL slots hold arbitrary values; zero them out of the sums
+            let (valid, (disc_price_valid, charge_valid)) = match nullable {
+                true => {
+                    let valid = [quantity.is_valid(i), price.is_valid(i), discount.is_valid(i), tax.is_valid(i)];
+                    (valid, state.nulls.record(valid[0], valid[1], valid[2], valid[3]))
+                }
+                false => ([true; 4], (true, true)),
+            };
+            let value = |values: &[i128], valid: bool| S::from_unscaled(if valid { values[i] } else { 0 });
+            let (q, p, d, t) = (value(q_vals, valid[0]), value(p_vals, valid[1]), value(d_vals, valid[2]), value(t_vals, valid[3]));
+            let disc_price = if disc_price_valid { mul(p, sub(one_d, d)?)? } else { S::default() };
+            let charge = if charge_valid { mul(disc_price, add(one_t, t)?)? } else { S::default() };
+            state.sum_qty = add(state.sum_qty, q)?;
+            state.sum_base_price = add(state.sum_base_price, p)?;
+            state.sum_disc_price = add(state.sum_disc_price, disc_price)?;
+            state.sum_charge = add(state.sum_charge, charge)?;
+            state.sum_discount = add(state.sum_discount, d)?;
+        }
+
+        Ok(())
+    }
+
+    /// Get results sorted by (returnflag, linestatus), with `exact` populated
+    pub fn get_results(&self) -> Vec<QueryResult> {
+        let scales = self.scales.unwrap_or(DecimalScales { quantity: 0, price: 0, discount: 0, tax: 0 });
+
+        let slots = self.states.iter().enumerate().map(|(idx, state)| {
+            let (flag, status) = self.keys.key(idx);
+            ((Some(flag), Some(status)), state)
+        });
+        let mut results: Vec<QueryResult> = slots
+            .chain(self.null_keys.iter().map(|(key, state)| (*key, state)))
+            .filter(|(_, state)| state.count > 0)
+            .map(|((flag, status), state)| {
+                let exact = ExactSums {
+                    sum_qty: Decimal::new(state.sum_qty.to_i128(), scales.quantity),
+                    sum_base_price: Decimal::new(state.sum_base_price.to_i128(), scales.price),
+                    sum_disc_price: Decimal::new(state.sum_disc_price.to_i128(), scales.disc_price()),
+                    sum_charge: Decimal::new(state.sum_charge.to_i128(), scales.charge()),
+                    sum_discount: Decimal::new(state.sum_discount.to_i128(), scales.discount),
+                };
+                let (count, nulls) = (state.count, &state.nulls);
+                QueryResult {
+                    returnflag: flag,
+                    linestatus: status,
+                    sum_qty: sum_or_null(exact.sum_qty.to_f64(), count, nulls.quantity),
+                    sum_base_price: sum_or_null(exact.sum_base_price.to_f64(), count, nulls.price),
+                    sum_disc_price: sum_or_null(exact.sum_disc_price.to_f64(), count, nulls.disc_price),
+                    sum_charge: sum_or_null(exact.sum_charge.to_f64(), count, nulls.charge),
+                    avg_qty: avg_or_null(exact.sum_qty.to_f64(), count, nulls.quantity),
+                    avg_price: avg_or_null(exact.sum_base_price.to_f64(), count, nulls.price),
+                    avg_disc: avg_or_null(exact.sum_discount.to_f64(), count, nulls.discount),
+                    count,
+                    exact: Some(exact),
+                    moments: None,
+                    distinct: None,
+                    price_quantiles: None,
+                }
+            })
+            .collect();
+
+        // Sort by (returnflag, linestatus) as per ORDER BY clause
+        sort_results(&mut results);
+
+        results
+    }
+
+    fn check_scales(&mut self, scales: DecimalScales) -> Result<(), GooseError> {
+        match self.scales {
+            None => {
+                self.scales = Some(scales);
+                Ok(())
+            }
+            Some(existing) if existing == scales => Ok(()),
+            Some(existing) => Err(GooseError::InvalidData(format!(
+                "decimal scales changed between batches: {:?} vs {:?}",
+                existing, scales
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integer_sum::Checked;
+    use arrow::array::{BooleanArray, StringArray};
+
+    fn dec(values: Vec<i128>) -> Decimal128Array {
+        Decimal128Array::from(values).with_precision_and_scale(15, 2).unwrap()
+    }
+
+    #[test]
+    fn test_decimal_display() {
+        assert_eq!(Decimal::new(12345, 2).to_string(), "123.45");
+        assert_eq!(Decimal::new(-5, 3).to_string(), "-0.005");
+        assert_eq!(Decimal::new(7, 0).to_string(), "7");
+        assert_eq!(Decimal::new(7, -2).to_string(), "700");
+    }
+
+    #[test]
+    fn test_exact_sums() {
+        // 0.1 + 0.2 style inputs that do not round-trip through f64 sums
+        let mask = BooleanArray::from(vec![true, true, false]);
+        let flags = StringArray::from(vec!["A", "A", "A"]);
+        let statuses = StringArray::from(vec!["F", "F", "F"]);
+        let q = dec(vec![10, 20, 99]);
+        let p = dec(vec![12_345_678, 1, 99]);
+        let d = dec(vec![7, 10, 0]);
+        let t = dec(vec![3, 8, 0]);
+
+        let mut agg = DecimalAggregator::with_keys(PerfectHash::tpch());
+        agg.aggregate_batch(&mask, &flags, &statuses, &q, &p, &d, &t).unwrap();
+        let results = agg.get_results();
+        assert_eq!(results.len(), 1);
+        let exact = results[0].exact.unwrap();
+
+        assert_eq!(exact.sum_qty, Decimal::new(30, 2));
+        assert_eq!(exact.sum_base_price, Decimal::new(12_345_679, 2));
+        // 123456.78 * 0.93 + 0.01 * 0.90
+        assert_eq!(exact.sum_disc_price.to_string(), "114814.8144");
+        // 123456.78 * 0.93 * 1.03 + 0.01 * 0.90 * 1.08
+        assert_eq!(exact.sum_charge.to_string(), "118259.259282");
+        assert_eq!(results[0].count, 2);
+    }
+
+    #[test]
+    fn test_scale_mismatch_is_error() {
+        let mask = BooleanArray::from(vec![true]);
+        let flags = StringArray::from(vec!["N"]);
+        let statuses = StringArray::from(vec!["O"]);
+        let v = dec(vec![1]);
+        let w = Decimal128Array::from(vec![1]).with_precision_and_scale(15, 3).unwrap();
+
+        let mut agg = DecimalAggregator::with_keys(PerfectHash::tpch());
+        agg.aggregate_batch(&mask, &flags, &statuses, &v, &v, &v, &v).unwrap();
+        let err = agg.aggregate_batch(&mask, &flags, &statuses, &w, &v, &v, &v).unwrap_err();
+        assert!(matches!(err, GooseError::InvalidData(_)));
+    }
+
+    #[test]
+    fn test_nulls() {
+        let mask = BooleanArray::from(vec![true, true, true]);
+        let flags = StringArray::from(vec![Some("R"), Some("R"), None]);
+        let statuses = StringArray::from(vec!["F", "F", "F"]);
+        let q = Decimal128Array::from(vec![Some(100), None, Some(300)]).with_precision_and_scale(15, 2).unwrap();
+        let p = dec(vec![1000, 2000, 3000]);
+        let d = Decimal128Array::from(vec![None, Some(0), Some(0)]).with_precision_and_scale(15, 2).unwrap();
+        let t = dec(vec![0, 0, 0]);
+
+        let mut agg = DecimalAggregator::with_keys(PerfectHash::tpch());
+        agg.aggregate_batch(&mask, &flags, &statuses, &q, &p, &d, &t).unwrap();
+        let results = agg.get_results();
+        assert_eq!(results.len(), 2);
+
+        let r = &results[0];
+        assert_eq!((r.returnflag, r.count), (Some(b'R'), 2));
+        assert_eq!((r.sum_qty, r.avg_qty), (Some(1.0), Some(1.0)));
+        assert_eq!((r.sum_base_price, r.avg_price), (Some(30.0), Some(15.0)));
+        // Only the second row has a discount
+        assert_eq!((r.sum_disc_price, r.avg_disc), (Some(20.0), Some(0.0)));
+        assert_eq!(r.exact.unwrap().sum_charge.to_string(), "20.000000");
+
+        assert_eq!((results[1].returnflag, results[1].count, results[1].sum_qty), (None, 1, Some(3.0)));
+    }
+
+    #[test]
+    fn test_accumulator_widths() {
+        let mask = BooleanArray::from(vec![true, true, true, false]);
+        let flags = StringArray::from(vec!["A", "N", "A", "R"]);
+        let statuses = StringArray::from(vec!["F", "O", "F", "F"]);
+        let q = dec(vec![100, 250, 4900, 1]);
+        let p = dec(vec![10_494_950, 90_000, 1_234_567, 1]);
+        let d = dec(vec![10, 0, 4, 1]);
+        let t = dec(vec![8, 2, 0, 1]);
+
+        let mut wide = DecimalAggregator::with_keys(PerfectHash::tpch());
+        wide.aggregate_batch(&mask, &flags, &statuses, &q, &p, &d, &t).unwrap();
+        let mut narrow = DecimalAggregator::<i64>::with_accumulator(PerfectHash::tpch());
+        narrow.aggregate_batch(&mask, &flags, &statuses, &q, &p, &d, &t).unwrap();
+        let mut checked = DecimalAggregator::<Checked>::with_accumulator(PerfectHash::tpch());
+        checked.aggregate_batch(&mask, &flags, &statuses, &q, &p, &d, &t).unwrap();
+        assert_eq!((narrow.width(), checked.width()), (SumWidth::I64, SumWidth::Checked));
+
+        let sums = |results: Vec<QueryResult>| results.iter().map(|r| (r.count, r.exact)).collect::<Vec<_>>();
+        let expected = sums(wide.get_results());
+        assert_eq!(sums(narrow.get_results()), expected);
+        assert_eq!(sums(checked.get_results()), expected);
+    }
+
+    #[test]
+    fn test_checked_overflow_is_error() {
+        let mask = BooleanArray::from(vec![true, true]);
+        let flags = StringArray::from(vec!["A", "A"]);
+        let statuses = StringArray::from(vec!["F", "F"]);
+        let big = Decimal128Array::from(vec![i128::MAX / 2 + 1, 1]).with_precision_and_scale(38, 2).unwrap();
+        let small = Decimal128Array::from(vec![1, 1]).with_precision_and_scale(38, 2).unwrap();
+
+        // Two large quantities: the sum itself leaves i128
+        let mut agg = DecimalAggregator::<Checked>::with_accumulator(PerfectHash::tpch());
+        let qty = Decimal128Array::from(vec![i128::MAX / 2 + 1, i128::MAX / 2 + 1]).with_precision_and_scale(38, 2).unwrap();
+        let err = agg.aggregate_batch(&mask, &flags, &statuses, &qty, &small, &small, &small).unwrap_err();
+        assert!(matches!(err, GooseError::Overflow(_)), "{}", err);
+
+        // A large price: `price * (1 - discount)` leaves i128
+        let mut agg = DecimalAggregator::<Checked>::with_accumulator(PerfectHash::tpch());
+        let discount = Decimal128Array::from(vec![-100, 0]).with_precision_and_scale(38, 2).unwrap();
+        let err = agg.aggregate_batch(&mask, &flags, &statuses, &small, &big, &discount, &small).unwrap_err();
+        assert!(matches!(err, GooseError::Overflow(_)));
+
+        // Partials that fit on their own but not together
+        let one = BooleanArray::from(vec![true, false]);
+        let partial = || {
+            let mut agg = DecimalAggregator::<Checked>::with_accumulator(PerfectHash::tpch());
+            agg.aggregate_batch(&one, &flags, &statuses, &big, &small, &small, &small).unwrap();
+            agg
+        };
+        let mut merged = partial();
+        assert!(matches!(merged.merge(&partial()), Err(GooseError::Overflow(_))));
+    }
+}
@@ -0,0 +1,275 @@
+//! COUNT(DISTINCT) state: exact hash sets and HyperLogLog sketches
+//!
+//! Both kinds of `DistinctCount` merge, so a group's state can be split
+//! over the perfect-hash accumulator sets or over threads and combined
+//! afterwards, like the sums. Values of any type are keyed by their Arrow
+//! row encoding (`distinct_rows`), so strings, dates and integers count
+//! exactly; float values that compare equal share one key.
+
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hasher};
+use std::sync::Arc;
+
+use arrow::array::{make_array, Array, ArrayRef, AsArray};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Float32Type, Float64Type};
+use arrow::row::{RowConverter, Rows, SortField};
+
+use crate::error::GooseError;
+
+/// Exact or approximate distinct counting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DistinctMode {
+    /// Keep every distinct value: exact, memory grows with the distinct count
+    #[default]
+    Exact,
+    /// `HyperLogLog` sketch: fixed memory, about 1.6% standard error
+    Approx,
+}
+
+/// Distinct values seen by one group
+#[derive(Debug, Clone)]
+pub enum DistinctCount {
+    /// Row-encoded keys
+    Exact(HashSet<Box<[u8]>>),
+    Approx(HyperLogLog),
+}
+
+impl DistinctCount {
+    pub fn new(mode: DistinctMode) -> Self {
+        match mode {
+            DistinctMode::Exact => DistinctCount::Exact(HashSet::new()),
+            DistinctMode::Approx => DistinctCount::Approx(HyperLogLog::new()),
+        }
+    }
+
+    /// Add a value by its key, one row of `distinct_rows`
+    #[inline]
+    pub fn insert(&mut self, key: &[u8]) {
+        match self {
+            DistinctCount::Exact(set) => {
+                if !set.contains(key) {
+                    set.insert(key.into());
+                }
+            }
+            DistinctCount::Approx(sketch) => sketch.insert(hash_key(key)),
+        }
+    }
+
+    /// Combine with the state of another, possibly overlapping set of rows
+    ///
+    /// Merging a sketch into an exact set turns it into a sketch.
+    pub fn merge(&mut self, other: &DistinctCount) {
+        match (&mut *self, other) {
+            (DistinctCount::Exact(set), DistinctCount::Exact(other)) => set.extend(other.iter().cloned()),
+            (DistinctCount::Approx(sketch), DistinctCount::Approx(other)) => sketch.merge(other),
+            (DistinctCount::Approx(sketch), DistinctCount::Exact(other)) => {
+                other.iter().for_each(|key| sketch.insert(hash_key(key)))
+            }
+            (DistinctCount::Exact(set), DistinctCount::Approx(other)) => {
+                let mut sketch = other.clone();
+                set.iter().for_each(|key| sketch.insert(hash_key(key)));
+                *self = DistinctCount::Approx(sketch);
+            }
+        }
+    }
+
+    /// Number of distinct values (estimated for a sketch)
+    pub fn count(&self) -> u64 {
+        match self {
+            DistinctCount::Exact(set) => set.len() as u64,
+            DistinctCount::Approx(sketch) => sketch.estimate(),
+        }
+    }
+}
+
+/// Encode `values` with Arrow's row format; row `i` is the key of value `i`
+///
+/// Dictionaries are encoded by value, and floats that compare equal get one
+/// key: -0.0 is folded into 0.0 and every NaN into one. NULL rows get a key
+/// too, so callers skip them. The encoding only depends on the values, so
+/// keys from different batches and threads agree.
+pub fn distinct_rows(values: &dyn Array) -> Result<Rows, GooseError> {
+    let values = match values.data_type() {
+        DataType::Dictionary(_, value) => cast(values, value)?,
+        _ => make_array(values.to_data()),
+    };
+    let values: ArrayRef = match values.data_type() {
+        DataType::Float64 => Arc::new(values.as_primitive::<Float64Type>().unary::<_, Float64Type>(|v| {
+            if v == 0.0 {
+                0.0
+            } else if v.is_nan() {
+                f64::NAN
+            } else {
+                v
+            }
+        })),
+        DataType::Float32 => Arc::new(values.as_primitive::<Float32Type>().unary::<_, Float32Type>(|v| {
+            if v == 0.0 {
+                0.0
+            } else if v.is_nan() {
+                f32::NAN
+            } else {
+                v
+            }
+        })),
+        _ => values,
+    };
+    let converter = RowConverter::new(vec![SortField::new(values.data_type().clone())])?;
+    Ok(converter.convert_columns(&[values])?)
+}
+
+/// 64-bit hash of a key for the sketch; `DefaultHasher::new` has fixed
+/// keys, so every thread hashes a key the same way
+#[inline]
+fn hash_key(key: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(key);
+    hasher.finish()
+}
+
+/// Index bits of a `HyperLogLog`: 2^12 one-byte registers (4 KiB)
+const PRECISION: u32 = 12;
+
+/// HyperLogLog cardinality sketch (Flajolet et al.) over 64-bit hashes
+///
+/// Every sketch has the same precision, so any two merge by taking the
+/// register-wise maximum, and merging is idempotent: inserting a value into
+/// both sides does not count it twice. Small cardinalities use the linear
+/// counting correction and are close to exact.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperLogLog {
+    registers: Box<[u8]>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HyperLogLog {
+    pub fn new() -> Self {
+        Self { registers: vec![0; 1 << PRECISION].into_boxed_slice() }
+    }
+
+    /// Add a key; keys are hashed, so they need not be well distributed
+    #[inline]
+    pub fn insert(&mut self, key: u64) {
+        let hash = mix64(key);
+        let index = (hash >> (64 - PRECISION)) as usize;
+        // Position of the first set bit after the index bits; the sentinel
+        // bit caps it when all remaining bits are zero
+        let rest = (hash << PRECISION) | (1 << (PRECISION - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        let register = &mut self.registers[index];
+        *register = (*register).max(rank);
+    }
+
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for (register, &theirs) in self.registers.iter_mut().zip(other.registers.iter()) {
+            *register = (*register).max(theirs);
+        }
+    }
+
+    /// Estimated number of distinct keys inserted
+    pub fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|&r| (-(r as f64)).exp2()).sum();
+        let raw = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        let estimate = if raw <= 2.5 * m && zeros > 0 { m * (m / zeros as f64).ln() } else { raw };
+        estimate.round() as u64
+    }
+}
+
+/// SplitMix64 finalizer: spreads sequential keys over all 64 bits
+#[inline]
+fn mix64(key: u64) -> u64 {
+    let mut z = key.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow::array::{DictionaryArray, Float64Array, Int32Array, StringArray};
+    use arrow::datatypes::Int32Type;
+
+    /// Insert every non-NULL value of an array
+    fn insert_all(distinct: &mut DistinctCount, values: &dyn Array) {
+        let rows = distinct_rows(values).unwrap();
+        (0..values.len()).filter(|&i| values.is_valid(i)).for_each(|i| distinct.insert(rows.row(i).as_ref()));
+    }
+
+    #[test]
+    fn test_exact() {
+        let mut a = DistinctCount::new(DistinctMode::Exact);
+        insert_all(&mut a, &Float64Array::from(vec![1.0, 2.0, 2.0, 0.0, -0.0, f64::NAN, -f64::NAN]));
+        assert_eq!(a.count(), 4);
+
+        let mut b = DistinctCount::new(DistinctMode::Exact);
+        insert_all(&mut b, &Float64Array::from(vec![2.0, 3.0]));
+        a.merge(&b);
+        assert_eq!(a.count(), 5);
+    }
+
+    #[test]
+    fn test_strings() {
+        // Plain and dictionary-encoded strings share keys; NULLs are skipped
+        let mut distinct = DistinctCount::new(DistinctMode::Exact);
+        insert_all(&mut distinct, &StringArray::from(vec![Some("AIR"), Some("RAIL"), None, Some("AIR")]));
+        let dictionary: DictionaryArray<Int32Type> = vec!["RAIL", "SHIP", "SHIP"].into_iter().collect();
+        insert_all(&mut distinct, &dictionary);
+        assert_eq!(distinct.count(), 3);
+
+        // Integers count exactly at any magnitude
+        let mut numbers = DistinctCount::new(DistinctMode::Exact);
+        insert_all(&mut numbers, &Int32Array::from(vec![1, 2, 1, i32::MAX]));
+        assert_eq!(numbers.count(), 3);
+    }
+
+    #[test]
+    fn test_hyperloglog() {
+        // Small counts are within a few of exact (one standard error is ~1.1)
+        let mut small = HyperLogLog::new();
+        (0..100u64).for_each(|k| small.insert(k));
+        assert!(small.estimate().abs_diff(100) <= 4, "estimate {} for 100", small.estimate());
+
+        // Overlapping halves merge to the union, within a few standard errors
+        let n = 200_000u64;
+        let (mut a, mut b) = (HyperLogLog::new(), HyperLogLog::new());
+        (0..n * 6 / 10).for_each(|k| a.insert(k));
+        (n * 4 / 10..n).for_each(|k| b.insert(k));
+        let mut merged = a.clone();
+        merged.merge(&b);
+        let error = merged.estimate().abs_diff(n) as f64 / n as f64;
+        assert!(error < 0.05, "estimate {} for {}", merged.estimate(), n);
+        // Idempotent: merging again changes nothing
+        merged.merge(&a);
+        assert_eq!(merged.estimate(), {
+            let mut again = a.clone();
+            again.merge(&b);
+            again.estimate()
+        });
+    }
+
+    #[test]
+    fn test_mixed_merge() {
+        let mut exact = DistinctCount::new(DistinctMode::Exact);
+        insert_all(&mut exact, &Int32Array::from_iter_values(0..50));
+        let mut approx = DistinctCount::new(DistinctMode::Approx);
+        insert_all(&mut approx, &Int32Array::from_iter_values(25..75));
+
+        let mut into_exact = exact.clone();
+        into_exact.merge(&approx);
+        assert!(matches!(into_exact, DistinctCount::Approx(_)));
+        approx.merge(&exact);
+        assert_eq!(into_exact.count(), approx.count());
+        assert!(approx.count().abs_diff(75) <= 4);
+    }
+}
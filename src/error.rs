@@ -0,0 +1,127 @@
+//! Crate-wide error type
+
+use std::fmt;
+
+use arrow::datatypes::DataType;
+use arrow::error::ArrowError;
+use parquet::errors::ParquetError;
+
+/// Errors returned by every fallible goose-db function
+#[derive(Debug)]
+pub enum GooseError {
+    /// A required column is not in the file or batch schema
+    MissingColumn(String),
+    /// A column exists but has a type the operator cannot handle
+    TypeMismatch {
+        column: String,
+        expected: String,
+        found: DataType,
+    },
+    /// Reading or decoding the Parquet file failed
+    Parquet(ParquetError),
+    /// An Arrow kernel failed (cast overflow, bad schema, ...)
+    Arrow(ArrowError),
+    /// The file decoded but its contents break an assumption, e.g. a group
+    /// key outside the perfect-hash domain or a truncated dictionary page
+    InvalidData(String),
+    /// Opening or reading a file failed
+    Io(std::io::Error),
+    /// The SQL text could not be parsed or bound
+    Sql(String),
+    /// The query is valid but not supported for this input
+    Unsupported(String),
+    /// An integer accumulator would have wrapped
+    Overflow(String),
+    /// A bug or a panicked worker thread
+    Internal(String),
+}
+
+/// `Result` with `GooseError` as the error type
+pub type Result<T> = std::result::Result<T, GooseError>;
+
+impl GooseError {
+    pub(crate) fn type_mismatch(column: &str, expected: &str, found: &DataType) -> Self {
+        GooseError::TypeMismatch {
+            column: column.to_string(),
+            expected: expected.to_string(),
+            found: found.clone(),
+        }
+    }
+}
+
+impl fmt::Display for GooseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GooseError::MissingColumn(column) => write!(f, "column {} not found", column),
+            GooseError::TypeMismatch { column, expected, found } => {
+                write!(f, "column {} has type {}, expected {}", column, found, expected)
+            }
+            GooseError::Parquet(e) => write!(f, "{}", e),
+            GooseError::Arrow(e) => write!(f, "{}", e),
+            GooseError::InvalidData(msg) => write!(f, "invalid data: {}", msg),
+            GooseError::Io(e) => write!(f, "I/O error: {}", e),
+            GooseError::Sql(msg) => write!(f, "SQL error: {}", msg),
+            GooseError::Unsupported(msg) => write!(f, "unsupported: {}", msg),
+            GooseError::Overflow(msg) => write!(f, "overflow: {}", msg),
+            GooseError::Internal(msg) => write!(f, "internal error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for GooseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GooseError::Parquet(e) => Some(e),
+            GooseError::Arrow(e) => Some(e),
+            GooseError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParquetError> for GooseError {
+    fn from(e: ParquetError) -> Self {
+        GooseError::Parquet(e)
+    }
+}
+
+impl From<ArrowError> for GooseError {
+    fn from(e: ArrowError) -> Self {
+        match e {
+            // The Parquet reader surfaces its own errors through Arrow's
+            // iterator type; unwrap them so callers see the real kind
+            ArrowError::ExternalError(inner) => match inner.downcast::<ParquetError>() {
+                Ok(parquet) => GooseError::Parquet(*parquet),
+                Err(inner) => GooseError::Arrow(ArrowError::ExternalError(inner)),
+            },
+            ArrowError::ParquetError(msg) => GooseError::Parquet(ParquetError::General(msg)),
+            ArrowError::IoError(_, e) => GooseError::Io(e),
+            e => GooseError::Arrow(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for GooseError {
+    fn from(e: std::io::Error) -> Self {
+        GooseError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        let parquet = ArrowError::ExternalError(Box::new(ParquetError::General("bad page".into())));
+        assert!(matches!(GooseError::from(parquet), GooseError::Parquet(_)));
+        let parquet = ArrowError::ParquetError("bad page".into());
+        assert!(matches!(GooseError::from(parquet), GooseError::Parquet(_)));
+
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+        assert!(matches!(GooseError::from(io), GooseError::Io(_)));
+
+        let e = GooseError::type_mismatch("l_shipdate", "Date32", &DataType::Utf8);
+        assert_eq!(e.to_string(), "column l_shipdate has type Utf8, expected Date32");
+    }
+}
@@ -3,47 +3,76 @@
 use arrow::array::{Array, Date32Array, RecordBatch, Scalar};
 use arrow::compute;
 
-use crate::reader::FILTER_DATE_DAYS;
+use crate::params::Q1Params;
+use crate::error::GooseError;
 
-/// Apply the filter: l_shipdate <= '1998-09-02'
+/// Apply the filter: l_shipdate <= cutoff
 /// Returns a filtered RecordBatch containing only qualifying rows
-pub fn apply_date_filter(batch: &RecordBatch) -> Result<RecordBatch, Box<dyn std::error::Error>> {
-    let filter_mask = create_date_filter_mask(batch)?;
+pub fn apply_date_filter(batch: &RecordBatch, params: &Q1Params) -> Result<RecordBatch, GooseError> {
+    let filter_mask = create_date_filter_mask(batch, params)?;
     let filtered = compute::filter_record_batch(batch, &filter_mask)?;
     Ok(filtered)
 }
 
-/// Create the filter mask: l_shipdate <= '1998-09-02'
-pub fn create_date_filter_mask(batch: &RecordBatch) -> Result<arrow::array::BooleanArray, Box<dyn std::error::Error>> {
+/// Create the filter mask: l_shipdate <= cutoff (1998-09-02 by default)
+///
+/// The mask has no NULLs: rows with a NULL ship date are false.
+pub fn create_date_filter_mask(batch: &RecordBatch, params: &Q1Params) -> Result<arrow::array::BooleanArray, GooseError> {
     // Find the l_shipdate column
     let shipdate_idx = batch
         .schema()
         .fields()
         .iter()
         .position(|f| f.name() == "l_shipdate")
-        .ok_or("l_shipdate column not found")?;
+        .ok_or_else(|| GooseError::MissingColumn("l_shipdate".to_string()))?;
     
     let shipdate_col = batch.column(shipdate_idx);
     let shipdate_array = shipdate_col
         .as_any()
         .downcast_ref::<Date32Array>()
-        .ok_or("l_shipdate is not Date32")?;
+        .ok_or_else(|| GooseError::type_mismatch("l_shipdate", "Date32", shipdate_col.data_type()))?;
     
     // Create a scalar for comparison
-    let scalar_date = Scalar::new(Date32Array::from(vec![FILTER_DATE_DAYS]));
+    let scalar_date = Scalar::new(Date32Array::from(vec![params.cutoff_days()]));
     
     // Create the filter mask using SIMD-optimized comparison
-    // l_shipdate <= 1998-09-02 (days since epoch = 10471)
+    // l_shipdate <= cutoff (days since epoch)
     let filter_mask = compute::kernels::cmp::lt_eq(
         shipdate_array,
         &scalar_date,
     )?;
-    
+
+    // A NULL ship date compares as NULL, which does not qualify; callers
+    // read the mask's value bits directly, so make those rows false
+    if filter_mask.null_count() > 0 {
+        return Ok(compute::prep_null_mask_filter(&filter_mask));
+    }
     Ok(filter_mask)
 }
 
 /// Get the number of rows that pass the filter (for statistics)
-pub fn count_matching_rows(batch: &RecordBatch) -> Result<usize, Box<dyn std::error::Error>> {
-    let mask = create_date_filter_mask(batch)?;
+pub fn count_matching_rows(batch: &RecordBatch, params: &Q1Params) -> Result<usize, GooseError> {
+    let mask = create_date_filter_mask(batch, params)?;
     Ok(mask.true_count())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_null_shipdate_does_not_qualify() {
+        let params = Q1Params::default();
+        let cutoff = params.cutoff_days();
+        let schema = Arc::new(Schema::new(vec![Field::new("l_shipdate", DataType::Date32, true)]));
+        let dates = Date32Array::from(vec![Some(cutoff), None, Some(cutoff + 1), Some(cutoff - 1)]);
+        let batch = RecordBatch::try_new(schema, vec![Arc::new(dates)]).unwrap();
+
+        let mask = create_date_filter_mask(&batch, &params).unwrap();
+        assert_eq!(mask.null_count(), 0);
+        assert_eq!(mask.iter().collect::<Vec<_>>(), vec![Some(true), Some(false), Some(false), Some(true)]);
+        assert_eq!(count_matching_rows(&batch, &params).unwrap(), 2);
+    }
+}
@@ -0,0 +1,839 @@
+//! General hash aggregation for arbitrary GROUP BY keys
+//!
+//! `Aggregator` is hard-wired to the 6 possible `(l_returnflag, l_linestatus)`
+//! groups. This operator handles any number of key columns of any type the
+//! Arrow row format supports (primitives, strings, dictionaries, ...).
+//! Key tuples are encoded with a `RowConverter` into byte strings that hash
+//! and compare as a unit, and whose byte order matches the ascending
+//! ORDER BY of the keys, so sorting the groups is a plain byte sort.
+//!
+//! Dictionary keys are grouped by value and come out as plain value arrays
+//! (see `key_output_field`).
+//!
+//! NULL keys form their own group, sorted last. NULL inputs are skipped by
+//! every aggregate but COUNT(*); SUM, AVG, MIN, MAX and the population
+//! variances of a group without any non-NULL input are NULL, and so are the
+//! sample variances below two inputs.
+//!
+//! COUNT(col) only reads the input's validity bitmap, MIN / MAX keep the
+//! input type (reduced with arrow's `min` / `max` kernels) and COUNT(DISTINCT)
+//! keys values by their row encoding, so these work on any column. The other
+//! aggregates read their input as Float64 and need a numeric column.
+//!
+//! The variances share one `Moments` state per aggregate (Welford updates,
+//! pairwise merges), so partial aggregators from different threads combine
+//! exactly like SUM and COUNT do. COUNT(DISTINCT)
+//! keeps a `DistinctCount` per group: a hash set, or a HyperLogLog sketch
+//! for APPROX_COUNT_DISTINCT. Quantiles keep a `QuantileSketch`: every
+//! value for MEDIAN / QUANTILE_CONT, a t-digest for APPROX_QUANTILE.
+//! SUM and AVG accumulate in a `SumState` of the aggregator's `Summation`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow::array::{
+    downcast_primitive_array, new_empty_array, new_null_array, Array, ArrayRef, ArrowPrimitiveType, AsArray,
+    BinaryArray, BooleanArray, Float64Array, LargeStringArray, PrimitiveArray, RecordBatch, StringArray,
+    UInt32Array, UInt64Array,
+};
+use arrow::compute::{cast, concat, take, SortOptions};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::row::{RowConverter, Rows, SortField};
+use crate::aggregator::{merge_option, Moments};
+use crate::distinct::{distinct_rows, DistinctCount, DistinctMode};
+use crate::error::GooseError;
+use crate::quantile::{QuantileMode, QuantileSketch};
+use crate::summation::{SumState, Summation};
+
+/// Aggregate function over one input column
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AggregateFunction {
+    Sum,
+    Avg,
+    /// COUNT(col)
+    Count,
+    /// COUNT(*), takes no input
+    CountStar,
+    Min,
+    Max,
+    /// VAR_SAMP, also spelled VARIANCE
+    VarSamp,
+    VarPop,
+    /// STDDEV_SAMP, also spelled STDDEV
+    StddevSamp,
+    StddevPop,
+    /// COUNT(DISTINCT col)
+    CountDistinct,
+    /// APPROX_COUNT_DISTINCT(col), estimated with HyperLogLog
+    ApproxCountDistinct,
+    /// QUANTILE_CONT(col, fraction) (MEDIAN is fraction 0.5), or
+    /// APPROX_QUANTILE(col, fraction) with `QuantileMode::Approx`
+    Quantile { fraction: f64, mode: QuantileMode },
+}
+
+impl AggregateFunction {
+    /// Output type of the aggregate over an input of type `input`
+    ///
+    /// MIN and MAX keep the input type (a dictionary's value type), counts
+    /// are UInt64 and everything else is Float64.
+    pub fn data_type(&self, input: &DataType) -> DataType {
+        match self {
+            AggregateFunction::Count
+            | AggregateFunction::CountStar
+            | AggregateFunction::CountDistinct
+            | AggregateFunction::ApproxCountDistinct => DataType::UInt64,
+            AggregateFunction::Min | AggregateFunction::Max => value_type(input).clone(),
+            _ => DataType::Float64,
+        }
+    }
+
+    /// Whether the aggregate can be NULL (over no non-NULL input)
+    pub fn is_nullable(&self) -> bool {
+        !matches!(
+            self,
+            AggregateFunction::Count
+                | AggregateFunction::CountStar
+                | AggregateFunction::CountDistinct
+                | AggregateFunction::ApproxCountDistinct
+        )
+    }
+
+    /// Whether the aggregate can read an input of type `input`
+    ///
+    /// SUM, AVG, the variances and the quantiles do arithmetic on their
+    /// input and need numbers; the others accept any column.
+    pub fn accepts(&self, input: &DataType) -> bool {
+        !self.reads_float() || matches!(input, DataType::Null) || value_type(input).is_numeric()
+    }
+
+    /// Whether the aggregate reads its input cast to Float64
+    fn reads_float(&self) -> bool {
+        !matches!(
+            self,
+            AggregateFunction::Count
+                | AggregateFunction::CountStar
+                | AggregateFunction::Min
+                | AggregateFunction::Max
+                | AggregateFunction::CountDistinct
+                | AggregateFunction::ApproxCountDistinct
+        )
+    }
+
+    /// Whether the aggregate is computed from `Moments` rather than sum and count
+    fn uses_moments(&self) -> bool {
+        matches!(
+            self,
+            AggregateFunction::VarSamp
+                | AggregateFunction::VarPop
+                | AggregateFunction::StddevSamp
+                | AggregateFunction::StddevPop
+        )
+    }
+
+    /// How the aggregate counts distinct values, if it does
+    fn distinct_mode(&self) -> Option<DistinctMode> {
+        match self {
+            AggregateFunction::CountDistinct => Some(DistinctMode::Exact),
+            AggregateFunction::ApproxCountDistinct => Some(DistinctMode::Approx),
+            _ => None,
+        }
+    }
+}
+
+/// Value type of a dictionary, any other type as is
+fn value_type(data_type: &DataType) -> &DataType {
+    match data_type {
+        DataType::Dictionary(_, value) => value,
+        data_type => data_type,
+    }
+}
+
+/// Output field of a group key column: nullable, and a dictionary comes
+/// out as its value type since the row format decodes keys to values
+pub fn key_output_field(field: &Field) -> Field {
+    let data_type = match field.data_type() {
+        DataType::Dictionary(_, value) => value.as_ref().clone(),
+        data_type => data_type.clone(),
+    };
+    field.clone().with_data_type(data_type).with_nullable(true)
+}
+
+/// One aggregate in the SELECT list
+#[derive(Debug, Clone)]
+pub struct AggregateExpr {
+    pub func: AggregateFunction,
+    /// Index into the `values` slice passed to `update_batch` (None for COUNT(*))
+    pub input: Option<usize>,
+    /// Output column name
+    pub name: String,
+    /// Type of the input column, Float64 unless set with `with_input_type`
+    pub input_type: DataType,
+}
+
+impl AggregateExpr {
+    pub fn new(func: AggregateFunction, input: Option<usize>, name: &str) -> Self {
+        Self { func, input, name: name.to_string(), input_type: DataType::Float64 }
+    }
+
+    /// Set the type of the input column, which MIN and MAX return
+    pub fn with_input_type(mut self, input_type: DataType) -> Self {
+        self.input_type = input_type;
+        self
+    }
+
+    /// Output type of the aggregate
+    pub fn data_type(&self) -> DataType {
+        self.func.data_type(&self.input_type)
+    }
+}
+
+/// An aggregate's input for one batch, in the form the aggregate reads it
+enum Input<'a> {
+    /// COUNT(*)
+    Star,
+    /// COUNT(col): only the validity bitmap is read
+    Validity(&'a dyn Array),
+    /// COUNT(DISTINCT): the input and its `distinct_rows` keys
+    Distinct(&'a dyn Array, Rows, DistinctMode),
+    /// The input cast to Float64
+    Float(Float64Array),
+    /// MIN / MAX: the input values as they are
+    Values(ArrayRef),
+}
+
+/// MIN or MAX of `array` as a one-value array of the same type, NULL if
+/// the array holds no non-NULL value
+fn extreme(array: &dyn Array, max: bool) -> Result<ArrayRef, GooseError> {
+    fn primitive<T: ArrowPrimitiveType>(array: &PrimitiveArray<T>, max: bool) -> ArrayRef {
+        let value = if max { arrow::compute::max(array) } else { arrow::compute::min(array) };
+        Arc::new(PrimitiveArray::<T>::from_iter([value]).with_data_type(array.data_type().clone()))
+    }
+
+    Ok(downcast_primitive_array!(
+        array => primitive(array, max),
+        DataType::Utf8 => {
+            let array = array.as_string::<i32>();
+            let value = if max { arrow::compute::max_string(array) } else { arrow::compute::min_string(array) };
+            Arc::new(StringArray::from(vec![value]))
+        }
+        DataType::LargeUtf8 => {
+            let array = array.as_string::<i64>();
+            let value = if max { arrow::compute::max_string(array) } else { arrow::compute::min_string(array) };
+            Arc::new(LargeStringArray::from(vec![value]))
+        }
+        DataType::Binary => {
+            let array = array.as_binary::<i32>();
+            let value = if max { arrow::compute::max_binary(array) } else { arrow::compute::min_binary(array) };
+            Arc::new(BinaryArray::from(vec![value]))
+        }
+        DataType::Boolean => {
+            let array = array.as_boolean();
+            let value = if max { arrow::compute::max_boolean(array) } else { arrow::compute::min_boolean(array) };
+            Arc::new(BooleanArray::from(vec![value]))
+        }
+        DataType::Null => new_null_array(&DataType::Null, 1),
+        data_type => return Err(GooseError::Unsupported(format!("MIN / MAX over {}", data_type))),
+    ))
+}
+
+/// Running state of one aggregate within one group
+#[derive(Debug, Clone, Default)]
+struct Accumulator {
+    sum: SumState,
+    count: u64,
+    /// Only updated for the aggregates that read it
+    moments: Moments,
+    /// Allocated on the first value of a COUNT(DISTINCT)
+    distinct: Option<Box<DistinctCount>>,
+    /// Allocated on the first value of a quantile
+    quantiles: Option<Box<QuantileSketch>>,
+    /// MIN or MAX so far, as a one-value array of the input type
+    extreme: Option<ArrayRef>,
+}
+
+impl Accumulator {
+    #[inline(always)]
+    fn update(&mut self, func: AggregateFunction, value: f64) {
+        if let AggregateFunction::Quantile { mode, .. } = func {
+            self.quantiles.get_or_insert_with(|| Box::new(QuantileSketch::new(mode))).insert(value);
+        } else if func.uses_moments() {
+            self.moments.update(value);
+        } else {
+            self.sum.add(value);
+            self.count += 1;
+        }
+    }
+
+    /// Fold a one-value MIN or MAX into the running one
+    fn update_extreme(&mut self, value: ArrayRef, max: bool) -> Result<(), GooseError> {
+        self.extreme = Some(match self.extreme.take() {
+            Some(current) => extreme(concat(&[current.as_ref(), value.as_ref()])?.as_ref(), max)?,
+            None => value,
+        });
+        Ok(())
+    }
+
+    fn merge(&mut self, other: &Accumulator, func: AggregateFunction) -> Result<(), GooseError> {
+        self.sum.merge(&other.sum);
+        self.count += other.count;
+        self.moments.merge(&other.moments);
+        merge_option(&mut self.distinct, &other.distinct, |a, b| a.merge(b));
+        merge_option(&mut self.quantiles, &other.quantiles, |a, b| a.merge(b));
+        if let Some(value) = &other.extreme {
+            self.update_extreme(value.clone(), func == AggregateFunction::Max)?;
+        }
+        Ok(())
+    }
+}
+
+/// Hash aggregation keyed on one or more Arrow columns
+pub struct HashAggregator {
+    key_fields: Vec<Field>,
+    aggregates: Vec<AggregateExpr>,
+    converter: RowConverter,
+    /// Encoded key -> group index
+    groups: HashMap<Box<[u8]>, usize>,
+    /// `group * aggregates.len() + aggregate` -> accumulator
+    accumulators: Vec<Accumulator>,
+    /// How SUM and AVG add their inputs
+    summation: Summation,
+}
+
+impl HashAggregator {
+    /// Create an aggregator for the given key columns and aggregates
+    pub fn try_new(key_fields: Vec<Field>, aggregates: Vec<AggregateExpr>) -> Result<Self, GooseError> {
+        let sort_fields = key_fields
+            .iter()
+            .map(|f| SortField::new_with_options(f.data_type().clone(), SortOptions { descending: false, nulls_first: false }))
+            .collect();
+        let converter = RowConverter::new(sort_fields)?;
+
+        Ok(Self {
+            key_fields,
+            aggregates,
+            converter,
+            groups: HashMap::new(),
+            accumulators: Vec::new(),
+            summation: Summation::Plain,
+        })
+    }
+
+    /// Accumulate SUM and AVG with `summation` instead of plain adds
+    pub fn with_summation(mut self, summation: Summation) -> Self {
+        self.summation = summation;
+        self
+    }
+
+    /// Number of distinct groups seen so far
+    pub fn num_groups(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Aggregate the rows of a batch selected by `mask` (all rows if None)
+    ///
+    /// `keys` must match the key fields given at construction; `values` holds
+    /// the evaluated aggregate inputs referenced by `AggregateExpr::input`.
+    /// Without key fields every row lands in a single global group; the row
+    /// count is then taken from `values` or `mask`.
+    pub fn update_batch(
+        &mut self,
+        keys: &[ArrayRef],
+        values: &[&dyn Array],
+        mask: Option<&BooleanArray>,
+    ) -> Result<(), GooseError> {
+        let rows = self.converter.convert_columns(keys)?;
+        let num_rows = match keys.first() {
+            Some(_) => rows.num_rows(),
+            None => values.first().map(|v| v.len()).or(mask.map(|m| m.len())).unwrap_or(0),
+        };
+        let num_aggs = self.aggregates.len();
+        let inputs = self.inputs(values)?;
+        let has_extremes = inputs.iter().any(|input| matches!(input, Input::Values(_)));
+        // (group, row) of every selected row, for MIN / MAX
+        let mut selected: Vec<(usize, u32)> = Vec::new();
+
+        for i in 0..num_rows {
+            if let Some(mask) = mask {
+                if !mask.is_valid(i) || !mask.value(i) {
+                    continue;
+                }
+            }
+
+            let group = if keys.is_empty() { self.group_index(&[]) } else { self.group_index(rows.row(i).as_ref()) };
+            if has_extremes {
+                selected.push((group, i as u32));
+            }
+            let accs = &mut self.accumulators[group * num_aggs..(group + 1) * num_aggs];
+
+            for (acc, (agg, input)) in accs.iter_mut().zip(self.aggregates.iter().zip(&inputs)) {
+                match input {
+                    Input::Star => acc.count += 1,
+                    Input::Validity(values) => acc.count += values.is_valid(i) as u64,
+                    Input::Distinct(values, rows, mode) if values.is_valid(i) => {
+                        acc.distinct.get_or_insert_with(|| Box::new(DistinctCount::new(*mode))).insert(rows.row(i).as_ref())
+                    }
+                    Input::Float(values) if values.is_valid(i) => acc.update(agg.func, values.value(i)),
+                    Input::Distinct(..) | Input::Float(_) | Input::Values(_) => {}
+                }
+            }
+        }
+
+        if has_extremes {
+            self.update_extremes(&inputs, selected)?;
+        }
+        Ok(())
+    }
+
+    /// Prepare each aggregate's input the way it reads it
+    fn inputs<'a>(&self, values: &[&'a dyn Array]) -> Result<Vec<Input<'a>>, GooseError> {
+        self.aggregates
+            .iter()
+            .map(|agg| {
+                let Some(index) = agg.input else { return Ok(Input::Star) };
+                let values = values[index];
+                if let Some(mode) = agg.func.distinct_mode() {
+                    return Ok(Input::Distinct(values, distinct_rows(values)?, mode));
+                }
+                Ok(match agg.func {
+                    AggregateFunction::Count => Input::Validity(values),
+                    AggregateFunction::Min | AggregateFunction::Max => {
+                        Input::Values(cast(values, value_type(values.data_type()))?)
+                    }
+                    func if !func.accepts(values.data_type()) => {
+                        return Err(GooseError::type_mismatch(&agg.name, "a numeric input", values.data_type()))
+                    }
+                    _ => Input::Float(cast(values, &DataType::Float64)?.as_primitive().clone()),
+                })
+            })
+            .collect()
+    }
+
+    /// Fold the selected rows of a batch into each group's MIN / MAX, one
+    /// kernel call per group and aggregate
+    fn update_extremes(&mut self, inputs: &[Input], mut selected: Vec<(usize, u32)>) -> Result<(), GooseError> {
+        let num_aggs = self.aggregates.len();
+        selected.sort_unstable();
+        for run in selected.chunk_by(|a, b| a.0 == b.0) {
+            let group = run[0].0;
+            let indices = UInt32Array::from_iter_values(run.iter().map(|&(_, row)| row));
+            for (a, (agg, input)) in self.aggregates.iter().zip(inputs).enumerate() {
+                let Input::Values(values) = input else { continue };
+                let max = agg.func == AggregateFunction::Max;
+                let value = extreme(take(values.as_ref(), &indices, None)?.as_ref(), max)?;
+                self.accumulators[group * num_aggs + a].update_extreme(value, max)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Merge another aggregator with the same keys and aggregates into this one
+    pub fn merge(&mut self, other: &HashAggregator) -> Result<(), GooseError> {
+        let num_aggs = self.aggregates.len();
+        for (key, &other_group) in &other.groups {
+            let group = self.group_index(key);
+            let theirs = &other.accumulators[other_group * num_aggs..(other_group + 1) * num_aggs];
+            let mine = &mut self.accumulators[group * num_aggs..(group + 1) * num_aggs];
+            for ((acc, other_acc), agg) in mine.iter_mut().zip(theirs).zip(&self.aggregates) {
+                acc.merge(other_acc, agg.func)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Produce one row per group, ordered ascending by the key columns
+    ///
+    /// An aggregator without key fields always produces exactly one row,
+    /// even if no input row was selected.
+    pub fn finish(&self) -> Result<RecordBatch, GooseError> {
+        let mut entries: Vec<(&[u8], usize)> = self.groups.iter().map(|(k, &g)| (k.as_ref(), g)).collect();
+        entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+        let parser = self.converter.parser();
+        let mut columns = self
+            .converter
+            .convert_rows(entries.iter().map(|(key, _)| parser.parse(key)))?;
+
+        let num_aggs = self.aggregates.len();
+        let empty = vec![Accumulator::default(); num_aggs];
+        let global = self.key_fields.is_empty() && entries.is_empty();
+        for (a, agg) in self.aggregates.iter().enumerate() {
+            let accs: Box<dyn Iterator<Item = &Accumulator>> = if global {
+                Box::new(std::iter::once(&empty[a]))
+            } else {
+                Box::new(entries.iter().map(|&(_, g)| &self.accumulators[g * num_aggs + a]))
+            };
+            let column: ArrayRef = match agg.func {
+                AggregateFunction::Sum => Arc::new(accs.map(|acc| (acc.count > 0).then(|| acc.sum.value())).collect::<Float64Array>()),
+                AggregateFunction::Avg => Arc::new(
+                    accs.map(|acc| (acc.count > 0).then(|| acc.sum.value() / acc.count as f64))
+                        .collect::<Float64Array>(),
+                ),
+                AggregateFunction::Count | AggregateFunction::CountStar => {
+                    Arc::new(accs.map(|acc| acc.count).collect::<UInt64Array>())
+                }
+                AggregateFunction::Min | AggregateFunction::Max => {
+                    let data_type = agg.data_type();
+                    let values: Vec<ArrayRef> =
+                        accs.map(|acc| acc.extreme.clone().unwrap_or_else(|| new_null_array(&data_type, 1))).collect();
+                    match values.is_empty() {
+                        true => new_empty_array(&data_type),
+                        false => concat(&values.iter().map(|v| v.as_ref()).collect::<Vec<_>>())?,
+                    }
+                }
+                AggregateFunction::VarSamp => Arc::new(accs.map(|acc| acc.moments.var_samp()).collect::<Float64Array>()),
+                AggregateFunction::VarPop => Arc::new(accs.map(|acc| acc.moments.var_pop()).collect::<Float64Array>()),
+                AggregateFunction::StddevSamp => Arc::new(accs.map(|acc| acc.moments.stddev_samp()).collect::<Float64Array>()),
+                AggregateFunction::StddevPop => Arc::new(accs.map(|acc| acc.moments.stddev_pop()).collect::<Float64Array>()),
+                AggregateFunction::Quantile { fraction, .. } => Arc::new(
+                    accs.map(|acc| acc.quantiles.as_ref().and_then(|q| q.quantile(fraction)))
+                        .collect::<Float64Array>(),
+                ),
+                AggregateFunction::CountDistinct | AggregateFunction::ApproxCountDistinct => {
+                    Arc::new(accs.map(|acc| acc.distinct.as_ref().map_or(0, |d| d.count())).collect::<UInt64Array>())
+                }
+            };
+            columns.push(column);
+        }
+
+        let fields: Vec<Field> = self
+            .key_fields
+            .iter()
+            .map(key_output_field)
+            .chain(self.aggregates.iter().map(|agg| Field::new(&agg.name, agg.data_type(), agg.func.is_nullable())))
+            .collect();
+
+        Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)?)
+    }
+
+    /// Look up the group for an encoded key, creating it if needed
+    #[inline]
+    fn group_index(&mut self, key: &[u8]) -> usize {
+        if let Some(&group) = self.groups.get(key) {
+            return group;
+        }
+        let group = self.groups.len();
+        self.groups.insert(key.into(), group);
+        let empty = Accumulator { sum: SumState::new(self.summation), ..Accumulator::default() };
+        self.accumulators.resize(self.accumulators.len() + self.aggregates.len(), empty);
+        group
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int64Array, StringArray};
+
+    fn aggregator() -> HashAggregator {
+        HashAggregator::try_new(
+            vec![
+                Field::new("mode", DataType::Utf8, true),
+                Field::new("supp", DataType::Int64, true),
+            ],
+            vec![
+                AggregateExpr::new(AggregateFunction::Sum, Some(0), "sum_v"),
+                AggregateExpr::new(AggregateFunction::Avg, Some(0), "avg_v"),
+                AggregateExpr::new(AggregateFunction::CountStar, None, "cnt"),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_group_by_two_columns() {
+        let mode: ArrayRef = Arc::new(StringArray::from(vec!["TRUCK", "AIR", "TRUCK", "AIR", "MAIL"]));
+        let supp: ArrayRef = Arc::new(Int64Array::from(vec![2, 1, 2, 3, 1]));
+        let v = Float64Array::from(vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+        let mask = BooleanArray::from(vec![true, true, true, true, false]);
+
+        let mut agg = aggregator();
+        agg.update_batch(&[mode, supp], &[&v], Some(&mask)).unwrap();
+        let out = agg.finish().unwrap();
+
+        assert_eq!(out.num_rows(), 3);
+        let modes = out.column(0).as_any().downcast_ref::<StringArray>().unwrap();
+        let supps = out.column(1).as_any().downcast_ref::<Int64Array>().unwrap();
+        let sums = out.column(2).as_any().downcast_ref::<Float64Array>().unwrap();
+        let avgs = out.column(3).as_any().downcast_ref::<Float64Array>().unwrap();
+        let counts = out.column(4).as_any().downcast_ref::<UInt64Array>().unwrap();
+
+        // Ordered by (mode, supp)
+        assert_eq!((modes.value(0), supps.value(0), sums.value(0), counts.value(0)), ("AIR", 1, 2.0, 1));
+        assert_eq!((modes.value(1), supps.value(1), sums.value(1), counts.value(1)), ("AIR", 3, 4.0, 1));
+        assert_eq!((modes.value(2), supps.value(2), sums.value(2), counts.value(2)), ("TRUCK", 2, 4.0, 2));
+        assert_eq!(avgs.value(2), 2.0);
+    }
+
+    #[test]
+    fn test_merge() {
+        let batch = |modes: Vec<&str>, vals: Vec<f64>| {
+            let n = modes.len();
+            let mode: ArrayRef = Arc::new(StringArray::from(modes));
+            let supp: ArrayRef = Arc::new(Int64Array::from(vec![7; n]));
+            (vec![mode, supp], Float64Array::from(vals))
+        };
+
+        let mut a = aggregator();
+        let (keys, v) = batch(vec!["SHIP", "RAIL"], vec![1.0, 2.0]);
+        a.update_batch(&keys, &[&v], None).unwrap();
+
+        let mut b = aggregator();
+        let (keys, v) = batch(vec!["RAIL", "FOB"], vec![10.0, 20.0]);
+        b.update_batch(&keys, &[&v], None).unwrap();
+
+        a.merge(&b).unwrap();
+        assert_eq!(a.num_groups(), 3);
+
+        let out = a.finish().unwrap();
+        let sums = out.column(2).as_any().downcast_ref::<Float64Array>().unwrap();
+        let counts = out.column(4).as_any().downcast_ref::<UInt64Array>().unwrap();
+        // FOB, RAIL, SHIP
+        assert_eq!(sums.values().to_vec(), vec![20.0, 12.0, 1.0]);
+        assert_eq!(counts.values().to_vec(), vec![1, 2, 1]);
+    }
+
+    #[test]
+    fn test_global_aggregate() {
+        let mut agg = HashAggregator::try_new(
+            vec![],
+            vec![
+                AggregateExpr::new(AggregateFunction::Sum, Some(0), "sum_v"),
+                AggregateExpr::new(AggregateFunction::CountStar, None, "cnt"),
+            ],
+        )
+        .unwrap();
+
+        // No rows seen yet: still one output row
+        let out = agg.finish().unwrap();
+        assert_eq!(out.num_rows(), 1);
+        assert_eq!(out.column(1).as_any().downcast_ref::<UInt64Array>().unwrap().value(0), 0);
+
+        let v = Float64Array::from(vec![1.5, 2.5, 4.0]);
+        let mask = BooleanArray::from(vec![true, false, true]);
+        agg.update_batch(&[], &[&v], Some(&mask)).unwrap();
+        let out = agg.finish().unwrap();
+        assert_eq!(out.num_rows(), 1);
+        assert_eq!(out.column(0).as_any().downcast_ref::<Float64Array>().unwrap().value(0), 5.5);
+        assert_eq!(out.column(1).as_any().downcast_ref::<UInt64Array>().unwrap().value(0), 2);
+    }
+
+    #[test]
+    fn test_summation() {
+        let run = |summation, parts: &[&[f64]]| {
+            let new = || {
+                let aggregates = vec![
+                    AggregateExpr::new(AggregateFunction::Sum, Some(0), "s"),
+                    AggregateExpr::new(AggregateFunction::Avg, Some(0), "a"),
+                ];
+                HashAggregator::try_new(vec![], aggregates).unwrap().with_summation(summation)
+            };
+            let mut total = new();
+            for part in parts {
+                let mut agg = new();
+                agg.update_batch(&[], &[&Float64Array::from(part.to_vec())], None).unwrap();
+                total.merge(&agg).unwrap();
+            }
+            let out = total.finish().unwrap();
+            let value = |i: usize| out.column(i).as_primitive::<arrow::datatypes::Float64Type>().value(0);
+            (value(0), value(1))
+        };
+
+        // Plain adds lose the ones next to 1e16, compensated ones keep them
+        // across the merge too
+        assert_eq!(run(Summation::Plain, &[&[1e16, 1.0, -1e16, 1.0]]), (1.0, 0.25));
+        assert_eq!(run(Summation::Kahan, &[&[1e16, 1.0, -1e16, 1.0]]), (2.0, 0.5));
+        assert_eq!(run(Summation::Kahan, &[&[1e16, 1.0], &[-1e16, 1.0]]), (2.0, 0.5));
+        assert_eq!(run(Summation::Pairwise, &[&[0.5, 1.5], &[2.0]]), (4.0, 4.0 / 3.0));
+    }
+
+    #[test]
+    fn test_nulls() {
+        let mode: ArrayRef = Arc::new(StringArray::from(vec![None, Some("AIR"), None, Some("AIR")]));
+        let supp: ArrayRef = Arc::new(Int64Array::from(vec![1, 1, 1, 1]));
+        let v = Float64Array::from(vec![None, Some(2.0), Some(3.0), None]);
+
+        let mut agg = aggregator();
+        agg.update_batch(&[mode, supp], &[&v], None).unwrap();
+        let out = agg.finish().unwrap();
+
+        // The NULL group sorts after AIR
+        assert_eq!(out.num_rows(), 2);
+        let modes = out.column(0).as_any().downcast_ref::<StringArray>().unwrap();
+        let sums = out.column(2).as_any().downcast_ref::<Float64Array>().unwrap();
+        let avgs = out.column(3).as_any().downcast_ref::<Float64Array>().unwrap();
+        let counts = out.column(4).as_any().downcast_ref::<UInt64Array>().unwrap();
+        assert_eq!((modes.value(0), modes.is_null(1)), ("AIR", true));
+        assert_eq!((sums.value(0), avgs.value(0), counts.value(0)), (2.0, 2.0, 2));
+        assert_eq!((sums.value(1), avgs.value(1), counts.value(1)), (3.0, 3.0, 2));
+
+        // SUM of only NULLs is NULL, COUNT(*) is not
+        let mut agg = aggregator();
+        let keys: Vec<ArrayRef> = vec![Arc::new(StringArray::from(vec!["SHIP"])), Arc::new(Int64Array::from(vec![1]))];
+        agg.update_batch(&keys, &[&Float64Array::from(vec![None])], None).unwrap();
+        let out = agg.finish().unwrap();
+        assert!(out.column(2).is_null(0) && out.column(3).is_null(0));
+        assert_eq!(out.column(4).as_any().downcast_ref::<UInt64Array>().unwrap().value(0), 1);
+    }
+
+    #[test]
+    fn test_moments() {
+        let funcs = [
+            AggregateFunction::Min,
+            AggregateFunction::Max,
+            AggregateFunction::VarSamp,
+            AggregateFunction::VarPop,
+            AggregateFunction::StddevSamp,
+            AggregateFunction::StddevPop,
+        ];
+        let new = || {
+            let aggregates = funcs.iter().map(|&f| AggregateExpr::new(f, Some(0), &format!("{:?}", f))).collect();
+            HashAggregator::try_new(vec![Field::new("mode", DataType::Utf8, true)], aggregates).unwrap()
+        };
+        let batch = |modes: Vec<&str>, vals: Vec<Option<f64>>| {
+            let mode: ArrayRef = Arc::new(StringArray::from(modes));
+            (vec![mode], Float64Array::from(vals))
+        };
+
+        // AIR gets 2, 4, 4, 4, 5, 5, 7, 9 split over two aggregators
+        let mut a = new();
+        let (keys, v) = batch(vec!["AIR", "AIR", "AIR", "RAIL", "AIR"], vec![Some(2.0), Some(4.0), None, Some(1.0), Some(4.0)]);
+        a.update_batch(&keys, &[&v], None).unwrap();
+        let mut b = new();
+        let (keys, v) = batch(vec!["AIR"; 5], vec![Some(4.0), Some(5.0), Some(5.0), Some(7.0), Some(9.0)]);
+        b.update_batch(&keys, &[&v], None).unwrap();
+        a.merge(&b).unwrap();
+
+        let out = a.finish().unwrap();
+        assert!(out.schema().fields()[1..].iter().all(|f| f.data_type() == &DataType::Float64 && f.is_nullable()));
+        let column = |i: usize| out.column(i + 1).as_any().downcast_ref::<Float64Array>().unwrap().clone();
+        let air: Vec<f64> = (0..funcs.len()).map(|i| column(i).value(0)).collect();
+        assert_eq!(air[..4], [2.0, 9.0, 32.0 / 7.0, 4.0]);
+        assert!((air[4] - (32.0f64 / 7.0).sqrt()).abs() < 1e-12);
+        assert_eq!(air[5], 2.0);
+
+        // One value: no sample variance
+        let rail: Vec<Option<f64>> = (0..funcs.len()).map(|i| Some(column(i)).filter(|c| c.is_valid(1)).map(|c| c.value(1))).collect();
+        assert_eq!(rail, vec![Some(1.0), Some(1.0), None, Some(0.0), None, Some(0.0)]);
+    }
+
+    #[test]
+    fn test_count_min_max_keep_input_type() {
+        let new = |input_type: DataType| {
+            let aggregates = [AggregateFunction::Count, AggregateFunction::Min, AggregateFunction::Max]
+                .iter()
+                .map(|&f| AggregateExpr::new(f, Some(0), &format!("{:?}", f)).with_input_type(input_type.clone()))
+                .collect();
+            HashAggregator::try_new(vec![Field::new("mode", DataType::Utf8, true)], aggregates).unwrap()
+        };
+        let keys = |modes: Vec<&str>| -> Vec<ArrayRef> { vec![Arc::new(StringArray::from(modes))] };
+
+        // Strings, split over two aggregators
+        let mut a = new(DataType::Utf8);
+        let comments = StringArray::from(vec![Some("quick"), None, Some("brown"), None]);
+        a.update_batch(&keys(vec!["AIR", "AIR", "AIR", "RAIL"]), &[&comments], None).unwrap();
+        let mut b = new(DataType::Utf8);
+        b.update_batch(&keys(vec!["AIR"]), &[&StringArray::from(vec!["fox"])], None).unwrap();
+        a.merge(&b).unwrap();
+
+        let out = a.finish().unwrap();
+        assert_eq!(out.schema().field(2).data_type(), &DataType::Utf8);
+        let counts = out.column(1).as_any().downcast_ref::<UInt64Array>().unwrap();
+        assert_eq!(counts.values().to_vec(), vec![3, 0]);
+        let min = out.column(2).as_string::<i32>();
+        let max = out.column(3).as_string::<i32>();
+        assert_eq!((min.value(0), max.value(0)), ("brown", "quick"));
+        assert!(min.is_null(1) && max.is_null(1));
+
+        // Dates come out as dates; a mask drops the later row
+        let mut agg = new(DataType::Date32);
+        let dates = arrow::array::Date32Array::from(vec![9000, 8000, 9500]);
+        let mask = BooleanArray::from(vec![true, true, false]);
+        agg.update_batch(&keys(vec!["AIR"; 3]), &[&dates], Some(&mask)).unwrap();
+        let out = agg.finish().unwrap();
+        assert_eq!(out.schema().field(3).data_type(), &DataType::Date32);
+        let max = out.column(3).as_primitive::<arrow::datatypes::Date32Type>();
+        assert_eq!(max.value(0), 9000);
+
+        // SUM needs numbers
+        let mut agg = HashAggregator::try_new(vec![], vec![AggregateExpr::new(AggregateFunction::Sum, Some(0), "s")]).unwrap();
+        let err = agg.update_batch(&[], &[&comments], None).unwrap_err();
+        assert!(matches!(err, GooseError::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn test_count_distinct() {
+        let new = || {
+            HashAggregator::try_new(
+                vec![Field::new("mode", DataType::Utf8, true)],
+                vec![
+                    AggregateExpr::new(AggregateFunction::CountDistinct, Some(0), "n"),
+                    AggregateExpr::new(AggregateFunction::ApproxCountDistinct, Some(0), "approx_n"),
+                    AggregateExpr::new(AggregateFunction::CountStar, None, "cnt"),
+                ],
+            )
+            .unwrap()
+        };
+        let update = |agg: &mut HashAggregator, modes: Vec<&str>, vals: Vec<Option<f64>>| {
+            let mode: ArrayRef = Arc::new(StringArray::from(modes));
+            agg.update_batch(&[mode], &[&Float64Array::from(vals)], None).unwrap();
+        };
+
+        let mut a = new();
+        update(&mut a, vec!["AIR", "AIR", "RAIL", "AIR"], vec![Some(1.0), Some(2.0), None, Some(1.0)]);
+        let mut b = new();
+        update(&mut b, vec!["AIR", "SHIP"], vec![Some(2.0), Some(-0.0)]);
+        let mut c = new();
+        update(&mut c, vec!["AIR", "SHIP"], vec![Some(3.0), Some(0.0)]);
+        b.merge(&c).unwrap();
+        a.merge(&b).unwrap();
+
+        let out = a.finish().unwrap();
+        assert!(!out.schema().field(1).is_nullable());
+        let column = |i: usize| out.column(i).as_any().downcast_ref::<UInt64Array>().unwrap().values().to_vec();
+        // AIR, RAIL (only a NULL value), SHIP (0.0 = -0.0)
+        assert_eq!(column(1), vec![3, 0, 1]);
+        assert_eq!(column(2), vec![3, 0, 1]);
+        assert_eq!(column(3), vec![5, 1, 2]);
+
+        // Strings are counted as they are, not through a Float64 cast
+        let mut agg = new();
+        let modes: ArrayRef = Arc::new(StringArray::from(vec!["AIR", "AIR", "AIR", "RAIL"]));
+        let comments = StringArray::from(vec![Some("quick"), Some("fox"), Some("quick"), None]);
+        agg.update_batch(&[modes], &[&comments], None).unwrap();
+        let out = agg.finish().unwrap();
+        let column = |i: usize| out.column(i).as_any().downcast_ref::<UInt64Array>().unwrap().values().to_vec();
+        assert_eq!(column(1), vec![2, 0]);
+        assert_eq!(column(2), vec![2, 0]);
+    }
+
+    #[test]
+    fn test_quantiles() {
+        let median = AggregateFunction::Quantile { fraction: 0.5, mode: QuantileMode::Exact };
+        let p90 = AggregateFunction::Quantile { fraction: 0.9, mode: QuantileMode::Approx };
+        let new = || {
+            HashAggregator::try_new(
+                vec![Field::new("mode", DataType::Utf8, true)],
+                vec![AggregateExpr::new(median, Some(0), "median"), AggregateExpr::new(p90, Some(0), "p90")],
+            )
+            .unwrap()
+        };
+        let update = |agg: &mut HashAggregator, modes: Vec<&str>, vals: Vec<Option<f64>>| {
+            let mode: ArrayRef = Arc::new(StringArray::from(modes));
+            agg.update_batch(&[mode], &[&Float64Array::from(vals)], None).unwrap();
+        };
+
+        let mut a = new();
+        update(&mut a, vec!["AIR", "AIR", "RAIL"], vec![Some(4.0), Some(1.0), None]);
+        let mut b = new();
+        update(&mut b, vec!["AIR", "AIR", "SHIP"], vec![Some(3.0), Some(2.0), Some(7.0)]);
+        a.merge(&b).unwrap();
+
+        let out = a.finish().unwrap();
+        let column = |i: usize| out.column(i).as_any().downcast_ref::<Float64Array>().unwrap().iter().collect::<Vec<_>>();
+        // AIR: 1, 2, 3, 4; RAIL has only a NULL value
+        assert_eq!(column(1), vec![Some(2.5), None, Some(7.0)]);
+        let p90 = column(2);
+        assert!((p90[0].unwrap() - 3.7).abs() < 1e-9);
+        assert_eq!((p90[1], p90[2]), (None, Some(7.0)));
+    }
+}
@@ -0,0 +1,313 @@
+//! Resolve a scan input into the list of Parquet files behind it
+//!
+//! An input string is one of
+//! - a file (`data/lineitem.parquet`), used as is whatever its extension
+//! - a directory, searched recursively for `*.parquet` files
+//! - a glob (`data/lineitem-*.parquet`, `data/**/*.parquet`) with `*`, `?`,
+//!   `[abc]` / `[a-z]` / `[!abc]` per path component and `**` for any number
+//!   of directories
+//! - several of the above joined with the platform path-list separator
+//!   (`:` on Unix, `;` on Windows), as in `$PATH`
+//!
+//! Files and directories whose name starts with `.` or `_` (`_SUCCESS`,
+//! `.part-0.crc`) are skipped while listing, like Spark and Hive do.
+//! Directories named `key=value` are Hive partitions; see `hive_partitions`.
+
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::GooseError;
+
+/// Expand `input` into a sorted, de-duplicated list of files per part,
+/// keeping the order of the parts
+pub fn resolve_paths(input: &str) -> Result<Vec<PathBuf>, GooseError> {
+    let mut paths = Vec::new();
+    for part in std::env::split_paths(input) {
+        let mut found = if is_glob(&part) {
+            expand_glob(&part)?
+        } else if part.is_dir() {
+            let mut files = Vec::new();
+            list_parquet_files(&part, &mut files)?;
+            files
+        } else {
+            // Plain file: a missing one surfaces as an I/O error when opened
+            vec![part.clone()]
+        };
+        if found.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no Parquet files found at {}", part.display())).into());
+        }
+        found.sort();
+        for path in found {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    Ok(paths)
+}
+
+/// Join several inputs into one input string accepted by `resolve_paths`
+pub fn join_inputs<S: AsRef<str>>(inputs: &[S]) -> Result<String, GooseError> {
+    let joined = std::env::join_paths(inputs.iter().map(|s| s.as_ref()))
+        .map_err(|e| GooseError::Io(io::Error::new(io::ErrorKind::InvalidInput, e)))?;
+    Ok(joined.to_string_lossy().into_owned())
+}
+
+/// Hive partition value that stands for NULL
+const HIVE_DEFAULT_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+/// `key=value` directory components of a file path, outermost first
+///
+/// Values are percent-decoded; `__HIVE_DEFAULT_PARTITION__` is `None`.
+/// `data/l_shipyear=1998/l_shipmonth=09/part-0.parquet` gives
+/// `[("l_shipyear", Some("1998")), ("l_shipmonth", Some("09"))]`.
+pub fn hive_partitions(path: &Path) -> Vec<(String, Option<String>)> {
+    let Some(dir) = path.parent() else {
+        return Vec::new();
+    };
+    dir.components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => {
+                let name = name.to_string_lossy();
+                let (key, value) = name.split_once('=')?;
+                if key.is_empty() {
+                    return None;
+                }
+                let value = (value != HIVE_DEFAULT_PARTITION).then(|| percent_decode(value));
+                Some((percent_decode(key), value))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Undo Hive's `%XX` escaping of path characters
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn is_glob(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?', '['])
+}
+
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.') || name.starts_with('_')
+}
+
+/// Recursively collect `*.parquet` files below `dir`
+fn list_parquet_files(dir: &Path, out: &mut Vec<PathBuf>) -> Result<(), GooseError> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        if is_hidden(&name) {
+            continue;
+        }
+        if path.is_dir() {
+            list_parquet_files(&path, out)?;
+        } else if path.extension().is_some_and(|ext| ext == "parquet") {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Expand a glob by walking from its longest wildcard-free prefix
+fn expand_glob(pattern: &Path) -> Result<Vec<PathBuf>, GooseError> {
+    let mut base = PathBuf::new();
+    let mut components = pattern.components().peekable();
+    while let Some(component) = components.peek() {
+        if is_glob(Path::new(component.as_os_str())) {
+            break;
+        }
+        base.push(component.as_os_str());
+        components.next();
+    }
+    let rest: Vec<String> = components
+        .filter(|c| !matches!(c, Component::CurDir))
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+
+    let mut out = Vec::new();
+    let dir = if base.as_os_str().is_empty() { PathBuf::from(".") } else { base.clone() };
+    expand_components(&dir, &base, &rest, &mut out)?;
+    Ok(out)
+}
+
+/// Match the remaining pattern `components` below `dir`; `prefix` is the
+/// path to report (`dir` without the implicit `.` for relative globs)
+fn expand_components(dir: &Path, prefix: &Path, components: &[String], out: &mut Vec<PathBuf>) -> Result<(), GooseError> {
+    let Some((first, rest)) = components.split_first() else {
+        return Ok(());
+    };
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    if first == "**" {
+        // Zero directories ...
+        expand_components(dir, prefix, rest, out)?;
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        if is_hidden(&name) {
+            continue;
+        }
+        let reported = prefix.join(&name);
+        if first == "**" {
+            // ... or one more, keeping `**` for the next level
+            if path.is_dir() {
+                expand_components(&path, &reported, components, out)?;
+            }
+        } else if wildcard_match(first, &name) {
+            if rest.is_empty() {
+                if path.is_file() {
+                    out.push(reported);
+                }
+            } else {
+                expand_components(&path, &reported, rest, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Match one path component against a pattern with `*`, `?` and `[...]`
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(p + 1),
+            Some('[') => match_class(&pattern, p, name[n]),
+            Some(&c) if c == name[n] => Some(p + 1),
+            _ => None,
+        };
+        match (step, backtrack) {
+            (Some(next), _) => {
+                p = next;
+                n += 1;
+            }
+            (None, Some((star_p, star_n))) => {
+                p = star_p;
+                n = star_n + 1;
+                backtrack = Some((star_p, star_n + 1));
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Match `c` against the class starting at `pattern[start] == '['`,
+/// returning the pattern position after `]` on success
+fn match_class(pattern: &[char], start: usize, c: char) -> Option<usize> {
+    let mut i = start + 1;
+    let negate = matches!(pattern.get(i), Some('!') | Some('^'));
+    if negate {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    while let Some(&lo) = pattern.get(i) {
+        if lo == ']' && !first {
+            return (matched != negate).then_some(i + 1);
+        }
+        first = false;
+        if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).is_some_and(|&hi| hi != ']') {
+            matched |= lo <= c && c <= pattern[i + 2];
+            i += 3;
+        } else {
+            matched |= lo == c;
+            i += 1;
+        }
+    }
+    // Unterminated class: treat `[` as a literal
+    (c == '[').then_some(start + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_path;
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.parquet", "part-0.parquet"));
+        assert!(!wildcard_match("*.parquet", "part-0.parquet.crc"));
+        assert!(wildcard_match("part-?.parquet", "part-7.parquet"));
+        assert!(!wildcard_match("part-?.parquet", "part-10.parquet"));
+        assert!(wildcard_match("part-[0-3]*", "part-2-of-4"));
+        assert!(!wildcard_match("part-[!0-3]*", "part-2-of-4"));
+        assert!(wildcard_match("a*b*c", "aXbYbZc"));
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("[]x]", "]"));
+    }
+
+    #[test]
+    fn test_hive_partitions() {
+        let path = Path::new("/lake/lineitem/l_shipyear=1998/l_shipmonth=09/part-0.parquet");
+        assert_eq!(
+            hive_partitions(path),
+            vec![("l_shipyear".to_string(), Some("1998".to_string())), ("l_shipmonth".to_string(), Some("09".to_string()))]
+        );
+        let path = Path::new("mode=AIR%2FSEA/flag=__HIVE_DEFAULT_PARTITION__/a=b=c.parquet");
+        assert_eq!(
+            hive_partitions(path),
+            vec![("mode".to_string(), Some("AIR/SEA".to_string())), ("flag".to_string(), None)]
+        );
+        assert!(hive_partitions(Path::new("data/lineitem.parquet")).is_empty());
+    }
+
+    #[test]
+    fn test_resolve_paths() {
+        let root = temp_path("resolve_paths");
+        let _ = std::fs::remove_dir_all(&root);
+        for file in ["a/1.parquet", "a/2.parquet", "b/c/3.parquet", "b/notes.txt", "_tmp/4.parquet", "a/.5.parquet"] {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"").unwrap();
+        }
+        let root_str = root.to_str().unwrap();
+        let names = |paths: Vec<PathBuf>| -> Vec<String> {
+            paths.iter().map(|p| p.strip_prefix(&root).unwrap().to_string_lossy().into_owned()).collect()
+        };
+
+        let all = vec!["a/1.parquet", "a/2.parquet", "b/c/3.parquet"];
+        assert_eq!(names(resolve_paths(root_str).unwrap()), all);
+        assert_eq!(names(resolve_paths(&format!("{}/**/*.parquet", root_str)).unwrap()), all);
+        assert_eq!(names(resolve_paths(&format!("{}/a/[12].parquet", root_str)).unwrap()), vec!["a/1.parquet", "a/2.parquet"]);
+
+        // Lists keep their order and drop duplicates
+        let list = join_inputs(&[format!("{}/b", root_str), format!("{}/a/*", root_str), format!("{}/a/1.parquet", root_str)]).unwrap();
+        assert_eq!(names(resolve_paths(&list).unwrap()), vec!["b/c/3.parquet", "a/1.parquet", "a/2.parquet"]);
+
+        assert!(matches!(resolve_paths(&format!("{}/*.csv", root_str)), Err(GooseError::Io(_))));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+//! Integer accumulators for the exact sums, and the proof that picks one
+//!
+//! Like DuckDB's `sum_no_overflow`, the exact decimal path skips overflow
+//! checks only where the Parquet footer proves them unnecessary: every
+//! surviving row group has min / max statistics for the four measures, and
+//! `rows * max |value|` of each sum (the derived `disc_price` and `charge`
+//! included) fits the accumulator. The narrowest width that fits is used;
+//! without a proof the sums are `Checked` and an overflow is an error
+//! instead of a wrapped result.
+
+use std::fmt;
+
+use crate::decimal_aggregator::DecimalScales;
+use crate::error::GooseError;
+
+/// Unscaled integer type the exact sums are accumulated in
+pub trait IntegerSum: Copy + Default + fmt::Debug + Send + 'static {
+    /// Which accumulator this is, for plans and errors
+    const WIDTH: SumWidth;
+
+    /// An input value; only called on values the width was chosen for
+    fn from_unscaled(value: i128) -> Self;
+
+    /// `None` on overflow; the proven widths never return it
+    fn add(self, other: Self) -> Option<Self>;
+
+    fn sub(self, other: Self) -> Option<Self>;
+
+    fn mul(self, other: Self) -> Option<Self>;
+
+    fn to_i128(self) -> i128;
+}
+
+impl IntegerSum for i64 {
+    const WIDTH: SumWidth = SumWidth::I64;
+
+    #[inline(always)]
+    fn from_unscaled(value: i128) -> Self {
+        value as i64
+    }
+
+    #[inline(always)]
+    fn add(self, other: Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    #[inline(always)]
+    fn sub(self, other: Self) -> Option<Self> {
+        Some(self - other)
+    }
+
+    #[inline(always)]
+    fn mul(self, other: Self) -> Option<Self> {
+        Some(self * other)
+    }
+
+    fn to_i128(self) -> i128 {
+        self as i128
+    }
+}
+
+impl IntegerSum for i128 {
+    const WIDTH: SumWidth = SumWidth::I128;
+
+    #[inline(always)]
+    fn from_unscaled(value: i128) -> Self {
+        value
+    }
+
+    #[inline(always)]
+    fn add(self, other: Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    #[inline(always)]
+    fn sub(self, other: Self) -> Option<Self> {
+        Some(self - other)
+    }
+
+    #[inline(always)]
+    fn mul(self, other: Self) -> Option<Self> {
+        Some(self * other)
+    }
+
+    fn to_i128(self) -> i128 {
+        self
+    }
+}
+
+/// i128 with every add and multiply checked
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Checked(pub i128);
+
+impl IntegerSum for Checked {
+    const WIDTH: SumWidth = SumWidth::Checked;
+
+    #[inline(always)]
+    fn from_unscaled(value: i128) -> Self {
+        Checked(value)
+    }
+
+    #[inline(always)]
+    fn add(self, other: Self) -> Option<Self> {
+        self.0.checked_add(other.0).map(Checked)
+    }
+
+    #[inline(always)]
+    fn sub(self, other: Self) -> Option<Self> {
+        self.0.checked_sub(other.0).map(Checked)
+    }
+
+    #[inline(always)]
+    fn mul(self, other: Self) -> Option<Self> {
+        self.0.checked_mul(other.0).map(Checked)
+    }
+
+    fn to_i128(self) -> i128 {
+        self.0
+    }
+}
+
+/// Accumulator chosen for a query's exact sums
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SumWidth {
+    /// Proven to fit 64 bits
+    I64,
+    /// Proven to fit 128 bits
+    I128,
+    /// Not proven: i128 with checked arithmetic
+    Checked,
+}
+
+impl fmt::Display for SumWidth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SumWidth::I64 => write!(f, "i64"),
+            SumWidth::I128 => write!(f, "i128"),
+            SumWidth::Checked => write!(f, "checked i128"),
+        }
+    }
+}
+
+/// Unscaled min and max of a measure over the scanned row groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    pub min: i128,
+    pub max: i128,
+}
+
+impl Bounds {
+    /// Largest magnitude of `offset + sign * value` over the bounds
+    fn magnitude(&self, offset: i128, sign: i128) -> Option<u128> {
+        let at = |v: i128| sign.checked_mul(v).and_then(|v| v.checked_add(offset)).map(i128::unsigned_abs);
+        Some(at(self.min)?.max(at(self.max)?))
+    }
+}
+
+/// Bounds of Q1's four measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeasureBounds {
+    pub quantity: Bounds,
+    pub price: Bounds,
+    pub discount: Bounds,
+    pub tax: Bounds,
+}
+
+/// The narrowest accumulator that provably holds every exact sum over
+/// `rows` rows, `Checked` without bounds or when even i128 is not proven
+pub fn choose_width(rows: u64, bounds: Option<MeasureBounds>, scales: DecimalScales) -> SumWidth {
+    let Some(largest) = bounds.and_then(|b| largest_sum(rows, &b, scales)) else {
+        return SumWidth::Checked;
+    };
+    if largest <= i64::MAX as u128 {
+        SumWidth::I64
+    } else if largest <= i128::MAX as u128 {
+        SumWidth::I128
+    } else {
+        SumWidth::Checked
+    }
+}
+
+/// Largest magnitude any of the five sums, or a per-row factor, can reach
+fn largest_sum(rows: u64, b: &MeasureBounds, scales: DecimalScales) -> Option<u128> {
+    let one = |scale: i8| 10i128.checked_pow(scale.max(0) as u32);
+    // `1 - discount` and `1 + tax` at their scales
+    let (discount_factor, tax_factor) = (b.discount.magnitude(one(scales.discount)?, -1)?, b.tax.magnitude(one(scales.tax)?, 1)?);
+    let price = b.price.magnitude(0, 1)?;
+    let disc_price = price.checked_mul(discount_factor)?;
+    let charge = disc_price.checked_mul(tax_factor)?;
+    let per_row = [b.quantity.magnitude(0, 1)?, price, b.discount.magnitude(0, 1)?, disc_price, charge];
+    let sums = per_row.into_iter().max()?.checked_mul(rows.max(1) as u128)?;
+    Some(sums.max(discount_factor).max(tax_factor))
+}
+
+/// Error for a checked exact sum that left the i128 range
+#[cold]
+pub fn overflow_error() -> GooseError {
+    GooseError::Overflow("exact decimal sum exceeds i128; footer statistics could not rule this out".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCALES: DecimalScales = DecimalScales { quantity: 2, price: 2, discount: 2, tax: 2 };
+
+    /// TPC-H value ranges, unscaled at scale 2
+    fn tpch() -> MeasureBounds {
+        MeasureBounds {
+            quantity: Bounds { min: 100, max: 5_000 },
+            price: Bounds { min: 90_000, max: 10_494_950 },
+            discount: Bounds { min: 0, max: 10 },
+            tax: Bounds { min: 0, max: 8 },
+        }
+    }
+
+    #[test]
+    fn test_choose_width() {
+        // charge per row is at most 10_494_950 * 100 * 108 ≈ 1.1e11
+        assert_eq!(choose_width(6_000_000, Some(tpch()), SCALES), SumWidth::I64);
+        assert_eq!(choose_width(60_000_000, Some(tpch()), SCALES), SumWidth::I64);
+        assert_eq!(choose_width(600_000_000, Some(tpch()), SCALES), SumWidth::I128);
+        assert_eq!(choose_width(6_000_000, None, SCALES), SumWidth::Checked);
+
+        // Negative discounts make `1 - discount` the larger factor
+        let mut skewed = tpch();
+        skewed.discount = Bounds { min: -1_000_000, max: 0 };
+        assert_eq!(choose_width(60_000_000, Some(skewed), SCALES), SumWidth::I128);
+
+        // Past i128 the proof fails rather than wrapping
+        let huge = Bounds { min: i128::MIN / 2, max: i128::MAX / 2 };
+        let huge = MeasureBounds { quantity: huge, price: huge, discount: huge, tax: huge };
+        assert_eq!(choose_width(2, Some(huge), SCALES), SumWidth::Checked);
+        assert_eq!(choose_width(0, Some(tpch()), SCALES), SumWidth::I64);
+    }
+
+    #[test]
+    fn test_checked() {
+        assert_eq!(Checked(i128::MAX).add(Checked(1)), None);
+        assert_eq!(Checked(i128::MAX / 2).mul(Checked(3)), None);
+        assert_eq!(Checked(-2).mul(Checked(3)).and_then(|v| v.add(Checked(1))), Some(Checked(-5)));
+        assert_eq!(<i64 as IntegerSum>::from_unscaled(-7).to_i128(), -7);
+    }
+}
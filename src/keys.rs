@@ -0,0 +1,222 @@
+//! Byte access to Q1's single-character group keys
+//!
+//! The perfect-hash aggregators key on one byte per row. `key_bytes` gives
+//! the unrolled loop a plain byte slice with row `i` at index `i`, but only
+//! after checking that every row really is one byte; otherwise it returns
+//! `None` and the caller falls back to the checked row-at-a-time path built
+//! on `key_byte`, which rejects multi-byte keys instead of misreading them.
+//!
+//! Dictionary-encoded keys are best handled through `key_ranks`, which
+//! maps each dictionary value once and then gathers per row by code, so
+//! neither the strings nor their byte layout are looked at per row.
+//!
+//! Accepted key columns are `Utf8`, `LargeUtf8`, `Utf8View` and
+//! dictionaries of `Utf8` with any integer key type.
+
+use std::borrow::Cow;
+
+use arrow::array::{downcast_dictionary_array, Array, AsArray, GenericStringArray, OffsetSizeTrait, StringViewArray};
+use arrow::datatypes::{ArrowNativeType, DataType};
+
+use crate::error::GooseError;
+
+/// Key column types, as reported in type mismatch errors
+pub const KEY_TYPES: &str = "Utf8, LargeUtf8, Utf8View or Dictionary(_, Utf8)";
+
+/// Whether a column of this type can be a Q1 group key
+pub fn is_key_type(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => true,
+        DataType::Dictionary(key, value) => key.is_dictionary_key_type() && **value == DataType::Utf8,
+        _ => false,
+    }
+}
+
+/// The key bytes of every row, `None` unless each row is exactly one byte
+///
+/// Offset-based arrays are borrowed straight from the value buffer, taking
+/// the slice offset into account; views and dictionaries are gathered.
+/// Arrays with NULLs are never one byte per row.
+pub fn key_bytes(array: &dyn Array) -> Option<Cow<'_, [u8]>> {
+    if array.null_count() > 0 {
+        return None;
+    }
+    match array.data_type() {
+        DataType::Utf8 => offset_bytes(array.as_string::<i32>()),
+        DataType::LargeUtf8 => offset_bytes(array.as_string::<i64>()),
+        DataType::Utf8View => view_bytes(array.as_string_view()),
+        dt if is_key_type(dt) => {
+            let table = value_table(array, |value| match value {
+                [b] => Some(*b),
+                _ => None,
+            });
+            dictionary_gather(array, &table).map(Cow::Owned)
+        }
+        _ => None,
+    }
+}
+
+/// Per-row `rank` of each key, or `None` if some row is NULL or (outside
+/// dictionaries) not one byte
+///
+/// `rank` maps a key's bytes to a small code, for example its position in
+/// a key domain. For a dictionary it runs once per dictionary value and the
+/// rows are then gathered by their codes, so a batch costs one lookup per
+/// distinct value plus a gather, whatever the length of the strings.
+pub fn key_ranks(array: &dyn Array, rank: impl Fn(&[u8]) -> u8) -> Option<Vec<u8>> {
+    if array.null_count() > 0 {
+        return None;
+    }
+    if is_key_type(array.data_type()) && matches!(array.data_type(), DataType::Dictionary(_, _)) {
+        let table = value_table(array, |value| Some(rank(value)));
+        return dictionary_gather(array, &table);
+    }
+    Some(key_bytes(array)?.iter().map(|&b| rank(&[b])).collect())
+}
+
+/// `map` applied to each value of a `Utf8` dictionary; NULL values map to `None`
+fn value_table(array: &dyn Array, map: impl Fn(&[u8]) -> Option<u8>) -> Vec<Option<u8>> {
+    downcast_dictionary_array!(
+        array => {
+            let values = array.values().as_string::<i32>();
+            (0..values.len()).map(|k| values.is_valid(k).then(|| map(values.value(k).as_bytes())).flatten()).collect()
+        },
+        _ => Vec::new()
+    )
+}
+
+/// `table[code]` for each row of a dictionary, `None` if any entry is
+fn dictionary_gather(array: &dyn Array, table: &[Option<u8>]) -> Option<Vec<u8>> {
+    downcast_dictionary_array!(
+        array => array.keys().values().iter().map(|code| table.get(code.as_usize()).copied().flatten()).collect(),
+        _ => None
+    )
+}
+
+/// Borrow the value buffer when the offsets advance by exactly one per row
+fn offset_bytes<O: OffsetSizeTrait>(array: &GenericStringArray<O>) -> Option<Cow<'_, [u8]>> {
+    // Offsets of a sliced array start at the slice's first value, not at 0
+    let offsets = array.value_offsets();
+    let start = offsets[0].as_usize();
+    let one_byte = offsets.iter().enumerate().all(|(i, offset)| offset.as_usize() == start + i);
+    one_byte.then(|| Cow::Borrowed(&array.value_data()[start..start + array.len()]))
+}
+
+/// Gather the inlined byte of views that are all one byte long
+fn view_bytes(array: &StringViewArray) -> Option<Cow<'_, [u8]>> {
+    // A view is length (low 32 bits) then up to 12 inlined bytes
+    let bytes = array
+        .views()
+        .iter()
+        .map(|&view| (view as u32 == 1).then_some((view >> 32) as u8))
+        .collect::<Option<Vec<u8>>>()?;
+    Some(Cow::Owned(bytes))
+}
+
+/// Key byte of row `i`, `None` for NULL; errors on keys that are not one byte
+pub fn key_byte(array: &dyn Array, i: usize) -> Result<Option<u8>, GooseError> {
+    if array.is_null(i) {
+        return Ok(None);
+    }
+    let value = match array.data_type() {
+        DataType::Utf8 => array.as_string::<i32>().value(i),
+        DataType::LargeUtf8 => array.as_string::<i64>().value(i),
+        DataType::Utf8View => array.as_string_view().value(i),
+        dt if is_key_type(dt) => {
+            let (code, values) = downcast_dictionary_array!(
+                array => (array.keys().value(i).as_usize(), array.values().as_string::<i32>()),
+                _ => unreachable!("is_key_type checked for a dictionary")
+            );
+            if code >= values.len() {
+                return Err(GooseError::InvalidData(format!("dictionary key out of range at row {}", i)));
+            }
+            if values.is_null(code) {
+                return Ok(None);
+            }
+            values.value(code)
+        }
+        dt => return Err(GooseError::type_mismatch("group key", KEY_TYPES, dt)),
+    };
+    match value.as_bytes() {
+        [b] => Ok(Some(*b)),
+        other => Err(GooseError::InvalidData(format!("group key {:?} is not single-byte", String::from_utf8_lossy(other)))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{DictionaryArray, Int8Array, LargeStringArray, StringArray};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_sliced_and_multi_byte() {
+        let array = StringArray::from(vec!["AB", "N", "R", "A", "É"]);
+        assert!(key_bytes(&array).is_none());
+        // The value buffer starts with "AB"; the slice must not read it
+        assert_eq!(key_bytes(&array.slice(1, 3)).unwrap().as_ref(), b"NRA");
+        assert!(key_bytes(&array.slice(3, 2)).is_none());
+        assert_eq!(key_bytes(&array.slice(2, 0)).unwrap().as_ref(), b"");
+
+        // Total length matches the row count, but the stride is 0, 2, 1
+        let uneven = StringArray::from(vec!["", "AN", "R"]);
+        assert!(key_bytes(&uneven).is_none());
+        assert_eq!(key_byte(&uneven, 2).unwrap(), Some(b'R'));
+        assert!(matches!(key_byte(&uneven, 1), Err(GooseError::InvalidData(_))));
+        assert!(matches!(key_byte(&array, 4), Err(GooseError::InvalidData(_))));
+
+        let nulls = StringArray::from(vec![Some("A"), None]);
+        assert!(key_bytes(&nulls).is_none());
+        assert_eq!(key_byte(&nulls, 1).unwrap(), None);
+    }
+
+    #[test]
+    fn test_other_string_types() {
+        let large = LargeStringArray::from(vec!["X", "A", "F"]).slice(1, 2);
+        assert_eq!(key_bytes(&large).unwrap().as_ref(), b"AF");
+
+        let view = StringViewArray::from(vec!["A", "O", "a longer string than twelve bytes"]);
+        assert_eq!(key_bytes(&view.slice(0, 2)).unwrap().as_ref(), b"AO");
+        assert!(key_bytes(&view).is_none());
+        assert_eq!(key_byte(&view, 1).unwrap(), Some(b'O'));
+
+        let values = Arc::new(StringArray::from(vec![Some("R"), Some("NN"), None, Some("A")]));
+        let dictionary = DictionaryArray::new(Int8Array::from(vec![3, 0, 0, 3]), values.clone());
+        assert_eq!(key_bytes(&dictionary).unwrap().as_ref(), b"ARRA");
+        assert_eq!(key_bytes(&dictionary.slice(1, 2)).unwrap().as_ref(), b"RR");
+        // A multi-byte or NULL dictionary value only matters where it is used
+        let dictionary = DictionaryArray::new(Int8Array::from(vec![0, 1, 2]), values);
+        assert!(key_bytes(&dictionary).is_none());
+        assert!(matches!(key_byte(&dictionary, 1), Err(GooseError::InvalidData(_))));
+        assert_eq!(key_byte(&dictionary, 2).unwrap(), None);
+
+        let ints = Int8Array::from(vec![1]);
+        assert!(!is_key_type(ints.data_type()));
+        assert!(key_bytes(&ints).is_none());
+        assert!(matches!(key_byte(&ints, 0), Err(GooseError::TypeMismatch { .. })));
+    }
+
+    #[test]
+    fn test_key_ranks() {
+        use arrow::array::Int32Array;
+
+        let rank = |value: &[u8]| match value {
+            b"A" => 0,
+            b"N" => 1,
+            b"RETURNED" => 2,
+            _ => u8::MAX,
+        };
+        // Each dictionary value is ranked once; multi-byte values are fine
+        let values = Arc::new(StringArray::from(vec![Some("RETURNED"), Some("A"), Some("??"), None]));
+        let dictionary = DictionaryArray::new(Int32Array::from(vec![1, 0, 0, 2, 1]), values.clone());
+        assert_eq!(key_ranks(&dictionary, rank).unwrap(), vec![0, 2, 2, u8::MAX, 0]);
+        assert_eq!(key_ranks(&dictionary.slice(1, 2), rank).unwrap(), vec![2, 2]);
+        // A row pointing at a NULL value needs the checked path
+        let dictionary = DictionaryArray::new(Int32Array::from(vec![1, 3]), values);
+        assert!(key_ranks(&dictionary, rank).is_none());
+
+        // Plain strings are ranked per row, and only when one byte each
+        assert_eq!(key_ranks(&StringArray::from(vec!["N", "A", "X"]), rank).unwrap(), vec![1, 0, u8::MAX]);
+        assert!(key_ranks(&StringArray::from(vec!["N", "RETURNED"]), rank).is_none());
+    }
+}
@@ -2,6 +2,24 @@ pub mod reader;
 pub mod filter;
 
 pub mod aggregator;
+pub mod hash_aggregator;
+pub mod decimal_aggregator;
+pub mod keys;
+pub mod distinct;
+pub mod quantile;
+pub mod summation;
+pub mod integer_sum;
 pub mod query;
 pub mod utils;
 pub mod memory;
+pub mod params;
+pub mod plan;
+pub mod profile;
+pub mod sql;
+pub mod cli;
+pub mod error;
+pub mod input;
+pub mod dbgen;
+
+#[cfg(test)]
+mod test_util;
@@ -0,0 +1,87 @@
+//! TPC-H Q1 substitution parameters
+//!
+//! The spec defines the predicate as
+//! `l_shipdate <= date '1998-12-01' - interval '[DELTA]' day`
+//! with DELTA drawn from [60, 120]. The validation query uses DELTA = 90,
+//! which gives the familiar 1998-09-02 cutoff.
+
+/// 1998-12-01 as days since epoch, the date DELTA is subtracted from
+pub const Q1_BASE_DATE_DAYS: i32 = 10561;
+
+/// DELTA used by the TPC-H validation query
+pub const DEFAULT_DELTA_DAYS: i32 = 90;
+
+/// Range of DELTA values allowed for qualification runs
+pub const DELTA_RANGE: std::ops::RangeInclusive<i32> = 60..=120;
+
+/// How the ship-date cutoff is specified
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShipDateCutoff {
+    /// DELTA days before 1998-12-01
+    DeltaDays(i32),
+    /// Explicit cutoff as days since epoch
+    Date(i32),
+}
+
+/// Parameters for one execution of Q1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Q1Params {
+    pub cutoff: ShipDateCutoff,
+}
+
+impl Default for Q1Params {
+    fn default() -> Self {
+        Self::with_delta(DEFAULT_DELTA_DAYS)
+    }
+}
+
+impl Q1Params {
+    /// Cutoff at 1998-12-01 minus `delta_days`
+    pub fn with_delta(delta_days: i32) -> Self {
+        Self { cutoff: ShipDateCutoff::DeltaDays(delta_days) }
+    }
+
+    /// Cutoff at an explicit date (days since epoch)
+    pub fn with_date(days: i32) -> Self {
+        Self { cutoff: ShipDateCutoff::Date(days) }
+    }
+
+    /// Inclusive ship-date cutoff as days since epoch
+    #[inline]
+    pub fn cutoff_days(&self) -> i32 {
+        match self.cutoff {
+            ShipDateCutoff::DeltaDays(delta) => Q1_BASE_DATE_DAYS - delta,
+            ShipDateCutoff::Date(days) => days,
+        }
+    }
+
+    /// Whether this is a DELTA the spec accepts for qualification runs
+    pub fn is_qualification_delta(&self) -> bool {
+        matches!(self.cutoff, ShipDateCutoff::DeltaDays(d) if DELTA_RANGE.contains(&d))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::FILTER_DATE_DAYS;
+
+    #[test]
+    fn test_default_matches_filter_date() {
+        assert_eq!(Q1Params::default().cutoff_days(), FILTER_DATE_DAYS);
+        assert!(Q1Params::default().is_qualification_delta());
+    }
+
+    #[test]
+    fn test_delta_bounds() {
+        use chrono::NaiveDate;
+        let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+        let days = |y, m, d| (NaiveDate::from_ymd_opt(y, m, d).unwrap() - epoch).num_days() as i32;
+
+        assert_eq!(Q1Params::with_delta(0).cutoff_days(), days(1998, 12, 1));
+        assert_eq!(Q1Params::with_delta(60).cutoff_days(), days(1998, 10, 2));
+        assert_eq!(Q1Params::with_delta(120).cutoff_days(), days(1998, 8, 3));
+        assert!(!Q1Params::with_delta(121).is_qualification_delta());
+        assert!(!Q1Params::with_date(days(1998, 9, 2)).is_qualification_delta());
+    }
+}
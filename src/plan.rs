@@ -0,0 +1,712 @@
+//! Logical and physical query plans
+//!
+//! A `LogicalPlan` is the operator tree of a bound query: Scan, Filter,
+//! Aggregate, Project, Sort and Limit. `create_physical_plan` opens the
+//! file, prunes row groups and picks the aggregation strategy, producing a
+//! `PhysicalPlan` that can be executed or printed with `explain()`.
+//!
+//! Execution is pull-based: every operator yields a stream of record
+//! batches. An Aggregate directly above a Filter and Scan is fused with
+//! them, so the aggregator reads the selection mask instead of a filtered
+//! copy of each batch, as in `query.rs`.
+
+use std::fmt::Write;
+use std::sync::Arc;
+
+use arrow::array::{Array, ArrayRef, BooleanArray, RecordBatch};
+use arrow::compute::{and_kleene, concat_batches, filter_record_batch, lexsort_to_indices, take};
+use arrow::compute::{SortColumn, SortOptions};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+
+use crate::aggregator::{results_to_record_batch, Q1_OUTPUT_COLUMNS};
+use crate::hash_aggregator::{key_output_field, AggregateExpr, AggregateFunction, HashAggregator};
+use crate::quantile::QuantileMode;
+use crate::params::Q1Params;
+use crate::profile::QueryProfile;
+use crate::query::{choose_strategy, run_perfect_hash, AggregationStrategy, ExecutionOptions, Q1_GROUP_BY, Q1_MEASURE_COLUMNS};
+use crate::reader::{plan_scan, LineitemPlan};
+use crate::sql::binder::{aggregate_column, group_column, BoundAggregate, BoundQuery};
+use crate::sql::eval::{evaluate, project};
+use crate::sql::parser::{format_date, parse_expr, Expr};
+use crate::error::GooseError;
+
+/// Stream of record batches produced by an operator
+pub type BatchStream<'a> = Box<dyn Iterator<Item = Result<RecordBatch, GooseError>> + 'a>;
+
+/// One ORDER BY key over the input of a Sort
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    /// Input column index
+    pub column: usize,
+    /// Input column name (for display)
+    pub name: String,
+    pub descending: bool,
+}
+
+/// Operator tree of a query, independent of the data
+#[derive(Debug, Clone)]
+pub enum LogicalPlan {
+    Scan {
+        path: String,
+        columns: Vec<String>,
+        /// Inclusive `l_shipdate` cutoff available for row group pruning
+        cutoff: Option<i32>,
+    },
+    Filter {
+        /// `l_shipdate <= cutoff`, evaluated with `create_date_filter_mask`
+        cutoff: Option<i32>,
+        /// Any other conjuncts
+        predicate: Option<Expr>,
+        input: Box<LogicalPlan>,
+    },
+    Aggregate {
+        group_by: Vec<Expr>,
+        aggregates: Vec<BoundAggregate>,
+        input: Box<LogicalPlan>,
+    },
+    Project {
+        exprs: Vec<(Expr, String)>,
+        input: Box<LogicalPlan>,
+    },
+    Sort {
+        keys: Vec<SortKey>,
+        input: Box<LogicalPlan>,
+    },
+    Limit {
+        limit: usize,
+        input: Box<LogicalPlan>,
+    },
+}
+
+impl LogicalPlan {
+    /// Build the operator tree of a bound SQL query
+    pub fn from_query(query: &BoundQuery) -> Self {
+        let mut plan = LogicalPlan::Scan {
+            path: query.path.clone(),
+            columns: query.scan_columns.clone(),
+            cutoff: query.cutoff,
+        };
+        if query.cutoff.is_some() || query.predicate.is_some() {
+            plan = LogicalPlan::Filter {
+                cutoff: query.cutoff,
+                predicate: query.predicate.clone(),
+                input: Box::new(plan),
+            };
+        }
+        if query.is_aggregate() {
+            plan = LogicalPlan::Aggregate {
+                group_by: query.group_by.clone(),
+                aggregates: query.aggregates.clone(),
+                input: Box::new(plan),
+            };
+        }
+        plan = LogicalPlan::Project {
+            exprs: query.projection.clone(),
+            input: Box::new(plan),
+        };
+        if !query.order_by.is_empty() {
+            plan = LogicalPlan::Sort {
+                keys: query
+                    .order_by
+                    .iter()
+                    .map(|&(column, descending)| SortKey {
+                        column,
+                        name: query.projection[column].1.clone(),
+                        descending,
+                    })
+                    .collect(),
+                input: Box::new(plan),
+            };
+        }
+        if let Some(limit) = query.limit {
+            plan = LogicalPlan::Limit {
+                limit,
+                input: Box::new(plan),
+            };
+        }
+        plan
+    }
+
+    /// Open the scanned file, prune row groups and choose operators
+    pub fn create_physical_plan(&self) -> Result<PhysicalPlan, GooseError> {
+        Ok(match self {
+            LogicalPlan::Scan { path, columns, cutoff } => scan(path, columns, *cutoff)?,
+            LogicalPlan::Filter { cutoff, predicate, input } => PhysicalPlan::Filter {
+                cutoff: *cutoff,
+                predicate: predicate.clone(),
+                input: Box::new(input.create_physical_plan()?),
+            },
+            LogicalPlan::Aggregate { group_by, aggregates, input } => {
+                // Q1-shaped aggregates can use the perfect hash, which reads
+                // the Q1 columns straight from a (date-filtered) scan
+                if let (Some(measures), Some((path, cutoff))) = (q1_measures(group_by, aggregates), cutoff_scan(input)) {
+                    let mut columns: Vec<String> = Q1_GROUP_BY.iter().map(|c| c.to_string()).collect();
+                    columns.extend(Q1_MEASURE_COLUMNS.iter().map(|c| c.to_string()));
+                    if let Ok(PhysicalPlan::Scan { plan, columns, cutoff }) = scan(path, &columns, cutoff) {
+                        if let AggregationStrategy::PerfectHash(keys) = choose_strategy(&plan, Q1_GROUP_BY)? {
+                            let mut input = PhysicalPlan::Scan { plan, columns, cutoff };
+                            if cutoff.is_some() {
+                                input = PhysicalPlan::Filter {
+                                    cutoff,
+                                    predicate: None,
+                                    input: Box::new(input),
+                                };
+                            }
+                            return Ok(PhysicalPlan::Aggregate {
+                                strategy: AggregationStrategy::PerfectHash(keys),
+                                q1_measures: Some(measures),
+                                group_by: group_by.clone(),
+                                aggregates: aggregates.clone(),
+                                input: Box::new(input),
+                            });
+                        }
+                    }
+                }
+                PhysicalPlan::Aggregate {
+                    strategy: AggregationStrategy::Hash,
+                    q1_measures: None,
+                    group_by: group_by.clone(),
+                    aggregates: aggregates.clone(),
+                    input: Box::new(input.create_physical_plan()?),
+                }
+            }
+            LogicalPlan::Project { exprs, input } => PhysicalPlan::Project {
+                exprs: exprs.clone(),
+                input: Box::new(input.create_physical_plan()?),
+            },
+            LogicalPlan::Sort { keys, input } => PhysicalPlan::Sort {
+                keys: keys.clone(),
+                input: Box::new(input.create_physical_plan()?),
+            },
+            LogicalPlan::Limit { limit, input } => PhysicalPlan::Limit {
+                limit: *limit,
+                input: Box::new(input.create_physical_plan()?),
+            },
+        })
+    }
+}
+
+/// Plan a scan, pruning row groups with the cutoff
+fn scan(path: &str, columns: &[String], cutoff: Option<i32>) -> Result<PhysicalPlan, GooseError> {
+    let params = Q1Params::with_date(cutoff.unwrap_or(i32::MAX));
+    let mut columns = columns.to_vec();
+    if columns.is_empty() {
+        // COUNT(*) or constant projections still need row counts
+        columns.push(plan_scan(path, &params, &[])?.schema().field(0).name().clone());
+    }
+    let names: Vec<&str> = columns.iter().map(String::as_str).collect();
+    let plan = plan_scan(path, &params, &names)?;
+    Ok(PhysicalPlan::Scan { plan, columns, cutoff })
+}
+
+/// `(path, cutoff)` if the input is a scan filtered at most by the cutoff
+fn cutoff_scan(input: &LogicalPlan) -> Option<(&str, Option<i32>)> {
+    match input {
+        LogicalPlan::Scan { path, cutoff, .. } => Some((path, *cutoff)),
+        LogicalPlan::Filter { predicate: None, input, .. } => cutoff_scan(input),
+        _ => None,
+    }
+}
+
+/// Q1 output column for each aggregate, when the aggregation is Q1-shaped
+///
+/// The grouping must be exactly `(l_returnflag, l_linestatus)` and every
+/// aggregate one that `Aggregator` already computes.
+fn q1_measures(group_by: &[Expr], aggregates: &[BoundAggregate]) -> Option<Vec<usize>> {
+    let keys: Vec<Expr> = Q1_GROUP_BY.iter().map(|c| Expr::Column(c.to_string())).collect();
+    if group_by != keys.as_slice() {
+        return None;
+    }
+
+    let measure = |sql: &str| parse_expr(sql).ok();
+    let known: Vec<(AggregateFunction, Option<Expr>, &str)> = vec![
+        (AggregateFunction::Sum, measure("l_quantity"), "sum_qty"),
+        (AggregateFunction::Sum, measure("l_extendedprice"), "sum_base_price"),
+        (AggregateFunction::Sum, measure("l_extendedprice * (1 - l_discount)"), "sum_disc_price"),
+        (AggregateFunction::Sum, measure("l_extendedprice * (1 - l_discount) * (1 + l_tax)"), "sum_charge"),
+        (AggregateFunction::Avg, measure("l_quantity"), "avg_qty"),
+        (AggregateFunction::Avg, measure("l_extendedprice"), "avg_price"),
+        (AggregateFunction::Avg, measure("l_discount"), "avg_disc"),
+        (AggregateFunction::CountStar, None, "count_order"),
+    ];
+
+    aggregates
+        .iter()
+        .map(|BoundAggregate { func, arg }| {
+            let (_, _, name) = known.iter().find(|(f, a, _)| f == func && a == arg)?;
+            Q1_OUTPUT_COLUMNS.iter().position(|c| c == name)
+        })
+        .collect()
+}
+
+/// Executable operator tree over a planned file
+pub enum PhysicalPlan {
+    /// TABLE_SCAN over the row groups that survived pruning
+    Scan {
+        plan: LineitemPlan,
+        columns: Vec<String>,
+        cutoff: Option<i32>,
+    },
+    Filter {
+        cutoff: Option<i32>,
+        predicate: Option<Expr>,
+        input: Box<PhysicalPlan>,
+    },
+    /// PERFECT_HASH_GROUP_BY or HASH_GROUP_BY, depending on `strategy`
+    ///
+    /// Output columns are `__group{i}` followed by `__agg{i}`.
+    Aggregate {
+        strategy: AggregationStrategy,
+        /// Q1 output column per aggregate (perfect hash only)
+        q1_measures: Option<Vec<usize>>,
+        group_by: Vec<Expr>,
+        aggregates: Vec<BoundAggregate>,
+        input: Box<PhysicalPlan>,
+    },
+    Project {
+        exprs: Vec<(Expr, String)>,
+        input: Box<PhysicalPlan>,
+    },
+    Sort {
+        keys: Vec<SortKey>,
+        input: Box<PhysicalPlan>,
+    },
+    Limit {
+        limit: usize,
+        input: Box<PhysicalPlan>,
+    },
+}
+
+impl PhysicalPlan {
+    /// Operator tree, one operator per line, children indented below parents
+    pub fn explain(&self) -> String {
+        let mut out = String::new();
+        self.explain_into(&mut out, 0);
+        out
+    }
+
+    fn explain_into(&self, out: &mut String, depth: usize) {
+        let indent = "  ".repeat(depth);
+        let input = match self {
+            PhysicalPlan::Scan { plan, columns, cutoff } => {
+                let total = plan.num_row_groups();
+                let kept = plan.row_groups().len();
+                let _ = write!(out, "{}TABLE_SCAN {}", indent, plan.path());
+                let (files, files_pruned) = (plan.num_files_total(), plan.num_files_pruned());
+                if files_pruned > 0 {
+                    let _ = write!(out, " files={}/{}", files - files_pruned, files);
+                } else if files > 1 {
+                    let _ = write!(out, " files={}", files);
+                }
+                let _ = write!(
+                    out,
+                    " columns=[{}] row_groups={}/{} ({} pruned",
+                    columns.join(", "),
+                    kept,
+                    total,
+                    total - kept
+                );
+                match cutoff {
+                    Some(days) => {
+                        let _ = write!(out, " by l_shipdate <= {}", format_date(*days));
+                        match plan.num_row_groups_all_pass() {
+                            0 => out.push(')'),
+                            n => {
+                                let _ = write!(out, ", {} all-pass)", n);
+                            }
+                        }
+                    }
+                    None => out.push(')'),
+                }
+                let (pages, pages_skipped, pages_all_pass) = plan.page_counts();
+                if cutoff.is_some() && pages_skipped + pages_all_pass > 0 {
+                    let _ = write!(
+                        out,
+                        " pages={}/{} ({} all-pass)",
+                        pages - pages_skipped,
+                        pages,
+                        pages_all_pass
+                    );
+                }
+                out.push('\n');
+                None
+            }
+            PhysicalPlan::Filter { cutoff, predicate, input } => {
+                let mut conjuncts: Vec<String> = cutoff
+                    .iter()
+                    .map(|days| format!("l_shipdate <= DATE '{}'", format_date(*days)))
+                    .collect();
+                conjuncts.extend(predicate.iter().map(|p| p.to_string()));
+                let _ = writeln!(out, "{}FILTER {}", indent, conjuncts.join(" AND "));
+                Some(input)
+            }
+            PhysicalPlan::Aggregate { strategy, group_by, aggregates, input, .. } => {
+                let operator = match strategy {
+                    AggregationStrategy::PerfectHash(keys) => format!("PERFECT_HASH_GROUP_BY ({} slots)", keys.num_slots()),
+                    AggregationStrategy::Hash => "HASH_GROUP_BY".to_string(),
+                };
+                let keys: Vec<String> = group_by
+                    .iter()
+                    .enumerate()
+                    .map(|(i, e)| format!("{} AS {}", e, group_column(i)))
+                    .collect();
+                let aggs: Vec<String> = aggregates
+                    .iter()
+                    .enumerate()
+                    .map(|(i, a)| format!("{} AS {}", aggregate_call(a), aggregate_column(i)))
+                    .collect();
+                let _ = writeln!(
+                    out,
+                    "{}{} group_by=[{}] aggregates=[{}]",
+                    indent,
+                    operator,
+                    keys.join(", "),
+                    aggs.join(", ")
+                );
+                Some(input)
+            }
+            PhysicalPlan::Project { exprs, input } => {
+                let items: Vec<String> = exprs
+                    .iter()
+                    .map(|(e, name)| match e {
+                        Expr::Column(c) if c == name => name.clone(),
+                        e => format!("{} AS {}", e, name),
+                    })
+                    .collect();
+                let _ = writeln!(out, "{}PROJECTION {}", indent, items.join(", "));
+                Some(input)
+            }
+            PhysicalPlan::Sort { keys, input } => {
+                let keys: Vec<String> = keys
+                    .iter()
+                    .map(|k| format!("{} {}", k.name, if k.descending { "DESC" } else { "ASC" }))
+                    .collect();
+                let _ = writeln!(out, "{}ORDER_BY {}", indent, keys.join(", "));
+                Some(input)
+            }
+            PhysicalPlan::Limit { limit, input } => {
+                let _ = writeln!(out, "{}LIMIT {}", indent, limit);
+                Some(input)
+            }
+        };
+        if let Some(input) = input {
+            input.explain_into(out, depth + 1);
+        }
+    }
+
+    /// Schema of the batches this operator produces
+    pub fn schema(&self) -> Result<SchemaRef, GooseError> {
+        Ok(match self {
+            PhysicalPlan::Scan { plan, columns, .. } => {
+                let fields = columns
+                    .iter()
+                    .map(|c| Ok(plan.schema().field_with_name(c)?.clone()))
+                    .collect::<Result<Vec<Field>, GooseError>>()?;
+                Arc::new(Schema::new(fields))
+            }
+            PhysicalPlan::Filter { input, .. } | PhysicalPlan::Sort { input, .. } | PhysicalPlan::Limit { input, .. } => {
+                input.schema()?
+            }
+            PhysicalPlan::Aggregate { group_by, aggregates, input, .. } => {
+                let input = input.schema()?;
+                let mut fields: Vec<Field> = key_fields(group_by, &input)?.iter().map(key_output_field).collect();
+                for (i, a) in aggregates.iter().enumerate() {
+                    let data_type = a.func.data_type(&input_type(a, &input)?);
+                    fields.push(Field::new(aggregate_column(i), data_type, a.func.is_nullable()));
+                }
+                Arc::new(Schema::new(fields))
+            }
+            PhysicalPlan::Project { exprs, input } => project(exprs, &RecordBatch::new_empty(input.schema()?))?.schema(),
+        })
+    }
+
+    /// Run the plan and collect its output into one batch
+    pub fn execute(&self) -> Result<RecordBatch, GooseError> {
+        let batches = self.stream()?.collect::<Result<Vec<_>, _>>()?;
+        Ok(concat_batches(&self.schema()?, &batches)?)
+    }
+
+    /// Run the plan as a stream of batches
+    pub fn stream(&self) -> Result<BatchStream<'_>, GooseError> {
+        Ok(match self {
+            PhysicalPlan::Scan { plan, .. } => Box::new(plan.read()?),
+            PhysicalPlan::Filter { cutoff, predicate, input } => Box::new(input.stream()?.map(move |batch| {
+                let batch = batch?;
+                let mask = filter_mask(*cutoff, predicate.as_ref(), &batch)?;
+                Ok(filter_record_batch(&batch, &mask)?)
+            })),
+            PhysicalPlan::Aggregate { .. } => Box::new(std::iter::once(self.aggregate())),
+            PhysicalPlan::Project { exprs, input } => {
+                Box::new(input.stream()?.map(move |batch| project(exprs, &batch?)))
+            }
+            PhysicalPlan::Sort { keys, input } => {
+                let sorted = (|| {
+                    let batches = input.stream()?.collect::<Result<Vec<_>, _>>()?;
+                    sort(&concat_batches(&input.schema()?, &batches)?, keys)
+                })();
+                Box::new(std::iter::once(sorted))
+            }
+            PhysicalPlan::Limit { limit, input } => {
+                // Stop pulling from the input once the limit is reached
+                let mut input = input.stream()?;
+                let mut remaining = *limit;
+                Box::new(std::iter::from_fn(move || {
+                    if remaining == 0 {
+                        return None;
+                    }
+                    let batch = match input.next()? {
+                        Ok(batch) => batch,
+                        Err(e) => return Some(Err(e)),
+                    };
+                    let n = batch.num_rows().min(remaining);
+                    remaining -= n;
+                    Some(Ok(batch.slice(0, n)))
+                }))
+            }
+        })
+    }
+
+    /// Run an Aggregate node to its single output batch
+    fn aggregate(&self) -> Result<RecordBatch, GooseError> {
+        let PhysicalPlan::Aggregate { strategy, q1_measures, group_by, aggregates, input } = self else {
+            unreachable!("aggregate() called on a non-Aggregate node")
+        };
+
+        if let (AggregationStrategy::PerfectHash(keys), Some(measures)) = (strategy, q1_measures) {
+            let (plan, cutoff) = input.fused_scan().ok_or_else(|| GooseError::Internal("perfect hash aggregation needs a scan input".to_string()))?;
+            let params = Q1Params::with_date(cutoff.unwrap_or(i32::MAX));
+            let aggregator = run_perfect_hash::<f64>(plan, &params, (**keys).clone(), &ExecutionOptions::default(), &mut QueryProfile::default())?;
+            let out = results_to_record_batch(&aggregator.get_results())?;
+
+            let mut columns = vec![out.column(0).clone(), out.column(1).clone()];
+            columns.extend(measures.iter().map(|&m| out.column(m).clone()));
+            let mut fields = vec![
+                Field::new(group_column(0), out.schema().field(0).data_type().clone(), true),
+                Field::new(group_column(1), out.schema().field(1).data_type().clone(), true),
+            ];
+            fields.extend(
+                measures
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &m)| out.schema().field(m).clone().with_name(aggregate_column(i))),
+            );
+            return Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)?);
+        }
+
+        // Aggregate inputs are deduplicated so each is evaluated once per batch
+        let input_schema = input.schema()?;
+        let mut inputs: Vec<&Expr> = Vec::new();
+        let aggregate_exprs = aggregates
+            .iter()
+            .enumerate()
+            .map(|(i, agg)| {
+                let input = agg.arg.as_ref().map(|arg| match inputs.iter().position(|e| *e == arg) {
+                    Some(index) => index,
+                    None => {
+                        inputs.push(arg);
+                        inputs.len() - 1
+                    }
+                });
+                let input_type = input_type(agg, &input_schema)?;
+                Ok(AggregateExpr::new(agg.func, input, &aggregate_column(i)).with_input_type(input_type))
+            })
+            .collect::<Result<Vec<_>, GooseError>>()?;
+        let mut aggregator = HashAggregator::try_new(key_fields(group_by, &input_schema)?, aggregate_exprs)?;
+
+        // Fuse with a Filter input: aggregate under its mask instead of copying
+        let (stream, filter) = match input.as_ref() {
+            PhysicalPlan::Filter { cutoff, predicate, input } => (input.stream()?, Some((*cutoff, predicate.as_ref()))),
+            input => (input.stream()?, None),
+        };
+        for batch in stream {
+            let batch = batch?;
+            if batch.num_rows() == 0 {
+                continue;
+            }
+            let mask = match filter {
+                Some((cutoff, predicate)) => filter_mask(cutoff, predicate, &batch)?,
+                None => BooleanArray::from(vec![true; batch.num_rows()]),
+            };
+            if mask.true_count() == 0 {
+                continue;
+            }
+
+            let keys = group_by
+                .iter()
+                .map(|expr| evaluate(expr, &batch))
+                .collect::<Result<Vec<_>, _>>()?;
+            let values = inputs
+                .iter()
+                .map(|expr| evaluate(expr, &batch))
+                .collect::<Result<Vec<ArrayRef>, GooseError>>()?;
+            let values: Vec<&dyn Array> = values.iter().map(|v| v.as_ref()).collect();
+
+            aggregator.update_batch(&keys, &values, Some(&mask))?;
+        }
+
+        aggregator.finish()
+    }
+
+    /// The scan (and its cutoff) under a cutoff-only Filter, if that is this node
+    fn fused_scan(&self) -> Option<(&LineitemPlan, Option<i32>)> {
+        match self {
+            PhysicalPlan::Scan { plan, cutoff, .. } => Some((plan, *cutoff)),
+            PhysicalPlan::Filter { predicate: None, input, .. } => input.fused_scan(),
+            _ => None,
+        }
+    }
+}
+
+/// `func(arg)` as written in SQL
+fn aggregate_call(aggregate: &BoundAggregate) -> String {
+    let name = match aggregate.func {
+        AggregateFunction::Sum => "sum",
+        AggregateFunction::Avg => "avg",
+        AggregateFunction::Count | AggregateFunction::CountStar | AggregateFunction::CountDistinct => "count",
+        AggregateFunction::Min => "min",
+        AggregateFunction::Max => "max",
+        AggregateFunction::VarSamp => "var_samp",
+        AggregateFunction::VarPop => "var_pop",
+        AggregateFunction::StddevSamp => "stddev_samp",
+        AggregateFunction::StddevPop => "stddev_pop",
+        AggregateFunction::ApproxCountDistinct => "approx_count_distinct",
+        AggregateFunction::Quantile { mode: QuantileMode::Exact, .. } => "quantile_cont",
+        AggregateFunction::Quantile { mode: QuantileMode::Approx, .. } => "approx_quantile",
+    };
+    match (&aggregate.arg, aggregate.func) {
+        (Some(arg), AggregateFunction::CountDistinct) => format!("{}(DISTINCT {})", name, arg),
+        (Some(arg), AggregateFunction::Quantile { fraction, .. }) => format!("{}({}, {})", name, arg, fraction),
+        (Some(arg), _) => format!("{}({})", name, arg),
+        (None, _) => format!("{}(*)", name),
+    }
+}
+
+/// Key fields of an aggregation, typed by evaluating the keys over an empty batch
+fn key_fields(group_by: &[Expr], input: &SchemaRef) -> Result<Vec<Field>, GooseError> {
+    let empty = RecordBatch::new_empty(input.clone());
+    group_by
+        .iter()
+        .enumerate()
+        .map(|(i, expr)| Ok(Field::new(group_column(i), evaluate(expr, &empty)?.data_type().clone(), true)))
+        .collect()
+}
+
+/// Type of an aggregate's argument over the input schema (Null for COUNT(*))
+fn input_type(aggregate: &BoundAggregate, input: &SchemaRef) -> Result<DataType, GooseError> {
+    Ok(match &aggregate.arg {
+        Some(arg) => evaluate(arg, &RecordBatch::new_empty(input.clone()))?.data_type().clone(),
+        None => DataType::Null,
+    })
+}
+
+/// Ship-date cutoff and residual predicate combined into one mask
+fn filter_mask(cutoff: Option<i32>, predicate: Option<&Expr>, batch: &RecordBatch) -> Result<BooleanArray, GooseError> {
+    let cutoff = match cutoff {
+        Some(days) => Some(crate::filter::create_date_filter_mask(batch, &Q1Params::with_date(days))?),
+        None => None,
+    };
+    let residual = match predicate {
+        Some(predicate) => {
+            let mask = evaluate(predicate, batch)?;
+            let mask = mask
+                .as_any()
+                .downcast_ref::<BooleanArray>()
+                .ok_or_else(|| GooseError::type_mismatch("WHERE clause", "Boolean", mask.data_type()))?;
+            Some(mask.clone())
+        }
+        None => None,
+    };
+    Ok(match (cutoff, residual) {
+        (Some(a), Some(b)) => and_kleene(&a, &b)?,
+        (Some(mask), None) | (None, Some(mask)) => mask,
+        (None, None) => BooleanArray::from(vec![true; batch.num_rows()]),
+    })
+}
+
+/// Sort a batch by the given keys
+fn sort(batch: &RecordBatch, keys: &[SortKey]) -> Result<RecordBatch, GooseError> {
+    let sort_columns: Vec<SortColumn> = keys
+        .iter()
+        .map(|key| SortColumn {
+            values: batch.column(key.column).clone(),
+            options: Some(SortOptions {
+                descending: key.descending,
+                nulls_first: key.descending,
+            }),
+        })
+        .collect();
+    let indices = lexsort_to_indices(&sort_columns, None)?;
+    let columns = batch
+        .columns()
+        .iter()
+        .map(|c| take(c.as_ref(), &indices, None))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(RecordBatch::try_new(batch.schema(), columns)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::{plan_sql, Catalog};
+    use crate::test_util::{sample_rows, temp_path, write_lineitem};
+
+    fn physical(sql: &str, path: &str) -> PhysicalPlan {
+        let mut catalog = Catalog::new();
+        catalog.register("lineitem", path);
+        LogicalPlan::from_query(&plan_sql(sql, &catalog).unwrap())
+            .create_physical_plan()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_explain_q1() {
+        let path = temp_path("plan-q1");
+        write_lineitem(&path, &sample_rows(4_000), 500);
+        let path_str = path.to_str().unwrap();
+
+        let plan = physical(
+            "SELECT l_returnflag, l_linestatus, sum(l_quantity) AS sum_qty, count(*) AS count_order \
+             FROM lineitem WHERE l_shipdate <= date '1992-06-01' \
+             GROUP BY l_returnflag, l_linestatus ORDER BY l_returnflag, l_linestatus",
+            path_str,
+        );
+        let explain = plan.explain();
+        let lines: Vec<&str> = explain.lines().collect();
+        assert_eq!(lines[0], "ORDER_BY l_returnflag ASC, l_linestatus ASC");
+        assert_eq!(lines[1], "  PROJECTION __group0 AS l_returnflag, __group1 AS l_linestatus, __agg0 AS sum_qty, __agg1 AS count_order");
+        // The key domain comes from the surviving row group: {A, N, R} x {F}
+        assert!(lines[2].starts_with("    PERFECT_HASH_GROUP_BY (3 slots) group_by=[l_returnflag AS __group0, l_linestatus AS __group1]"));
+        assert!(lines[2].ends_with("aggregates=[sum(l_quantity) AS __agg0, count(*) AS __agg1]"));
+        assert_eq!(lines[3], "      FILTER l_shipdate <= DATE '1992-06-01'");
+        // Ship dates rise through the file, so only the first row group survives
+        assert!(lines[4].starts_with(&format!("        TABLE_SCAN {} columns=[", path_str)));
+        assert!(lines[4].ends_with("row_groups=1/8 (7 pruned by l_shipdate <= 1992-06-01)"));
+
+        let out = plan.execute().unwrap();
+        assert_eq!(out.num_columns(), 4);
+        assert_eq!(out.schema(), plan.schema().unwrap());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_explain_general() {
+        let path = temp_path("plan-general");
+        write_lineitem(&path, &sample_rows(1_000), 500);
+        let path_str = path.to_str().unwrap();
+
+        let plan = physical(
+            "SELECT l_linestatus, avg(l_tax) FROM lineitem WHERE l_quantity > 10 GROUP BY l_linestatus LIMIT 1",
+            path_str,
+        );
+        let explain = plan.explain();
+        let lines: Vec<&str> = explain.lines().collect();
+        assert_eq!(lines[0], "LIMIT 1");
+        assert_eq!(lines[1], "  PROJECTION __group0 AS l_linestatus, __agg0 AS avg(l_tax)");
+        assert_eq!(lines[2], "    HASH_GROUP_BY group_by=[l_linestatus AS __group0] aggregates=[avg(l_tax) AS __agg0]");
+        assert_eq!(lines[3], "      FILTER (l_quantity > 10)");
+        assert!(lines[4].ends_with("columns=[l_quantity, l_tax, l_linestatus] row_groups=2/2 (0 pruned)"));
+
+        assert_eq!(plan.execute().unwrap().num_rows(), 1);
+        std::fs::remove_file(path).unwrap();
+    }
+}
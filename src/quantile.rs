@@ -0,0 +1,335 @@
+//! Quantile state: exact value lists and t-digest sketches
+//!
+//! Quantiles are continuous (`quantile_cont` / `PERCENTILE_CONT`): the
+//! value at position `q * (n - 1)` of the sorted input, interpolated
+//! between its neighbours. Both kinds of `QuantileSketch` merge, so the
+//! state of a group can be split over accumulator sets and threads.
+
+use std::borrow::Cow;
+
+/// Exact or approximate quantiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuantileMode {
+    /// Keep every value: exact, memory grows with the input
+    #[default]
+    Exact,
+    /// `TDigest` sketch: bounded memory, most accurate near the tails
+    Approx,
+}
+
+/// Values seen by one group, for quantile queries
+#[derive(Debug, Clone)]
+pub enum QuantileSketch {
+    Exact(Vec<f64>),
+    Approx(TDigest),
+}
+
+impl QuantileSketch {
+    pub fn new(mode: QuantileMode) -> Self {
+        match mode {
+            QuantileMode::Exact => QuantileSketch::Exact(Vec::new()),
+            QuantileMode::Approx => QuantileSketch::Approx(TDigest::new()),
+        }
+    }
+
+    #[inline]
+    pub fn insert(&mut self, value: f64) {
+        match self {
+            QuantileSketch::Exact(values) => values.push(value),
+            QuantileSketch::Approx(digest) => digest.insert(value),
+        }
+    }
+
+    /// Combine with the values of another, disjoint set of rows
+    ///
+    /// Merging a digest into an exact list turns it into a digest.
+    pub fn merge(&mut self, other: &QuantileSketch) {
+        match (&mut *self, other) {
+            (QuantileSketch::Exact(values), QuantileSketch::Exact(other)) => values.extend_from_slice(other),
+            (QuantileSketch::Approx(digest), QuantileSketch::Approx(other)) => digest.merge(other),
+            (QuantileSketch::Approx(digest), QuantileSketch::Exact(other)) => other.iter().for_each(|&v| digest.insert(v)),
+            (QuantileSketch::Exact(values), QuantileSketch::Approx(other)) => {
+                let mut digest = other.clone();
+                values.iter().for_each(|&v| digest.insert(v));
+                *self = QuantileSketch::Approx(digest);
+            }
+        }
+    }
+
+    /// Number of values inserted
+    pub fn count(&self) -> u64 {
+        match self {
+            QuantileSketch::Exact(values) => values.len() as u64,
+            QuantileSketch::Approx(digest) => digest.count,
+        }
+    }
+
+    /// Sort an exact list in place, so that `quantile` reads it directly
+    ///
+    /// Call it once before asking for several quantiles; a digest is left
+    /// as is.
+    pub fn sort(&mut self) {
+        if let QuantileSketch::Exact(values) = self {
+            values.sort_unstable_by(f64::total_cmp);
+        }
+    }
+
+    /// The `q` quantile (`0.0..=1.0`), NULL over no values
+    ///
+    /// An exact list that has not been `sort`ed since its last insert or
+    /// merge is sorted into a copy for this call.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        match self {
+            QuantileSketch::Exact(values) if values.is_sorted_by(|a, b| a.total_cmp(b).is_le()) => {
+                quantile_of_sorted(values, q)
+            }
+            QuantileSketch::Exact(values) => {
+                let mut sorted = values.clone();
+                sorted.sort_unstable_by(f64::total_cmp);
+                quantile_of_sorted(&sorted, q)
+            }
+            QuantileSketch::Approx(digest) => digest.quantile(q),
+        }
+    }
+}
+
+/// Continuous quantile of sorted values
+pub fn quantile_of_sorted(sorted: &[f64], q: f64) -> Option<f64> {
+    let last = sorted.len().checked_sub(1)?;
+    let position = q.clamp(0.0, 1.0) * last as f64;
+    let (lo, hi) = (position.floor() as usize, position.ceil() as usize);
+    Some(sorted[lo] + (sorted[hi] - sorted[lo]) * (position - lo as f64))
+}
+
+/// Scale parameter δ: a digest keeps at most about δ centroids
+const COMPRESSION: f64 = 200.0;
+
+/// Values buffered before they are folded into the centroids
+const BUFFER_SIZE: usize = 1_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+/// Merging t-digest (Dunning & Ertl) with the arcsine scale function
+///
+/// Centroids near the median may absorb many values, centroids near
+/// either end only a few, so the relative rank error shrinks toward the
+/// tails where p95 / p99 live. Deterministic: the same inserts and merges
+/// always give the same digest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TDigest {
+    centroids: Vec<Centroid>,
+    /// Values not yet folded into `centroids`
+    buffer: Vec<f64>,
+    count: u64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for TDigest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TDigest {
+    pub fn new() -> Self {
+        Self { centroids: Vec::new(), buffer: Vec::new(), count: 0, min: f64::INFINITY, max: f64::NEG_INFINITY }
+    }
+
+    #[inline]
+    pub fn insert(&mut self, value: f64) {
+        self.buffer.push(value);
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        if self.buffer.len() >= BUFFER_SIZE {
+            self.centroids = self.compressed().into_owned();
+            self.buffer.clear();
+        }
+    }
+
+    pub fn merge(&mut self, other: &TDigest) {
+        if other.count == 0 {
+            return;
+        }
+        self.centroids = compress(self.pending().chain(other.pending()).collect());
+        self.buffer.clear();
+        self.count += other.count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    /// Centroids plus buffered values, unsorted
+    fn pending(&self) -> impl Iterator<Item = Centroid> + '_ {
+        let buffered = self.buffer.iter().map(|&mean| Centroid { mean, weight: 1.0 });
+        self.centroids.iter().copied().chain(buffered)
+    }
+
+    /// The centroids with the buffer folded in
+    fn compressed(&self) -> Cow<'_, [Centroid]> {
+        match self.buffer.is_empty() {
+            true => Cow::Borrowed(&self.centroids),
+            false => Cow::Owned(compress(self.pending().collect())),
+        }
+    }
+
+    /// Estimated `q` quantile, NULL over no values
+    ///
+    /// Value `i` of the sorted input sits at rank `i + 0.5`, and a centroid
+    /// at the middle of the ranks it covers. A rank between two centroid
+    /// middles interpolates their means; ranks before the first (after the
+    /// last) middle interpolate from the exact minimum (maximum).
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let centroids = self.compressed();
+        let total = self.count as f64;
+        // Same positions as the exact mode: q = 0 is the minimum, q = 1 the maximum
+        let rank = q.clamp(0.0, 1.0) * (total - 1.0) + 0.5;
+
+        let first = centroids[0];
+        if rank < first.weight / 2.0 {
+            let t = (rank - 0.5) / (first.weight / 2.0 - 0.5);
+            return Some(self.min + (first.mean - self.min) * t);
+        }
+        let mut cumulative = first.weight / 2.0;
+        for pair in centroids.windows(2) {
+            let step = (pair[0].weight + pair[1].weight) / 2.0;
+            if rank < cumulative + step {
+                let t = (rank - cumulative) / step;
+                return Some(pair[0].mean + (pair[1].mean - pair[0].mean) * t);
+            }
+            cumulative += step;
+        }
+        let last = centroids[centroids.len() - 1];
+        let span = total - 0.5 - cumulative;
+        match span > 0.0 {
+            true => Some(last.mean + (self.max - last.mean) * ((rank - cumulative) / span).min(1.0)),
+            false => Some(last.mean),
+        }
+    }
+}
+
+/// Arcsine scale function k₁: centroids may span at most 1 in k
+#[inline]
+fn scale(q: f64) -> f64 {
+    COMPRESSION / (2.0 * std::f64::consts::PI) * (2.0 * q - 1.0).clamp(-1.0, 1.0).asin()
+}
+
+/// Sort centroids and merge neighbours while the merged one spans at most
+/// one unit of `scale`
+fn compress(mut centroids: Vec<Centroid>) -> Vec<Centroid> {
+    centroids.sort_unstable_by(|a, b| a.mean.total_cmp(&b.mean));
+    let total: f64 = centroids.iter().map(|c| c.weight).sum();
+    let mut out: Vec<Centroid> = Vec::with_capacity(COMPRESSION as usize);
+    let mut iter = centroids.into_iter();
+    let Some(mut current) = iter.next() else { return out };
+    // Weight of the centroids before `current`
+    let mut before = 0.0;
+    for next in iter {
+        let merged = current.weight + next.weight;
+        if scale((before + merged) / total) - scale(before / total) <= 1.0 {
+            current.mean += (next.mean - current.mean) * next.weight / merged;
+            current.weight = merged;
+        } else {
+            before += current.weight;
+            out.push(current);
+            current = next;
+        }
+    }
+    out.push(current);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic values with a long right tail, like prices
+    fn values(n: usize, seed: u64) -> Vec<f64> {
+        let mut state = seed;
+        (0..n)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                let u = (state >> 11) as f64 / (1u64 << 53) as f64;
+                900.0 + 1e5 * u * u
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_exact() {
+        let mut sketch = QuantileSketch::new(QuantileMode::Exact);
+        [4.0, 1.0, 3.0, 2.0].iter().for_each(|&v| sketch.insert(v));
+        assert_eq!(sketch.quantile(0.5), Some(2.5));
+        assert_eq!((sketch.quantile(0.0), sketch.quantile(1.0)), (Some(1.0), Some(4.0)));
+        assert_eq!(sketch.quantile(1.0 / 3.0), Some(2.0));
+
+        let mut other = QuantileSketch::new(QuantileMode::Exact);
+        other.insert(5.0);
+        sketch.merge(&other);
+        assert_eq!((sketch.count(), sketch.quantile(0.5)), (5, Some(3.0)));
+        assert_eq!(QuantileSketch::new(QuantileMode::Exact).quantile(0.5), None);
+
+        // Sorted once in place, the list answers without a copy
+        sketch.sort();
+        assert!(matches!(&sketch, QuantileSketch::Exact(v) if v == &[1.0, 2.0, 3.0, 4.0, 5.0]));
+        assert_eq!((sketch.quantile(0.25), sketch.quantile(0.5)), (Some(2.0), Some(3.0)));
+    }
+
+    #[test]
+    fn test_tdigest_against_sorted() {
+        let data = values(100_000, 0x2545_F491_4F6C_DD1D);
+        let mut sorted = data.clone();
+        sorted.sort_unstable_by(f64::total_cmp);
+        let rank_of = |v: f64| sorted.partition_point(|&x| x < v) as f64 / sorted.len() as f64;
+
+        // One digest, and four partial digests merged (like the ILP sets)
+        let mut one = TDigest::new();
+        data.iter().for_each(|&v| one.insert(v));
+        let mut parts: Vec<TDigest> = (0..4).map(|_| TDigest::new()).collect();
+        data.iter().enumerate().for_each(|(i, &v)| parts[i % 4].insert(v));
+        let mut merged = parts[0].clone();
+        parts[1..].iter().for_each(|p| merged.merge(p));
+        assert!(merged.centroids.len() <= COMPRESSION as usize);
+
+        for digest in [&one, &merged] {
+            assert_eq!(digest.count, 100_000);
+            for q in [0.01, 0.25, 0.5, 0.95, 0.99, 0.999] {
+                let estimate = digest.quantile(q).unwrap();
+                // Within a tenth of a percentile of the true rank
+                assert!((rank_of(estimate) - q).abs() < 0.001, "q {} estimate {} rank {}", q, estimate, rank_of(estimate));
+            }
+            assert_eq!((digest.quantile(0.0), digest.quantile(1.0)), (sorted.first().copied(), sorted.last().copied()));
+        }
+    }
+
+    #[test]
+    fn test_small_digest_is_exact() {
+        // Fewer values than the buffer: each is its own centroid
+        let data = values(101, 7);
+        let mut digest = QuantileSketch::new(QuantileMode::Approx);
+        let mut exact = QuantileSketch::new(QuantileMode::Exact);
+        data.iter().for_each(|&v| {
+            digest.insert(v);
+            exact.insert(v);
+        });
+        for q in [0.0, 0.1, 0.5, 0.99, 1.0] {
+            let (d, e) = (digest.quantile(q).unwrap(), exact.quantile(q).unwrap());
+            assert!((d - e).abs() <= 1e-9 * e.abs(), "q {}: {} vs {}", q, d, e);
+        }
+
+        // Merging an exact list into a digest, either way round
+        let mut into_exact = exact.clone();
+        into_exact.merge(&digest);
+        assert!(matches!(into_exact, QuantileSketch::Approx(_)));
+        assert_eq!(into_exact.count(), 202);
+    }
+}
@@ -1,78 +1,998 @@
 //! Query orchestration - ties together all components
 
-use crate::aggregator::{Aggregator, QueryResult};
+use crate::aggregator::{record_batch_to_results, results_to_record_batch, AggregationMode, Aggregator, PerfectHash, QueryResult};
+use crate::decimal_aggregator::{DecimalAggregator, DecimalScales};
+use crate::distinct::DistinctMode;
+use crate::hash_aggregator::{AggregateExpr, AggregateFunction, HashAggregator};
+use crate::params::Q1Params;
+use crate::quantile::QuantileMode;
+use crate::summation::{FloatSum, KahanSum, PairwiseSum, Summation};
+use crate::integer_sum::{choose_width, Checked, IntegerSum, MeasureBounds, SumWidth};
+use crate::profile::{timed, QueryProfile};
 
-use crate::reader::read_lineitem;
-use arrow::array::Array;
+use crate::reader::{plan_scan, FilterStrategy, LineitemPlan, LineitemReader, REQUIRED_COLUMNS};
+use crate::error::GooseError;
+use arrow::array::{ArrayRef, BooleanArray, Float64Array, RecordBatch};
+use arrow::buffer::BooleanBuffer;
+use arrow::compute::binary;
+use arrow::datatypes::{DataType, Field};
+use std::time::Instant;
+
+/// How a GROUP BY is executed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AggregationStrategy {
+    /// Slot array over a key domain discovered from file metadata
+    PerfectHash(Box<PerfectHash>),
+    /// General `HashAggregator` for any key columns
+    Hash,
+}
+
+/// Pick the aggregation strategy for a set of group-by columns
+///
+/// The perfect hash array is only used for Q1's `(l_returnflag, l_linestatus)`
+/// and only when the reader can prove the key domain from dictionary pages or
+/// statistics, every value is a single byte, and the domain fits in
+/// `MAX_PERFECT_HASH_SLOTS`. Anything else falls back to the hash table.
+pub fn choose_strategy(plan: &LineitemPlan, group_by: &[&str]) -> Result<AggregationStrategy, GooseError> {
+    if group_by != Q1_GROUP_BY {
+        return Ok(AggregationStrategy::Hash);
+    }
+
+    let (Some(flags), Some(statuses)) = (plan.column_domain("l_returnflag")?, plan.column_domain("l_linestatus")?) else {
+        return Ok(AggregationStrategy::Hash);
+    };
+
+    Ok(match PerfectHash::from_domains(&flags, &statuses) {
+        Some(keys) => AggregationStrategy::PerfectHash(Box::new(keys)),
+        None => AggregationStrategy::Hash,
+    })
+}
+
+/// Execution knobs that do not change the query's meaning
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecutionOptions {
+    /// Worker threads: 1 runs on the calling thread, 0 uses one per core
+    pub num_threads: usize,
+    /// Floating-point or exact decimal accumulation
+    pub mode: AggregationMode,
+    /// Mask batches after decoding, or push the ship-date predicate into
+    /// the Parquet reader
+    pub filter: FilterStrategy,
+    /// Also collect MIN / MAX / variance state of the input measures into
+    /// `QueryResult::moments` (floating-point perfect-hash path only)
+    pub moments: bool,
+    /// Also count distinct `l_orderkey` values per group into
+    /// `QueryResult::distinct` (floating-point perfect-hash path only)
+    pub distinct_orders: Option<DistinctMode>,
+    /// Also keep `l_extendedprice` quantile state per group in
+    /// `QueryResult::price_quantiles` (floating-point perfect-hash path only)
+    pub price_quantiles: Option<QuantileMode>,
+    /// How the floating-point sums are accumulated, by the perfect-hash and
+    /// hash aggregators alike (floating-point mode only)
+    pub summation: Summation,
+}
+
+impl Default for ExecutionOptions {
+    fn default() -> Self {
+        Self {
+            num_threads: 1,
+            mode: AggregationMode::Float,
+            filter: FilterStrategy::Mask,
+            moments: false,
+            distinct_orders: None,
+            price_quantiles: None,
+            summation: Summation::Plain,
+        }
+    }
+}
+
+impl ExecutionOptions {
+    /// Whether any option needs per-group state beyond the Q1 sums
+    fn needs_extras(&self) -> bool {
+        self.moments || self.distinct_orders.is_some() || self.price_quantiles.is_some()
+    }
+}
+
+/// Q1's GROUP BY columns
+pub(crate) const Q1_GROUP_BY: &[&str] = &["l_returnflag", "l_linestatus"];
+
+/// Measure columns read by the Q1 aggregates
+pub(crate) const Q1_MEASURE_COLUMNS: &[&str] = &["l_quantity", "l_extendedprice", "l_discount", "l_tax", "l_shipdate"];
+
+/// Q1's aggregates over the values [qty, price, disc_price, charge, discount]
+fn q1_aggregates() -> Vec<AggregateExpr> {
+    vec![
+        AggregateExpr::new(AggregateFunction::Sum, Some(0), "sum_qty"),
+        AggregateExpr::new(AggregateFunction::Sum, Some(1), "sum_base_price"),
+        AggregateExpr::new(AggregateFunction::Sum, Some(2), "sum_disc_price"),
+        AggregateExpr::new(AggregateFunction::Sum, Some(3), "sum_charge"),
+        AggregateExpr::new(AggregateFunction::Avg, Some(0), "avg_qty"),
+        AggregateExpr::new(AggregateFunction::Avg, Some(1), "avg_price"),
+        AggregateExpr::new(AggregateFunction::Avg, Some(4), "avg_disc"),
+        AggregateExpr::new(AggregateFunction::CountStar, None, "count_order"),
+    ]
+}
 
 /// Execute TPC-H Query 1
-/// 
-/// Returns the query results sorted by (l_returnflag, l_linestatus)
-pub fn execute_tpch_q1(data_path: &str) -> Result<Vec<QueryResult>, Box<dyn std::error::Error>> {
-    // Initialize aggregator with perfect hash array
-    let mut aggregator = Aggregator::new();
-    
-    // Read parquet file with column projection (no caching)
-    let reader = read_lineitem(data_path)?;
-    
+///
+/// Returns the query results sorted by (l_returnflag, l_linestatus).
+/// `params` selects the ship-date cutoff; `Q1Params::default()` is the
+/// validation query (DELTA = 90).
+pub fn execute_tpch_q1(data_path: &str, params: &Q1Params) -> Result<Vec<QueryResult>, GooseError> {
     // Process batches sequentially
-    for batch_result in reader {
+    execute_tpch_q1_with_options(data_path, params, &ExecutionOptions::default())
+}
+
+/// Execute TPC-H Query 1 across a pool of worker threads
+///
+/// The row groups that survive pruning are dealt round-robin to
+/// `num_threads` workers (0 = one per available core). Each worker scans its
+/// share into a private aggregator; the partial states are merged at the
+/// end. Groups and counts are identical to `execute_tpch_q1`; float sums may
+/// differ in the last bits because the addition order changes.
+pub fn execute_tpch_q1_parallel(
+    data_path: &str,
+    params: &Q1Params,
+    num_threads: usize,
+) -> Result<Vec<QueryResult>, GooseError> {
+    let options = ExecutionOptions {
+        num_threads,
+        ..ExecutionOptions::default()
+    };
+    execute_tpch_q1_with_options(data_path, params, &options)
+}
+
+/// Execute TPC-H Query 1 with explicit execution options
+///
+/// With `AggregationMode::ExactDecimal` every `QueryResult` carries exact
+/// `Decimal` sums in `exact`; this mode needs the perfect-hash path, so it
+/// fails on data whose key domain cannot be proven.
+pub fn execute_tpch_q1_with_options(
+    data_path: &str,
+    params: &Q1Params,
+    options: &ExecutionOptions,
+) -> Result<Vec<QueryResult>, GooseError> {
+    Ok(execute_tpch_q1_profiled(data_path, params, options)?.0)
+}
+
+/// Execute TPC-H Query 1 and report where the time went
+///
+/// Returns the same results as `execute_tpch_q1_with_options` together with
+/// per-phase timings, row counts and row group pruning statistics.
+pub fn execute_tpch_q1_profiled(
+    data_path: &str,
+    params: &Q1Params,
+    options: &ExecutionOptions,
+) -> Result<(Vec<QueryResult>, QueryProfile), GooseError> {
+    let start = Instant::now();
+    let mut profile = QueryProfile::default();
+
+    // Read parquet file with column projection (no caching)
+    let mut columns = REQUIRED_COLUMNS.to_vec();
+    if options.distinct_orders.is_some() {
+        columns.push("l_orderkey");
+    }
+    let plan = timed(&mut profile.plan, || plan_scan(data_path, params, &columns))?.with_filter(options.filter);
+    profile.row_groups_total = plan.num_row_groups();
+    profile.row_groups_skipped = plan.num_row_groups() - plan.row_groups().len();
+
+    let results = run_q1(&plan, params, options, &mut profile)?;
+    profile.rows_out = results.len() as u64;
+    profile.total = start.elapsed();
+    Ok((results, profile))
+}
+
+/// Physical plan of Q1 for a file, as printed by `PhysicalPlan::explain`
+///
+/// Shows the projected columns, the row groups left after pruning and
+/// whether the perfect hash or the general hash aggregation was chosen.
+pub fn explain_tpch_q1(data_path: &str, params: &Q1Params) -> Result<String, GooseError> {
+    let mut catalog = crate::sql::Catalog::new();
+    catalog.register("lineitem", data_path);
+    crate::sql::explain_sql(&crate::sql::tpch_q1_sql(params), &catalog)
+}
+
+/// Execute Q1's aggregates grouped by arbitrary columns
+///
+/// Returns one row per group ordered by the group-by columns, with the key
+/// columns first followed by the Q1 aggregate columns. Grouping by
+/// `(l_returnflag, l_linestatus)` takes the perfect-hash fast path when the
+/// key domain allows it; any other key set uses the general `HashAggregator`.
+pub fn execute_q1_group_by(
+    data_path: &str,
+    params: &Q1Params,
+    group_by: &[&str],
+) -> Result<RecordBatch, GooseError> {
+    let mut columns: Vec<&str> = group_by.to_vec();
+    columns.extend(Q1_MEASURE_COLUMNS.iter().filter(|c| !group_by.contains(c)));
+    let plan = plan_scan(data_path, params, &columns)?;
+
+    match choose_strategy(&plan, group_by)? {
+        AggregationStrategy::PerfectHash(keys) => {
+            results_to_record_batch(&run_perfect_hash::<f64>(&plan, params, *keys, &ExecutionOptions::default(), &mut QueryProfile::default())?.get_results())
+        }
+        AggregationStrategy::Hash => {
+            run_hash(&plan, params, group_by, 1, Summation::Plain, &mut QueryProfile::default())?.finish()
+        }
+    }
+}
+
+/// Run Q1 over a planned scan with whichever strategy the key domain allows
+fn run_q1(
+    plan: &LineitemPlan,
+    params: &Q1Params,
+    options: &ExecutionOptions,
+    profile: &mut QueryProfile,
+) -> Result<Vec<QueryResult>, GooseError> {
+    let num_threads = options.num_threads;
+    let strategy = timed(&mut profile.plan, || choose_strategy(plan, Q1_GROUP_BY))?;
+    match (strategy, options.mode) {
+        (AggregationStrategy::PerfectHash(keys), AggregationMode::Float) => match options.summation {
+            Summation::Plain => run_float::<f64>(plan, params, *keys, options, profile),
+            Summation::Kahan => run_float::<KahanSum>(plan, params, *keys, options, profile),
+            Summation::Pairwise => run_float::<PairwiseSum>(plan, params, *keys, options, profile),
+        },
+        _ if options.needs_extras() => Err(GooseError::Unsupported(
+            "moments, distinct counts and quantiles need floating-point perfect-hash aggregation".to_string(),
+        )),
+        (_, AggregationMode::ExactDecimal) if options.summation != Summation::Plain => {
+            Err(GooseError::Unsupported("summation strategies apply to floating-point sums only".to_string()))
+        }
+        (AggregationStrategy::PerfectHash(keys), AggregationMode::ExactDecimal) => {
+            let width = timed(&mut profile.plan, || exact_sum_width(plan));
+            profile.sum_width = Some(width);
+            match width {
+                SumWidth::I64 => run_decimal::<i64>(plan, params, *keys, num_threads, profile),
+                SumWidth::I128 => run_decimal::<i128>(plan, params, *keys, num_threads, profile),
+                SumWidth::Checked => run_decimal::<Checked>(plan, params, *keys, num_threads, profile),
+            }
+        }
+        (AggregationStrategy::Hash, AggregationMode::Float) => {
+            let aggregator = run_hash(plan, params, Q1_GROUP_BY, num_threads, options.summation, profile)?;
+            timed(&mut profile.sort, || record_batch_to_results(&aggregator.finish()?))
+        }
+        // Nothing survived pruning, so there is no key domain to prove
+        (AggregationStrategy::Hash, AggregationMode::ExactDecimal) if plan.row_groups().is_empty() => Ok(Vec::new()),
+        (AggregationStrategy::Hash, AggregationMode::ExactDecimal) => {
+            Err(GooseError::Unsupported("exact decimal mode needs a provable perfect-hash key domain".to_string()))
+        }
+    }
+}
+
+/// Aggregate a scan into perfect hash arrays with `S` sums
+fn run_float<S: FloatSum>(
+    plan: &LineitemPlan,
+    params: &Q1Params,
+    keys: PerfectHash,
+    options: &ExecutionOptions,
+    profile: &mut QueryProfile,
+) -> Result<Vec<QueryResult>, GooseError> {
+    let aggregator = run_perfect_hash::<S>(plan, params, keys, options, profile)?;
+    Ok(timed(&mut profile.sort, || aggregator.get_results()))
+}
+
+/// Aggregate a scan into perfect hash arrays over `keys`, summing in `S`
+pub(crate) fn run_perfect_hash<S: FloatSum>(
+    plan: &LineitemPlan,
+    params: &Q1Params,
+    keys: PerfectHash,
+    options: &ExecutionOptions,
+    profile: &mut QueryProfile,
+) -> Result<Aggregator<S>, GooseError> {
+    let new_aggregator = || {
+        let mut aggregator = Aggregator::<S>::with_accumulator(keys.clone());
+        if options.moments {
+            aggregator = aggregator.with_moments();
+        }
+        if let Some(mode) = options.distinct_orders {
+            aggregator = aggregator.with_distinct(mode);
+        }
+        if let Some(mode) = options.price_quantiles {
+            aggregator = aggregator.with_quantiles(mode);
+        }
+        Ok(aggregator)
+    };
+    scan_partitioned(
+        plan,
+        options.num_threads,
+        profile,
+        new_aggregator,
+        |aggregator, reader, profile| aggregate_reader(aggregator, reader, params, profile),
+        |aggregator, partial| {
+            aggregator.merge(&partial);
+            Ok(())
+        },
+    )
+}
+
+/// Aggregate a scan into perfect hash arrays with exact decimal sums
+fn run_decimal<S: IntegerSum>(
+    plan: &LineitemPlan,
+    params: &Q1Params,
+    keys: PerfectHash,
+    num_threads: usize,
+    profile: &mut QueryProfile,
+) -> Result<Vec<QueryResult>, GooseError> {
+    let aggregator = scan_partitioned(
+        plan,
+        num_threads,
+        profile,
+        || Ok(DecimalAggregator::<S>::with_accumulator(keys.clone())),
+        |aggregator, reader, profile| decimal_aggregate_reader(aggregator, reader, params, profile),
+        |aggregator, partial| aggregator.merge(&partial),
+    )?;
+    Ok(timed(&mut profile.sort, || aggregator.get_results()))
+}
+
+/// Narrowest accumulator the footer proves safe for Q1's exact sums
+///
+/// Needs row counts and min / max statistics of the four measures in every
+/// surviving row group; anything unproven gets `SumWidth::Checked`.
+pub fn exact_sum_width(plan: &LineitemPlan) -> SumWidth {
+    let scale = |column: &str| match plan.schema().field_with_name(column).map(Field::data_type) {
+        Ok(DataType::Decimal128(_, scale)) => Some(*scale),
+        Ok(dt) if dt.is_signed_integer() => Some(0),
+        _ => None,
+    };
+    let bounds = |column: &str| scale(column).and(plan.column_bounds(column));
+    let (Some(quantity), Some(price), Some(discount), Some(tax)) =
+        (scale("l_quantity"), scale("l_extendedprice"), scale("l_discount"), scale("l_tax"))
+    else {
+        return SumWidth::Checked;
+    };
+    let measures = (bounds("l_quantity"), bounds("l_extendedprice"), bounds("l_discount"), bounds("l_tax"));
+    let bounds = match measures {
+        (Some(quantity), Some(price), Some(discount), Some(tax)) => Some(MeasureBounds { quantity, price, discount, tax }),
+        _ => None,
+    };
+    choose_width(plan.num_rows(), bounds, DecimalScales { quantity, price, discount, tax })
+}
+
+/// Aggregate a scan through the general `HashAggregator`
+fn run_hash(
+    plan: &LineitemPlan,
+    params: &Q1Params,
+    group_by: &[&str],
+    num_threads: usize,
+    summation: Summation,
+    profile: &mut QueryProfile,
+) -> Result<HashAggregator, GooseError> {
+    let key_fields = group_by
+        .iter()
+        .map(|name| Ok(plan.schema().field_with_name(name)?.clone()))
+        .collect::<Result<Vec<Field>, GooseError>>()?;
+
+    scan_partitioned(
+        plan,
+        num_threads,
+        profile,
+        || Ok(HashAggregator::try_new(key_fields.clone(), q1_aggregates())?.with_summation(summation)),
+        |aggregator, reader, profile| hash_aggregate_reader(aggregator, reader, params, group_by, profile),
+        |aggregator, partial| aggregator.merge(&partial),
+    )
+}
+
+/// Scan the plan's row groups on up to `num_threads` workers and merge the results
+///
+/// `num_threads == 0` means one worker per available core. With a single
+/// worker everything runs on the calling thread. Each worker profiles into
+/// its own `QueryProfile`; they are summed into `profile`, and the time spent
+/// merging partial states counts as `sort`.
+fn scan_partitioned<A: Send>(
+    plan: &LineitemPlan,
+    num_threads: usize,
+    profile: &mut QueryProfile,
+    init: impl Fn() -> Result<A, GooseError> + Sync,
+    scan: impl Fn(&mut A, LineitemReader, &mut QueryProfile) -> Result<(), GooseError> + Sync,
+    merge: impl Fn(&mut A, A) -> Result<(), GooseError>,
+) -> Result<A, GooseError> {
+    let num_threads = match num_threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+    let num_workers = num_threads.min(plan.row_groups().len());
+    let mut aggregator = init()?;
+    if num_workers <= 1 {
+        scan(&mut aggregator, plan.read()?, profile)?;
+        return Ok(aggregator);
+    }
+
+    // Round-robin keeps the share of partially-filtered (late) row groups even
+    let mut assignments = vec![Vec::new(); num_workers];
+    for (i, &rg) in plan.row_groups().iter().enumerate() {
+        assignments[i % num_workers].push(rg);
+    }
+
+    let partials: Vec<Result<(A, QueryProfile), GooseError>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = assignments
+            .into_iter()
+            .map(|row_groups| {
+                let (init, scan) = (&init, &scan);
+                scope.spawn(move || {
+                    let mut partial = init()?;
+                    let mut partial_profile = QueryProfile::default();
+                    let reader = plan.read_row_groups(row_groups)?;
+                    scan(&mut partial, reader, &mut partial_profile)?;
+                    Ok((partial, partial_profile))
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap_or_else(|_| Err(GooseError::Internal("worker thread panicked".to_string()))))
+            .collect()
+    });
+
+    for partial in partials {
+        let (partial, partial_profile) = partial?;
+        profile.merge(&partial_profile);
+        timed(&mut profile.sort, || merge(&mut aggregator, partial))?;
+    }
+
+    Ok(aggregator)
+}
+
+/// Drain a reader into the exact decimal aggregator
+fn decimal_aggregate_reader<S: IntegerSum>(
+    aggregator: &mut DecimalAggregator<S>,
+    mut reader: LineitemReader,
+    params: &Q1Params,
+    profile: &mut QueryProfile,
+) -> Result<(), GooseError> {
+    while let Some(batch_result) = next_batch(&mut reader, profile) {
         let batch = batch_result?;
-        
-        // Skip empty batches
         if batch.num_rows() == 0 {
             continue;
         }
-        
-        // Create filter mask: l_shipdate <= '1998-09-02'
-        let mask = crate::filter::create_date_filter_mask(&batch)?;
-        
-        // Skip if everything filtered out (optimization)
+        profile.rows_in += batch.num_rows() as u64;
+
+        let mask = date_mask(&batch, reader.all_pass(), params, profile)?;
         if mask.true_count() == 0 {
             continue;
         }
+        profile.rows_selected += mask.true_count() as u64;
+
+        let returnflag = crate::utils::get_key_column(&batch, "l_returnflag")?;
+        let linestatus = crate::utils::get_key_column(&batch, "l_linestatus")?;
+
+        // No f64 cast: the raw i128 values go straight into the fused loop
+        let (quantity, price, discount, tax) = timed(&mut profile.decimal_cast, || {
+            Ok::<_, GooseError>((
+                crate::utils::get_decimal_column(&batch, "l_quantity")?,
+                crate::utils::get_decimal_column(&batch, "l_extendedprice")?,
+                crate::utils::get_decimal_column(&batch, "l_discount")?,
+                crate::utils::get_decimal_column(&batch, "l_tax")?,
+            ))
+        })?;
 
-        // Get typed arrays from ORIGINAL batch (no copy)
-        // Since we are filtering inside the loop, we work with the full batch arrays
-        let returnflag = batch
-            .column(batch.schema().index_of("l_returnflag")?)
-            .as_any()
-            .downcast_ref::<arrow::array::StringArray>()
-            .ok_or("l_returnflag is not String")?;
-            
-        let linestatus = batch
-            .column(batch.schema().index_of("l_linestatus")?)
-            .as_any()
-            .downcast_ref::<arrow::array::StringArray>()
-            .ok_or("l_linestatus is not String")?;
-        
-        let quantity = crate::utils::get_f64_column(&batch, "l_quantity")?;
-        let price = crate::utils::get_f64_column(&batch, "l_extendedprice")?;
-        let discount = crate::utils::get_f64_column(&batch, "l_discount")?;
-        let tax = crate::utils::get_f64_column(&batch, "l_tax")?;
-        
-        // Aggregate into perfect hash array using the mask
-        // Expressions (disc_price, charge) are computed on the fly inside aggregate_batch
-        aggregator.aggregate_batch(
-            &mask,
-            returnflag,
-            linestatus,
-            &quantity,
-            &price,
-            &discount,
-            &tax,
-        )?;
-    }
-    
-    // Get sorted results
-    let results = aggregator.get_results();
-    
-    Ok(results)
+        timed(&mut profile.aggregation, || {
+            aggregator.aggregate_batch(&mask, returnflag.as_ref(), linestatus.as_ref(), &quantity, &price, &discount, &tax)
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Drain a reader into the general hash aggregator
+fn hash_aggregate_reader(
+    aggregator: &mut HashAggregator,
+    mut reader: LineitemReader,
+    params: &Q1Params,
+    group_by: &[&str],
+    profile: &mut QueryProfile,
+) -> Result<(), GooseError> {
+    while let Some(batch_result) = next_batch(&mut reader, profile) {
+        let batch = batch_result?;
+        if batch.num_rows() == 0 {
+            continue;
+        }
+        profile.rows_in += batch.num_rows() as u64;
+
+        let mask = date_mask(&batch, reader.all_pass(), params, profile)?;
+        if mask.true_count() == 0 {
+            continue;
+        }
+        profile.rows_selected += mask.true_count() as u64;
+
+        let keys = group_by
+            .iter()
+            .map(|name| Ok(crate::utils::get_column(&batch, name)?.clone()))
+            .collect::<Result<Vec<ArrayRef>, GooseError>>()?;
+
+        let (quantity, price, discount, tax) = timed(&mut profile.decimal_cast, || f64_measures(&batch))?;
+
+        timed(&mut profile.aggregation, || {
+            // NULL in, NULL out
+            let disc_price: Float64Array = binary(&price, &discount, |p, d| p * (1.0 - d))?;
+            let charge: Float64Array = binary(&disc_price, &tax, |dp, t| dp * (1.0 + t))?;
+
+            aggregator.update_batch(&keys, &[&quantity, &price, &disc_price, &charge, &discount], Some(&mask))
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Next batch from a reader, timed as Parquet decode
+#[inline]
+fn next_batch(reader: &mut LineitemReader, profile: &mut QueryProfile) -> Option<Result<RecordBatch, GooseError>> {
+    timed(&mut profile.decode, || reader.next())
+}
+
+/// The four Q1 measure columns cast to f64: (quantity, price, discount, tax)
+fn f64_measures(batch: &RecordBatch) -> Result<(Float64Array, Float64Array, Float64Array, Float64Array), GooseError> {
+    Ok((
+        crate::utils::get_f64_column(batch, "l_quantity")?,
+        crate::utils::get_f64_column(batch, "l_extendedprice")?,
+        crate::utils::get_f64_column(batch, "l_discount")?,
+        crate::utils::get_f64_column(batch, "l_tax")?,
+    ))
+}
+
+/// Drain a reader into the aggregator
+fn aggregate_reader<S: FloatSum>(
+    aggregator: &mut Aggregator<S>,
+    mut reader: LineitemReader,
+    params: &Q1Params,
+    profile: &mut QueryProfile,
+) -> Result<(), GooseError> {
+    while let Some(batch_result) = next_batch(&mut reader, profile) {
+        let batch = batch_result?;
+        aggregate_record_batch(aggregator, &batch, reader.all_pass(), params, profile)?;
+    }
+    Ok(())
+}
+
+/// Ship-date mask of a batch, skipping the filter kernel when the page
+/// index proved that every row qualifies
+fn date_mask(batch: &RecordBatch, all_pass: bool, params: &Q1Params, profile: &mut QueryProfile) -> Result<BooleanArray, GooseError> {
+    timed(&mut profile.mask, || {
+        if all_pass {
+            Ok(BooleanArray::new(BooleanBuffer::new_set(batch.num_rows()), None))
+        } else {
+            crate::filter::create_date_filter_mask(batch, params)
+        }
+    })
+}
+
+/// Filter and aggregate a single batch
+fn aggregate_record_batch<S: FloatSum>(
+    aggregator: &mut Aggregator<S>,
+    batch: &RecordBatch,
+    all_pass: bool,
+    params: &Q1Params,
+    profile: &mut QueryProfile,
+) -> Result<(), GooseError> {
+    // Skip empty batches
+    if batch.num_rows() == 0 {
+        return Ok(());
+    }
+    profile.rows_in += batch.num_rows() as u64;
+
+    // Create filter mask: l_shipdate <= cutoff, unless the reader proved
+    // that every row qualifies
+    let mask = if all_pass { None } else { Some(timed(&mut profile.mask, || crate::filter::create_date_filter_mask(batch, params))?) };
+    let selected = mask.as_ref().map_or(batch.num_rows(), |m| m.true_count());
+
+    // Skip if everything filtered out (optimization)
+    if selected == 0 {
+        return Ok(());
+    }
+    profile.rows_selected += selected as u64;
+
+    // Get typed arrays from ORIGINAL batch (no copy)
+    // Since we are filtering inside the loop, we work with the full batch arrays
+    let returnflag = crate::utils::get_key_column(batch, "l_returnflag")?;
+    let linestatus = crate::utils::get_key_column(batch, "l_linestatus")?;
+
+    let (quantity, price, discount, tax) = timed(&mut profile.decimal_cast, || f64_measures(batch))?;
+
+    // Aggregate into perfect hash array using the mask
+    // Expressions (disc_price, charge) are computed on the fly inside aggregate_batch
+    timed(&mut profile.aggregation, || match &mask {
+        Some(mask) => aggregator.aggregate_batch(mask, returnflag.as_ref(), linestatus.as_ref(), &quantity, &price, &discount, &tax),
+        None => aggregator.aggregate_batch_unmasked(returnflag.as_ref(), linestatus.as_ref(), &quantity, &price, &discount, &tax),
+    })?;
+
+    if aggregator.distinct_mode().is_some() {
+        let orderkey = crate::utils::get_column(batch, "l_orderkey")?;
+        timed(&mut profile.aggregation, || {
+            aggregator.aggregate_distinct(mask.as_ref(), returnflag.as_ref(), linestatus.as_ref(), orderkey.as_ref())
+        })?;
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    
-    // Integration test would go here with sample data
+    use crate::reader::plan_lineitem;
+    use crate::test_util::{sample_rows, temp_path, write_lineitem, write_lineitem_with_props};
+    use crate::decimal_aggregator::Decimal;
+    use parquet::file::properties::WriterProperties;
+
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let path = temp_path("parallel");
+        write_lineitem(&path, &sample_rows(20_000), 1_000);
+        let path_str = path.to_str().unwrap();
+
+        let params = Q1Params::default();
+        let sequential = execute_tpch_q1(path_str, &params).unwrap();
+        assert!(!sequential.is_empty());
+        for threads in [1, 3, 8, 64] {
+            let parallel = execute_tpch_q1_parallel(path_str, &params, threads).unwrap();
+            assert_eq!(parallel.len(), sequential.len());
+            for (p, s) in parallel.iter().zip(&sequential) {
+                assert_eq!((p.returnflag, p.linestatus, p.count), (s.returnflag, s.linestatus, s.count));
+                assert!((p.sum_charge.unwrap() - s.sum_charge.unwrap()).abs() <= 1e-6 * s.sum_charge.unwrap().abs());
+                assert!((p.sum_qty.unwrap() - s.sum_qty.unwrap()).abs() <= 1e-9 * s.sum_qty.unwrap().abs());
+            }
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_multi_file() {
+        let rows = sample_rows(9_000);
+        let single = temp_path("multi_single");
+        write_lineitem(&single, &rows, 1_000);
+        let dir = temp_path("multi_dir");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (i, shard) in rows.chunks(3_000).enumerate() {
+            write_lineitem(&dir.join(format!("part-{}.parquet", i)), shard, 1_000);
+        }
+
+        let params = Q1Params::with_date(8800);
+        let expected = execute_tpch_q1(single.to_str().unwrap(), &params).unwrap();
+        let dir_str = dir.to_str().unwrap();
+        let glob = format!("{}/part-*.parquet", dir_str);
+        let list = crate::input::join_inputs(&[format!("{}/part-0.parquet", dir_str), format!("{}/part-[12].parquet", dir_str)]).unwrap();
+        for input in [dir_str, glob.as_str(), list.as_str()] {
+            let plan = plan_lineitem(input, &params).unwrap();
+            assert_eq!((plan.num_files(), plan.num_row_groups()), (3, 9));
+            // Only the first shard has ship dates before the cutoff
+            assert!(plan.row_groups().iter().all(|rg| rg.file == 0));
+
+            for threads in [1, 4] {
+                let results = execute_tpch_q1_parallel(input, &params, threads).unwrap();
+                assert_eq!(results.len(), expected.len());
+                for (r, e) in results.iter().zip(&expected) {
+                    assert_eq!((r.returnflag, r.linestatus, r.count), (e.returnflag, e.linestatus, e.count));
+                    assert!((r.sum_charge.unwrap() - e.sum_charge.unwrap()).abs() <= 1e-6 * e.sum_charge.unwrap().abs());
+                }
+            }
+        }
+        assert!(explain_tpch_q1(dir_str, &params).unwrap().contains(" files=3 "));
+
+        // Shards must agree on the projected column types
+        let other = dir.join("part-3.parquet");
+        let batch = RecordBatch::try_from_iter([("l_returnflag", std::sync::Arc::new(arrow::array::Int32Array::from(vec![1])) as ArrayRef)]).unwrap();
+        let mut writer = parquet::arrow::ArrowWriter::try_new(std::fs::File::create(&other).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+        let plan = crate::reader::plan_scan_files(&[dir.join("part-0.parquet"), other], &params, &["l_returnflag"]);
+        assert!(matches!(plan, Err(GooseError::TypeMismatch { .. })));
+
+        std::fs::remove_file(single).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_hive_partitions() {
+        use crate::sql::parser::{format_date, parse_date};
+
+        let rows = sample_rows(7_000);
+        let single = temp_path("hive_single");
+        write_lineitem(&single, &rows, 1_000);
+        let lake = temp_path("hive_lake");
+        let _ = std::fs::remove_dir_all(&lake);
+        let year = |r: &crate::test_util::Row| format_date(r.shipdate)[..4].parse::<i32>().unwrap();
+        for y in 1992..=1998 {
+            let shard: Vec<_> = rows.iter().filter(|r| year(r) == y).cloned().collect();
+            let dir = lake.join(format!("l_shipyear={}", y));
+            std::fs::create_dir_all(&dir).unwrap();
+            write_lineitem(&dir.join("part-0.parquet"), &shard, 500);
+        }
+        let lake_str = lake.to_str().unwrap();
+
+        let params = Q1Params::with_date(parse_date("1995-06-30").unwrap());
+        let plan = plan_lineitem(lake_str, &params).unwrap();
+        assert_eq!((plan.num_files(), plan.num_files_pruned()), (4, 3));
+        assert_eq!(plan.schema().field_with_name("l_shipyear").unwrap().data_type(), &arrow::datatypes::DataType::Int32);
+
+        let expected = execute_tpch_q1(single.to_str().unwrap(), &params).unwrap();
+        let results = execute_tpch_q1_parallel(lake_str, &params, 3).unwrap();
+        assert_eq!(
+            results.iter().map(|r| r.count).collect::<Vec<_>>(),
+            expected.iter().map(|r| r.count).collect::<Vec<_>>()
+        );
+
+        // Partition keys are queryable columns, even when nothing else is read
+        let mut catalog = crate::sql::Catalog::new();
+        catalog.register("lineitem", lake_str);
+        let sql = "SELECT l_shipyear, count(*) FROM lineitem WHERE l_shipdate <= '1995-06-30' GROUP BY l_shipyear ORDER BY 1";
+        let out = crate::sql::execute_sql(sql, &catalog).unwrap();
+        let years = out.column(0).as_any().downcast_ref::<arrow::array::Int32Array>().unwrap();
+        assert_eq!(years.values().to_vec(), vec![1992, 1993, 1994, 1995]);
+        let counts = out.column(1).as_any().downcast_ref::<arrow::array::UInt64Array>().unwrap();
+        assert_eq!(counts.values().iter().sum::<u64>(), expected.iter().map(|r| r.count).sum::<u64>());
+        assert!(crate::sql::explain_sql(sql, &catalog).unwrap().contains("files=4/7"));
+
+        // Every file pruned: the schema still comes from one footer
+        let plan = plan_lineitem(lake_str, &Q1Params::with_date(0)).unwrap();
+        assert_eq!((plan.num_files(), plan.num_files_pruned(), plan.row_groups().len()), (1, 7, 0));
+        assert!(execute_tpch_q1(lake_str, &Q1Params::with_date(0)).unwrap().is_empty());
+
+        std::fs::remove_file(single).unwrap();
+        std::fs::remove_dir_all(lake).unwrap();
+    }
+
+    #[test]
+    fn test_cutoff_params() {
+        let rows = sample_rows(10_000);
+        let path = temp_path("cutoff");
+        write_lineitem(&path, &rows, 1_000);
+        let path_str = path.to_str().unwrap();
+
+        for params in [Q1Params::with_delta(60), Q1Params::with_delta(120), Q1Params::with_date(9000)] {
+            let results = execute_tpch_q1(path_str, &params).unwrap();
+            let total: u64 = results.iter().map(|r| r.count).sum();
+            let expected = rows.iter().filter(|r| r.shipdate <= params.cutoff_days()).count() as u64;
+            assert_eq!(total, expected);
+        }
+
+        // A cutoff before every ship date prunes all row groups, in both modes
+        for mode in [AggregationMode::Float, AggregationMode::ExactDecimal] {
+            let options = ExecutionOptions { mode, ..ExecutionOptions::default() };
+            assert!(execute_tpch_q1_with_options(path_str, &Q1Params::with_date(0), &options).unwrap().is_empty());
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_group_by_strategies_agree() {
+        let path = temp_path("group_by");
+        write_lineitem(&path, &sample_rows(10_000), 2_000);
+        let path_str = path.to_str().unwrap();
+        let params = Q1Params::default();
+
+        let plan = plan_lineitem(path_str, &params).unwrap();
+        assert_eq!(choose_strategy(&plan, Q1_GROUP_BY).unwrap(), AggregationStrategy::PerfectHash(Box::new(PerfectHash::tpch())));
+        assert_eq!(choose_strategy(&plan, &["l_linestatus", "l_returnflag"]).unwrap(), AggregationStrategy::Hash);
+
+        let fast = execute_q1_group_by(path_str, &params, Q1_GROUP_BY).unwrap();
+        // Same keys forced through the general engine
+        let general = run_hash(&plan, &params, Q1_GROUP_BY, 1, Summation::Plain, &mut QueryProfile::default()).unwrap().finish().unwrap();
+
+        assert_eq!(fast.schema(), general.schema());
+        assert_eq!(fast.num_rows(), general.num_rows());
+        assert_eq!(fast.column(0), general.column(0));
+        assert_eq!(fast.column(1), general.column(1));
+        assert_eq!(fast.column(9), general.column(9));
+
+        // Single arbitrary key: counts must add up to the qualifying rows
+        let by_flag = execute_q1_group_by(path_str, &params, &["l_returnflag"]).unwrap();
+        let fast_counts = fast.column(9).as_any().downcast_ref::<arrow::array::UInt64Array>().unwrap();
+        let flag_counts = by_flag.column(8).as_any().downcast_ref::<arrow::array::UInt64Array>().unwrap();
+        assert_eq!(by_flag.num_rows(), 3);
+        assert_eq!(flag_counts.values().iter().sum::<u64>(), fast_counts.values().iter().sum::<u64>());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_profile() {
+        let rows = sample_rows(20_000);
+        let path = temp_path("profile");
+        write_lineitem(&path, &rows, 2_000);
+        let path_str = path.to_str().unwrap();
+        let params = Q1Params::with_date(9_000);
+        let qualifying = rows.iter().filter(|r| r.shipdate <= 9_000).count() as u64;
+
+        for num_threads in [1, 4] {
+            let options = ExecutionOptions {
+                num_threads,
+                ..ExecutionOptions::default()
+            };
+            let (results, profile) = execute_tpch_q1_profiled(path_str, &params, &options).unwrap();
+            assert_eq!(profile.rows_selected, qualifying);
+            assert_eq!(profile.rows_out, results.len() as u64);
+            assert_eq!(profile.row_groups_total, 10);
+            // Ship dates rise through the file: late row groups are pruned,
+            // every row of the surviving ones is decoded
+            assert!(profile.row_groups_skipped > 0);
+            assert_eq!(profile.rows_in, 2_000 * (10 - profile.row_groups_skipped) as u64);
+            assert!(profile.decode > std::time::Duration::ZERO);
+            assert!(profile.aggregation > std::time::Duration::ZERO);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_row_filter_matches_mask() {
+        let rows = sample_rows(20_000);
+        let path = temp_path("row_filter_q1");
+        write_lineitem(&path, &rows, 2_000);
+        let path_str = path.to_str().unwrap();
+        let params = Q1Params::with_date(rows[15_000].shipdate);
+
+        for mode in [AggregationMode::Float, AggregationMode::ExactDecimal] {
+            let mask = ExecutionOptions { mode, ..ExecutionOptions::default() };
+            let pushdown = ExecutionOptions { filter: FilterStrategy::RowFilter, num_threads: 3, ..mask };
+            let (expected, mask_profile) = execute_tpch_q1_profiled(path_str, &params, &mask).unwrap();
+            let (results, profile) = execute_tpch_q1_profiled(path_str, &params, &pushdown).unwrap();
+            assert_eq!(results.len(), expected.len());
+            for (r, e) in results.iter().zip(&expected) {
+                assert_eq!((r.returnflag, r.linestatus, r.count, r.exact), (e.returnflag, e.linestatus, e.count, e.exact));
+                assert!((r.sum_charge.unwrap() - e.sum_charge.unwrap()).abs() < 1e-9 * e.sum_charge.unwrap().abs());
+            }
+            // Rows failing the predicate are never handed to the aggregator
+            assert_eq!(profile.rows_selected, mask_profile.rows_selected);
+            assert_eq!(profile.rows_in, profile.rows_selected);
+            assert!(mask_profile.rows_in > mask_profile.rows_selected);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_non_standard_key_domains() {
+        let mut rows = sample_rows(4_000);
+        rows[10].returnflag = "X";
+        rows[20].linestatus = "P";
+        let params = Q1Params::default();
+        let expected: u64 = rows.iter().filter(|r| r.shipdate <= params.cutoff_days()).count() as u64;
+
+        // Discovered from dictionary pages: X and P get their own slots
+        let path = temp_path("domain_dict");
+        write_lineitem(&path, &rows, 1_000);
+        let path_str = path.to_str().unwrap();
+        let plan = plan_lineitem(path_str, &params).unwrap();
+        let keys = PerfectHash::new(b"ANRX", b"FOP").unwrap();
+        assert_eq!(choose_strategy(&plan, Q1_GROUP_BY).unwrap(), AggregationStrategy::PerfectHash(Box::new(keys)));
+
+        let results = execute_tpch_q1(path_str, &params).unwrap();
+        assert_eq!(results.iter().map(|r| r.count).sum::<u64>(), expected);
+        assert!(results.iter().any(|r| r.returnflag == Some(b'X') && r.count == 1));
+        assert!(results.iter().any(|r| r.linestatus == Some(b'P') && r.count == 1));
+        std::fs::remove_file(&path).unwrap();
+
+        // No dictionary: domain unknown, falls back to the hash table with the same answer
+        let path = temp_path("domain_plain");
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(1_000)
+            .set_dictionary_enabled(false)
+            .build();
+        write_lineitem_with_props(&path, &rows, props);
+        let plain_path = path.to_str().unwrap();
+        let plan = plan_lineitem(plain_path, &params).unwrap();
+        assert_eq!(choose_strategy(&plan, Q1_GROUP_BY).unwrap(), AggregationStrategy::Hash);
+        assert!(explain_tpch_q1(plain_path, &params).unwrap().contains("  HASH_GROUP_BY"));
+
+        let fallback = execute_tpch_q1_parallel(plain_path, &params, 2).unwrap();
+        assert_eq!(fallback.len(), results.len());
+        for (a, b) in fallback.iter().zip(&results) {
+            assert_eq!((a.returnflag, a.linestatus, a.count), (b.returnflag, b.linestatus, b.count));
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        // Multi-byte keys cannot be a QueryResult, but GROUP BY still works
+        rows[30].returnflag = "NN";
+        let path = temp_path("domain_multibyte");
+        write_lineitem(&path, &rows, 1_000);
+        let path_str = path.to_str().unwrap();
+        assert!(matches!(execute_tpch_q1(path_str, &params), Err(GooseError::InvalidData(_))));
+        let batch = execute_q1_group_by(path_str, &params, Q1_GROUP_BY).unwrap();
+        let counts = batch.column(9).as_any().downcast_ref::<arrow::array::UInt64Array>().unwrap();
+        assert_eq!(counts.values().iter().sum::<u64>(), expected);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_exact_decimal_mode() {
+        let rows = sample_rows(20_000);
+        let path = temp_path("exact");
+        write_lineitem(&path, &rows, 3_000);
+        let path_str = path.to_str().unwrap();
+        let params = Q1Params::default();
+
+        let float = execute_tpch_q1(path_str, &params).unwrap();
+        let options = ExecutionOptions {
+            num_threads: 4,
+            mode: AggregationMode::ExactDecimal,
+            ..ExecutionOptions::default()
+        };
+        let exact = execute_tpch_q1_with_options(path_str, &params, &options).unwrap();
+        assert_eq!(exact.len(), float.len());
+
+        for (e, f) in exact.iter().zip(&float) {
+            assert_eq!((e.returnflag, e.linestatus, e.count), (f.returnflag, f.linestatus, f.count));
+            let sums = e.exact.unwrap();
+
+            // Reference computed row by row in i128
+            let group = rows.iter().filter(|r| {
+                r.shipdate <= params.cutoff_days()
+                    && Some(r.returnflag.as_bytes()[0]) == e.returnflag
+                    && Some(r.linestatus.as_bytes()[0]) == e.linestatus
+            });
+            let (mut qty, mut disc_price, mut charge) = (0i128, 0i128, 0i128);
+            for r in group {
+                qty += r.quantity;
+                disc_price += r.extendedprice * (100 - r.discount);
+                charge += r.extendedprice * (100 - r.discount) * (100 + r.tax);
+            }
+            assert_eq!(sums.sum_qty, Decimal::new(qty, 2));
+            assert_eq!(sums.sum_disc_price, Decimal::new(disc_price, 4));
+            assert_eq!(sums.sum_charge, Decimal::new(charge, 6));
+            assert!((e.sum_charge.unwrap() - f.sum_charge.unwrap()).abs() <= 1e-9 * f.sum_charge.unwrap().abs());
+        }
+
+        let batch = results_to_record_batch(&exact).unwrap();
+        assert_eq!(batch.schema().field(5).data_type(), &arrow::datatypes::DataType::Decimal128(38, 6));
+
+        // Footer statistics prove 64-bit sums for TPC-H value ranges
+        let plan = plan_lineitem(path_str, &params).unwrap();
+        assert_eq!(exact_sum_width(&plan), SumWidth::I64);
+        let (_, profile) = execute_tpch_q1_profiled(path_str, &params, &options).unwrap();
+        assert_eq!(profile.sum_width, Some(SumWidth::I64));
+
+        // Without statistics nothing is proven, and the checked sums agree
+        let props = WriterProperties::builder()
+            .set_statistics_enabled(parquet::file::properties::EnabledStatistics::None)
+            .build();
+        write_lineitem_with_props(&path, &rows, props);
+        let plan = plan_lineitem(path_str, &params).unwrap();
+        assert_eq!(exact_sum_width(&plan), SumWidth::Checked);
+        let checked = execute_tpch_q1_with_options(path_str, &params, &options).unwrap();
+        assert_eq!(checked.iter().map(|r| r.exact).collect::<Vec<_>>(), exact.iter().map(|r| r.exact).collect::<Vec<_>>());
+
+        let moments = ExecutionOptions { moments: true, ..options };
+        assert!(matches!(execute_tpch_q1_with_options(path_str, &params, &moments), Err(GooseError::Unsupported(_))));
+        let kahan = ExecutionOptions { summation: Summation::Kahan, ..options };
+        assert!(matches!(execute_tpch_q1_with_options(path_str, &params, &kahan), Err(GooseError::Unsupported(_))));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_summation() {
+        let rows = sample_rows(20_000);
+        let path = temp_path("summation");
+        write_lineitem(&path, &rows, 3_000);
+        // Without dictionaries the key domain is unknown: the hash aggregator runs
+        let hash_path = temp_path("summation_hash");
+        let props = WriterProperties::builder().set_max_row_group_size(3_000).set_dictionary_enabled(false).build();
+        write_lineitem_with_props(&hash_path, &rows, props);
+        let params = Q1Params::default();
+        let run = |path: &std::path::Path, summation, num_threads, mode| {
+            let options = ExecutionOptions { num_threads, mode, summation, ..ExecutionOptions::default() };
+            execute_tpch_q1_with_options(path.to_str().unwrap(), &params, &options).unwrap()
+        };
+        let exact = run(&path, Summation::Plain, 1, AggregationMode::ExactDecimal);
+
+        for summation in [Summation::Kahan, Summation::Pairwise] {
+            let one = run(&path, summation, 1, AggregationMode::Float);
+            for other in [run(&path, summation, 4, AggregationMode::Float), run(&hash_path, summation, 1, AggregationMode::Float), run(&hash_path, summation, 4, AggregationMode::Float)] {
+                for ((a, b), e) in one.iter().zip(&other).zip(&exact) {
+                    assert_eq!((a.returnflag, a.linestatus, a.count), (e.returnflag, e.linestatus, e.count));
+                    assert_eq!((b.returnflag, b.linestatus, b.count), (e.returnflag, e.linestatus, e.count));
+                    let sums = |r: &QueryResult| [r.sum_qty, r.sum_base_price, r.sum_disc_price, r.sum_charge].map(Option::unwrap);
+                    let exact_sums = e.exact.unwrap();
+                    let exact_sums = [exact_sums.sum_qty, exact_sums.sum_base_price, exact_sums.sum_disc_price, exact_sums.sum_charge];
+                    for ((x, y), z) in sums(a).into_iter().zip(sums(b)).zip(exact_sums) {
+                        // The split over accumulators and threads moves the result by an ulp at most
+                        assert!((x - y).abs() <= 2.0 * f64::EPSILON * x.abs(), "{:?}: {} vs {}", summation, x, y);
+                        assert!((x - z.to_f64()).abs() <= 1e-12 * x.abs());
+                    }
+                }
+            }
+        }
+
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(hash_path).unwrap();
+    }
 }
@@ -1,11 +1,45 @@
 //! Parquet reader with column projection and row group pruning
+//!
+//! A scan covers one or more files (see `input::resolve_paths`); row groups
+//! are pruned per file and read back through a single `LineitemReader`.
+//! Hive partition keys in the file paths become extra (virtual) columns, and
+//! ship-date partitions (`l_shipdate=`, `l_shipyear=` / `l_shipmonth=`)
+//! prune whole files before their footers are read. Within the surviving
+//! row groups the `l_shipdate` page index skips pages past the cutoff and
+//! flags pages that lie entirely before it; row groups whose max statistic
+//! is at or before the cutoff are flagged as a whole (see
+//! `LineitemReader::all_pass`). With `FilterStrategy::RowFilter` the
+//! ship-date predicate is pushed into the Parquet reader instead, which
+//! decodes `l_shipdate` first and the other columns only for qualifying rows.
 
-use arrow::array::RecordBatch;
-use arrow::datatypes::SchemaRef;
-use arrow::error::ArrowError;
-use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use arrow::array::{RecordBatch, RecordBatchOptions, StringArray};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Field, FieldRef, Schema, SchemaRef};
+use parquet::arrow::arrow_reader::{
+    ArrowPredicateFn, ArrowReaderMetadata, ArrowReaderOptions, ParquetRecordBatchReader, ParquetRecordBatchReaderBuilder,
+    RowFilter, RowSelection, RowSelector,
+};
 use parquet::arrow::ProjectionMask;
+use parquet::basic::{Encoding, PageType, Type as PhysicalType};
+use parquet::column::page::{Page, PageReader};
+use parquet::file::metadata::{ColumnChunkMetaData, ParquetMetaData};
+use parquet::file::page_index::index::Index;
+use parquet::file::serialized_reader::SerializedPageReader;
+use parquet::file::statistics::Statistics;
+use parquet::format::{self, PageHeader};
+use parquet::thrift::TSerializable;
+use std::collections::{BTreeSet, VecDeque};
 use std::fs::File;
+use std::io::{BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thrift::protocol::TCompactInputProtocol;
+
+use crate::input::{hive_partitions, resolve_paths};
+use crate::integer_sum::Bounds;
+use crate::params::Q1Params;
+use crate::sql::parser::parse_date;
+use crate::error::GooseError;
 
 /// Columns we need for TPC-H Q1
 pub const REQUIRED_COLUMNS: &[&str] = &[
@@ -18,97 +52,847 @@ pub const REQUIRED_COLUMNS: &[&str] = &[
     "l_shipdate",
 ];
 
-/// The filter date: 1998-09-02 as days since epoch
+/// Group key columns kept dictionary-encoded as `Dictionary(Int32, Utf8)`
+/// rather than decoded to one string per row
+pub const DICTIONARY_COLUMNS: &[&str] = &["l_returnflag", "l_linestatus"];
+
+/// The default filter date: 1998-09-02 as days since epoch
 /// 1998-09-02 = days since 1970-01-01 = 10471
+/// (see `Q1Params` for other cutoffs)
 pub const FILTER_DATE_DAYS: i32 = 10471;
 
-/// Read parquet file with column projection
+/// Rows per decoded batch
+const BATCH_SIZE: usize = 8192;
+
+/// Where the ship-date predicate is evaluated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilterStrategy {
+    /// Decode every projected column and let the caller build a mask over
+    /// the batch (`filter::create_date_filter_mask`)
+    #[default]
+    Mask,
+    /// Install a Parquet `RowFilter`: `l_shipdate` is decoded and tested
+    /// first, and the other columns are decoded only for qualifying rows,
+    /// so every returned batch is `all_pass`
+    RowFilter,
+}
+
+/// Read a lineitem file, directory or glob with column projection
 /// Returns an iterator over record batches
-pub fn read_lineitem(path: &str) -> Result<LineitemReader, Box<dyn std::error::Error>> {
+pub fn read_lineitem(path: &str, params: &Q1Params) -> Result<LineitemReader, GooseError> {
+    read_lineitem_with_filter(path, params, FilterStrategy::Mask)
+}
+
+/// Like `read_lineitem`, choosing where the ship-date predicate runs
+pub fn read_lineitem_with_filter(path: &str, params: &Q1Params, filter: FilterStrategy) -> Result<LineitemReader, GooseError> {
+    plan_lineitem(path, params)?.with_filter(filter).read()
+}
+
+/// Open a lineitem input, resolve the projection and prune row groups
+/// against the ship-date cutoff in `params`
+///
+/// `path` is anything `input::resolve_paths` accepts. Each footer is parsed
+/// once here; readers built from the returned plan share the decoded
+/// metadata, so splitting the surviving row groups across workers does not
+/// re-read it.
+pub fn plan_lineitem(path: &str, params: &Q1Params) -> Result<LineitemPlan, GooseError> {
+    plan_scan(path, params, REQUIRED_COLUMNS)
+}
+
+/// Like `plan_lineitem`, but projecting an arbitrary set of columns
+///
+/// Pruning still uses the `l_shipdate` statistics, which do not need to be
+/// part of the projection. Files without `l_shipdate` are not pruned.
+pub fn plan_scan(path: &str, params: &Q1Params, columns: &[&str]) -> Result<LineitemPlan, GooseError> {
+    plan_files(path, &resolve_paths(path)?, params, columns)
+}
+
+/// Like `plan_scan`, over an explicit list of files
+///
+/// Every file must contain the projected columns with the types of the
+/// first file; the first file's schema is the scan schema.
+pub fn plan_scan_files(paths: &[PathBuf], params: &Q1Params, columns: &[&str]) -> Result<LineitemPlan, GooseError> {
+    let label = crate::input::join_inputs(&paths.iter().map(|p| p.to_string_lossy()).collect::<Vec<_>>())?;
+    plan_files(&label, paths, params, columns)
+}
+
+fn plan_files(label: &str, paths: &[PathBuf], params: &Q1Params, columns: &[&str]) -> Result<LineitemPlan, GooseError> {
+    if paths.is_empty() {
+        return Err(GooseError::Io(std::io::Error::new(std::io::ErrorKind::NotFound, "no input files")));
+    }
+    let partitions: Vec<_> = paths.iter().map(|p| hive_partitions(p)).collect();
+    let partition_fields = partition_fields(&partitions)?;
+
+    let mut files: Vec<FilePlan> = Vec::with_capacity(paths.len());
+    let mut row_groups = Vec::new();
+    let mut pruned = Vec::new();
+    for (path, partition) in paths.iter().zip(&partitions) {
+        // Partition pruning: skip the file without touching its footer
+        if partition_min_shipdate(partition).is_some_and(|min| min > params.cutoff_days()) {
+            pruned.push((path, partition));
+            continue;
+        }
+        let file = plan_file(path, params, columns, &partition_fields, partition)?;
+        if let Some(first) = files.first() {
+            check_types(first, &file, columns)?;
+        }
+        let index = files.len();
+        row_groups.extend(file.row_groups.iter().map(|&row_group| RowGroupId { file: index, row_group }));
+        files.push(file);
+    }
+    let files_pruned = pruned.len();
+    if files.is_empty() {
+        // Everything was pruned; one footer still provides the schema
+        let (path, partition) = pruned[0];
+        let mut file = plan_file(path, params, columns, &partition_fields, partition)?;
+        file.row_groups.clear();
+        files.push(file);
+    }
+
+    // Partition keys the files do not store themselves become virtual columns
+    let file_schema = files[0].metadata.schema().clone();
+    let mut fields = file_schema.fields().to_vec();
+    fields.extend(partition_fields.into_iter().filter(|f| file_schema.field_with_name(f.name()).is_err()));
+    let schema = Arc::new(Schema::new_with_metadata(fields, file_schema.metadata().clone()));
+
+    Ok(LineitemPlan {
+        path: label.to_string(),
+        files,
+        files_total: paths.len(),
+        files_pruned,
+        schema,
+        row_groups,
+        cutoff: params.cutoff_days(),
+        filter: FilterStrategy::Mask,
+    })
+}
+
+/// Reject files whose projected columns disagree with the first file's types
+fn check_types(first: &FilePlan, file: &FilePlan, columns: &[&str]) -> Result<(), GooseError> {
+    for &name in columns {
+        if let (Ok(expected), Ok(found)) = (first.metadata.schema().field_with_name(name), file.metadata.schema().field_with_name(name)) {
+            if expected.data_type() != found.data_type() {
+                return Err(GooseError::type_mismatch(
+                    &format!("{} in {}", name, file.path.display()),
+                    &expected.data_type().to_string(),
+                    found.data_type(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// One field per partition key: Int32 when every value is an integer,
+/// otherwise Utf8
+///
+/// All files must use the same keys in the same order.
+fn partition_fields(partitions: &[Vec<(String, Option<String>)>]) -> Result<Vec<FieldRef>, GooseError> {
+    let keys: Vec<&str> = partitions[0].iter().map(|(k, _)| k.as_str()).collect();
+    for partition in partitions {
+        if !partition.iter().map(|(k, _)| k.as_str()).eq(keys.iter().copied()) {
+            return Err(GooseError::InvalidData(format!(
+                "partition keys {:?} differ from {:?}",
+                partition.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+                keys
+            )));
+        }
+    }
+
+    Ok(keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| {
+            let integer = partitions.iter().all(|p| p[i].1.as_ref().is_none_or(|v| v.parse::<i32>().is_ok()));
+            let data_type = if integer { DataType::Int32 } else { DataType::Utf8 };
+            Arc::new(Field::new(*key, data_type, true))
+        })
+        .collect())
+}
+
+/// Earliest ship date a partition can hold, if its keys pin one down
+///
+/// Understands `l_shipdate=YYYY-MM-DD` and `l_shipyear=YYYY` with an
+/// optional `l_shipmonth=MM`.
+fn partition_min_shipdate(partition: &[(String, Option<String>)]) -> Option<i32> {
+    let get = |key: &str| partition.iter().find(|(k, _)| k == key).and_then(|(_, v)| v.as_deref());
+    if let Some(date) = get("l_shipdate") {
+        return parse_date(date).ok();
+    }
+    let year: i32 = get("l_shipyear")?.parse().ok()?;
+    let month: u32 = match get("l_shipmonth") {
+        Some(month) => month.parse().ok()?,
+        None => 1,
+    };
+    parse_date(&format!("{:04}-{:02}-01", year, month)).ok()
+}
+
+/// Parse one footer, resolve the projection and prune its row groups
+fn plan_file(
+    path: &Path,
+    params: &Q1Params,
+    columns: &[&str],
+    partition_fields: &[FieldRef],
+    partition: &[(String, Option<String>)],
+) -> Result<FilePlan, GooseError> {
     let file = File::open(path)?;
-    let mut builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
-    
+    let metadata = preserve_dictionaries(ArrowReaderMetadata::load(&file, ArrowReaderOptions::new().with_page_index(true))?);
+
     // Get arrow schema and projection indices FIRST
-    let arrow_schema = builder.schema().clone();
-    
-    // Find indices of required columns
-    let projection_indices: Vec<usize> = REQUIRED_COLUMNS
+    let arrow_schema = metadata.schema();
+
+    // Find indices of required columns; partition keys not stored in the
+    // file are filled in per batch
+    let mut projection_indices = Vec::new();
+    let mut partition_columns = Vec::new();
+    for &col_name in columns {
+        if let Some(idx) = arrow_schema.fields().iter().position(|f| f.name() == col_name) {
+            projection_indices.push(idx);
+        } else if let Some(i) = partition_fields.iter().position(|f| f.name() == col_name) {
+            partition_columns.push((partition_fields[i].clone(), partition[i].1.clone()));
+        } else {
+            return Err(GooseError::MissingColumn(col_name.to_string()));
+        }
+    }
+
+    // Row Group Skipping: Filter out row groups that don't match our predicate
+
+    // Get the parquet schema to find column indices; files without
+    // l_shipdate are scanned in full
+    let shipdate_idx = metadata
+        .parquet_schema()
+        .columns()
         .iter()
-        .map(|col_name| {
-            arrow_schema
-                .fields()
-                .iter()
-                .position(|f| f.name() == *col_name)
-                .expect(&format!("Column {} not found", col_name))
+        .position(|c| c.name() == "l_shipdate");
+
+    // Iterate over row groups and check statistics
+    let cutoff_days = params.cutoff_days();
+    let mut row_groups = Vec::new();
+    let mut pages = vec![None; metadata.metadata().num_row_groups()];
+    let mut all_pass = vec![false; metadata.metadata().num_row_groups()];
+    for (i, rg) in metadata.metadata().row_groups().iter().enumerate() {
+        let stats = shipdate_idx.and_then(|idx| rg.column(idx).statistics());
+        let min_val = stats.and_then(|s| s.min_bytes_opt());
+        if let Some(min_val) = min_val {
+            if min_val.len() == 4 {
+                let min_days = i32::from_le_bytes(min_val.try_into().unwrap());
+
+                if min_days > cutoff_days {
+                    continue;
+                }
+            }
+        }
+
+        // Fully qualifying: max at or before the cutoff and no NULLs, so
+        // neither the page index nor the filter is needed
+        if let Some(stats) = stats {
+            if let (Some(max_val), Some(0)) = (stats.max_bytes_opt(), stats.null_count_opt()) {
+                if max_val.len() == 4 && i32::from_le_bytes(max_val.try_into().unwrap()) <= cutoff_days {
+                    all_pass[i] = true;
+                    row_groups.push(i);
+                    continue;
+                }
+            }
+        }
+
+        // Page Skipping: the same test per page, from the column index
+        pages[i] = shipdate_idx.and_then(|idx| page_states(metadata.metadata(), i, idx, cutoff_days));
+        if pages[i].as_ref().is_some_and(|p| p.iter().all(|&(_, state)| state == PageState::Skip)) {
+            continue;
+        }
+        row_groups.push(i);
+    }
+
+    Ok(FilePlan {
+        path: path.to_path_buf(),
+        metadata,
+        projection_indices,
+        partition_columns,
+        row_groups,
+        pages,
+        all_pass,
+    })
+}
+
+/// Read the `DICTIONARY_COLUMNS` string columns of a file as dictionaries
+///
+/// Writers dictionary-encode these low-cardinality columns; with the
+/// dictionary type as a schema hint the reader hands out the codes and the
+/// row group's dictionary instead of copying a string per row. Files whose
+/// schema cannot take the hint are read as they are.
+fn preserve_dictionaries(metadata: ArrowReaderMetadata) -> ArrowReaderMetadata {
+    let dictionary = DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8));
+    let is_string = |dt: &DataType| match dt {
+        DataType::Utf8 => true,
+        DataType::Dictionary(_, value) => **value == DataType::Utf8,
+        _ => false,
+    };
+    let schema = metadata.schema();
+    let fields: Vec<FieldRef> = schema
+        .fields()
+        .iter()
+        .map(|f| match DICTIONARY_COLUMNS.contains(&f.name().as_str()) && is_string(f.data_type()) {
+            true => Arc::new(f.as_ref().clone().with_data_type(dictionary.clone())),
+            false => f.clone(),
         })
         .collect();
-    
-    // Row Group Skipping: Filter out row groups that don't match our predicate
-    
-    // Get the parquet schema to find column indices - SCOPE 1
-    let shipdate_idx = {
-        let parquet_schema = builder.parquet_schema();
-        parquet_schema
-            .columns()
-            .iter()
-            .position(|c| c.name() == "l_shipdate")
-            .expect("l_shipdate not found in parquet schema")
+    if fields.iter().zip(schema.fields()).all(|(hinted, field)| hinted == field) {
+        return metadata;
+    }
+    let hinted = Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()));
+    let options = ArrowReaderOptions::new().with_page_index(true).with_schema(hinted);
+    ArrowReaderMetadata::try_new(metadata.metadata().clone(), options).unwrap_or(metadata)
+}
+
+/// What the `l_shipdate` page index proves about a page's rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PageState {
+    /// Every value is past the cutoff or NULL
+    Skip,
+    /// Some rows may qualify; the filter has to run
+    Filter,
+    /// Every value is at or before the cutoff and none is NULL
+    AllPass,
+}
+
+/// `(row count, state)` of each page of a row group's `l_shipdate` column,
+/// or `None` without a usable column and offset index
+fn page_states(metadata: &ParquetMetaData, rg: usize, col: usize, cutoff: i32) -> Option<Vec<(usize, PageState)>> {
+    let Index::INT32(index) = metadata.column_index()?.get(rg)?.get(col)? else {
+        return None;
     };
+    let locations = metadata.offset_index()?.get(rg)?.get(col)?.page_locations();
+    if locations.len() != index.indexes.len() {
+        return None;
+    }
+    let num_rows = metadata.row_group(rg).num_rows() as usize;
+
+    Some(
+        index
+            .indexes
+            .iter()
+            .enumerate()
+            .map(|(i, page)| {
+                let start = locations[i].first_row_index as usize;
+                let end = locations.get(i + 1).map_or(num_rows, |next| next.first_row_index as usize);
+                let state = match (page.min, page.max, page.null_count) {
+                    (Some(min), _, _) if min > cutoff => PageState::Skip,
+                    // min and max are only absent when every value is NULL
+                    (None, None, _) => PageState::Skip,
+                    (_, Some(max), Some(0)) if max <= cutoff => PageState::AllPass,
+                    _ => PageState::Filter,
+                };
+                (end - start, state)
+            })
+            .collect(),
+    )
+}
+
+/// A row group of a scan: the file's position in the plan and the row
+/// group's index within that file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowGroupId {
+    pub file: usize,
+    pub row_group: usize,
+}
+
+/// One file of a scan with its footer and surviving row groups
+#[derive(Clone)]
+struct FilePlan {
+    path: PathBuf,
+    metadata: ArrowReaderMetadata,
+    projection_indices: Vec<usize>,
+    /// Projected partition keys and this file's value for each
+    partition_columns: Vec<(FieldRef, Option<String>)>,
+    row_groups: Vec<usize>,
+    /// Page states per row group (indexed like the file's row groups),
+    /// `None` for pruned row groups, fully qualifying row groups and files
+    /// without a page index
+    pages: Vec<Option<Vec<(usize, PageState)>>>,
+    /// Row groups whose statistics prove `l_shipdate <= cutoff` for every row
+    all_pass: Vec<bool>,
+}
+
+/// A lineitem input with its projection resolved and row groups pruned
+#[derive(Clone)]
+pub struct LineitemPlan {
+    path: String,
+    files: Vec<FilePlan>,
+    files_total: usize,
+    files_pruned: usize,
+    schema: SchemaRef,
+    row_groups: Vec<RowGroupId>,
+    cutoff: i32,
+    filter: FilterStrategy,
+}
+
+impl LineitemPlan {
+    /// Evaluate the ship-date predicate as `filter` says in readers built
+    /// from this plan (`FilterStrategy::Mask` by default)
+    pub fn with_filter(mut self, filter: FilterStrategy) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Where readers built from this plan evaluate the ship-date predicate
+    pub fn filter(&self) -> FilterStrategy {
+        self.filter
+    }
+
+    /// The input as given (file, directory, glob or path list)
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Files of the scan, in read order
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|f| f.path.as_path())
+    }
+
+    /// Number of files in the scan whose footers were read
+    pub fn num_files(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Number of input files, pruned or not
+    pub fn num_files_total(&self) -> usize {
+        self.files_total
+    }
+
+    /// Number of files skipped by partition pruning
+    ///
+    /// When every file is pruned, the first one's footer is still read for
+    /// the schema and `num_files` is 1.
+    pub fn num_files_pruned(&self) -> usize {
+        self.files_pruned
+    }
+
+    /// Row groups that survived statistics pruning, in file order
+    pub fn row_groups(&self) -> &[RowGroupId] {
+        &self.row_groups
+    }
+
+    /// Arrow schema of the first file (not just the projection), followed
+    /// by the partition keys
+    pub fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    /// Total number of row groups over all files
+    pub fn num_row_groups(&self) -> usize {
+        self.files.iter().map(|f| f.metadata.metadata().num_row_groups()).sum()
+    }
+
+    /// Surviving row groups whose `l_shipdate` statistics prove that every
+    /// row qualifies
+    pub fn num_row_groups_all_pass(&self) -> usize {
+        self.files.iter().map(|f| f.row_groups.iter().filter(|&&rg| f.all_pass[rg]).count()).sum()
+    }
+
+    /// `l_shipdate` pages of the surviving row groups that have a page
+    /// index: `(total, skipped, all pass)`
+    pub fn page_counts(&self) -> (usize, usize, usize) {
+        let states = self
+            .files
+            .iter()
+            .flat_map(|f| f.row_groups.iter().filter_map(|&rg| f.pages[rg].as_ref()))
+            .flatten()
+            .map(|&(_, state)| state);
+        states.fold((0, 0, 0), |(total, skipped, all_pass), state| {
+            (total + 1, skipped + (state == PageState::Skip) as usize, all_pass + (state == PageState::AllPass) as usize)
+        })
+    }
+
+    /// Discover the distinct values of a string column from file metadata
+    ///
+    /// Each surviving row group must either be fully dictionary-encoded for
+    /// the column (the dictionary page then lists every value it can hold) or
+    /// have statistics with `min == max` and no nulls. Encodings are proven
+    /// from chunk metadata or page headers; only dictionary pages are read
+    /// in full. Returns the sorted union of values, or `None` if any row
+    /// group cannot be proven, in which case callers must not assume a
+    /// closed domain.
+    pub fn column_domain(&self, column: &str) -> Result<Option<Vec<Vec<u8>>>, GooseError> {
+        let mut domain = BTreeSet::new();
+        for file_plan in &self.files {
+            match file_plan.column_domain(column)? {
+                Some(values) => domain.extend(values),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(domain.into_iter().collect()))
+    }
+
+    /// Rows in the surviving row groups, an upper bound on the rows scanned
+    pub fn num_rows(&self) -> u64 {
+        let rows = |f: &FilePlan| f.row_groups.iter().map(|&rg| f.metadata.metadata().row_group(rg).num_rows() as u64).sum::<u64>();
+        self.files.iter().map(rows).sum()
+    }
+
+    /// Unscaled min and max of an integer or decimal column over the
+    /// surviving row groups, from their footer statistics
+    ///
+    /// `None` unless each of those row groups has exact min / max
+    /// statistics for the column or holds only NULLs in it. A scan with no
+    /// values at all has bounds `0..=0`.
+    pub fn column_bounds(&self, column: &str) -> Option<Bounds> {
+        let mut bounds: Option<Bounds> = None;
+        for file_plan in &self.files {
+            let col_idx = file_plan.metadata.parquet_schema().columns().iter().position(|c| c.name() == column)?;
+            for &rg in &file_plan.row_groups {
+                let row_group = file_plan.metadata.metadata().row_group(rg);
+                let stats = row_group.column(col_idx).statistics()?;
+                if stats.null_count_opt() == Some(row_group.num_rows() as u64) {
+                    continue;
+                }
+                if !stats.min_is_exact() || !stats.max_is_exact() {
+                    return None;
+                }
+                let (min, max) = statistics_bounds(stats)?;
+                bounds = Some(match bounds {
+                    Some(b) => Bounds { min: b.min.min(min), max: b.max.max(max) },
+                    None => Bounds { min, max },
+                });
+            }
+        }
+        Some(bounds.unwrap_or(Bounds { min: 0, max: 0 }))
+    }
+
+    /// Read every surviving row group
+    pub fn read(&self) -> Result<LineitemReader, GooseError> {
+        self.read_row_groups(self.row_groups.clone())
+    }
+
+    /// Read only the given row groups, in the order given
+    ///
+    /// Files are opened one at a time as the reader reaches them.
+    pub fn read_row_groups(&self, row_groups: Vec<RowGroupId>) -> Result<LineitemReader, GooseError> {
+        // Consecutive row groups of the same file share one Parquet reader
+        let mut pending: VecDeque<(FilePlan, Vec<usize>)> = VecDeque::new();
+        for id in row_groups {
+            match pending.back_mut() {
+                Some((file, groups)) if file.path == self.files[id.file].path => groups.push(id.row_group),
+                _ => pending.push_back((self.files[id.file].clone(), vec![id.row_group])),
+            }
+        }
+
+        Ok(LineitemReader {
+            pending,
+            row_filter: (self.filter == FilterStrategy::RowFilter).then_some(self.cutoff),
+            current: None,
+            runs: VecDeque::new(),
+            all_pass: false,
+            schema: self.schema().clone(),
+        })
+    }
+}
+
+impl FilePlan {
+    /// `LineitemPlan::column_domain` for this file's surviving row groups
+    fn column_domain(&self, column: &str) -> Result<Option<Vec<Vec<u8>>>, GooseError> {
+        let Some(col_idx) = self.metadata.parquet_schema().columns().iter().position(|c| c.name() == column) else {
+            return Ok(None);
+        };
+        if self.row_groups.is_empty() {
+            return Ok(Some(Vec::new()));
+        }
+
+        let file = Arc::new(File::open(&self.path)?);
+        let mut domain = BTreeSet::new();
+
+        for &rg_idx in &self.row_groups {
+            let rg = self.metadata.metadata().row_group(rg_idx);
+            let chunk = rg.column(col_idx);
+            if chunk.column_type() != PhysicalType::BYTE_ARRAY {
+                return Ok(None);
+            }
+
+            // Constant chunk: statistics alone pin the single value
+            if let Some(value) = chunk.statistics().and_then(constant_value) {
+                domain.insert(value.to_vec());
+                continue;
+            }
+
+            // Otherwise every data page must reference the dictionary page;
+            // a writer that fell back to PLAIN mid-chunk can hold other values
+            let dictionary_only = match dictionary_only(chunk) {
+                Some(proven) => proven,
+                None => data_pages_dictionary_only(&file, chunk)?,
+            };
+            if !dictionary_only || chunk.dictionary_page_offset().is_none() {
+                return Ok(None);
+            }
+
+            // The chunk starts at the dictionary page: decode that page only
+            let mut pages = SerializedPageReader::new(file.clone(), chunk, rg.num_rows() as usize, None)?;
+            match pages.get_next_page()? {
+                Some(Page::DictionaryPage { buf, num_values, .. }) => {
+                    domain.extend(decode_plain_byte_arrays(&buf, num_values as usize)?);
+                }
+                _ => return Ok(None),
+            }
+        }
+
+        Ok(Some(domain.into_iter().collect()))
+    }
+
+    /// Append the projected partition keys as constant columns
+    fn with_partition_columns(&self, batch: RecordBatch) -> Result<RecordBatch, GooseError> {
+        if self.partition_columns.is_empty() {
+            return Ok(batch);
+        }
+        let num_rows = batch.num_rows();
+        let mut fields = batch.schema().fields().to_vec();
+        let mut columns = batch.columns().to_vec();
+        for (field, value) in &self.partition_columns {
+            let values = StringArray::from(vec![value.as_deref(); num_rows]);
+            columns.push(cast(&values, field.data_type())?);
+            fields.push(field.clone());
+        }
+        let options = RecordBatchOptions::new().with_row_count(Some(num_rows));
+        Ok(RecordBatch::try_new_with_options(Arc::new(Schema::new(fields)), columns, &options)?)
+    }
 
-    // 2. Iterate over row groups and check statistics - SCOPE 2
-    let row_groups_to_read = {
-        let metadata = builder.metadata();
-        let mut groups = Vec::new();
-
-        for (i, rg) in metadata.row_groups().iter().enumerate() {
-            if let Some(stats) = rg.column(shipdate_idx).statistics() {
-                // valid way for deprecated min_bytes:
-                let min_val = stats.min_bytes();
-                 
-                if min_val.len() == 4 {
-                    let min_days = i32::from_le_bytes(min_val.try_into().unwrap());
-                    
-                    if min_days > FILTER_DATE_DAYS {
-                        continue;
+    /// Open a projected reader over some of this file's row groups
+    ///
+    /// Also returns the selected rows as `(row count, all pass)` runs in
+    /// output order. With `row_filter` set to the cutoff, rows failing the
+    /// ship-date predicate are dropped inside the Parquet reader.
+    fn open(
+        &self,
+        row_groups: Vec<usize>,
+        row_filter: Option<i32>,
+    ) -> Result<(ParquetRecordBatchReader, VecDeque<(usize, bool)>), GooseError> {
+        // Skip pages past the cutoff; remember which selected runs need no filter
+        let mut selectors = Vec::new();
+        let mut runs = VecDeque::new();
+        for &rg in &row_groups {
+            match &self.pages[rg] {
+                Some(pages) => {
+                    for &(rows, state) in pages {
+                        if state == PageState::Skip {
+                            selectors.push(RowSelector::skip(rows));
+                        } else {
+                            selectors.push(RowSelector::select(rows));
+                            runs.push_back((rows, state == PageState::AllPass));
+                        }
                     }
                 }
+                None => {
+                    let rows = self.metadata.metadata().row_group(rg).num_rows() as usize;
+                    selectors.push(RowSelector::select(rows));
+                    runs.push_back((rows, self.all_pass[rg]));
+                }
             }
-            groups.push(i);
         }
-        groups
+
+        let file = File::open(&self.path)?;
+        let mut builder = ParquetRecordBatchReaderBuilder::new_with_metadata(file, self.metadata.clone())
+            .with_row_groups(row_groups);
+        if selectors.iter().any(|s| s.skip) {
+            builder = builder.with_row_selection(RowSelection::from(selectors));
+        }
+
+        // Late materialization: pointless when statistics already proved
+        // every selected row qualifies
+        let shipdate_idx = builder.parquet_schema().columns().iter().position(|c| c.name() == "l_shipdate");
+        if let (Some(cutoff), Some(idx)) = (row_filter, shipdate_idx) {
+            if runs.iter().any(|&(_, pass)| !pass) {
+                let params = Q1Params::with_date(cutoff);
+                let predicate = ArrowPredicateFn::new(ProjectionMask::leaves(builder.parquet_schema(), [idx]), move |batch| {
+                    crate::filter::create_date_filter_mask(&batch, &params).map_err(|e| arrow::error::ArrowError::ExternalError(Box::new(e)))
+                });
+                builder = builder.with_row_filter(RowFilter::new(vec![Box::new(predicate)]));
+                // Only qualifying rows come back, in runs of unknown length
+                runs = VecDeque::from([(usize::MAX, true)]);
+            }
+        }
+
+        // Create projection mask
+        let projection = ProjectionMask::roots(builder.parquet_schema(), self.projection_indices.clone());
+
+        // Build reader with projection and reasonable batch size
+        let reader = builder
+            .with_projection(projection)
+            .with_batch_size(BATCH_SIZE)
+            .build()?;
+        Ok((reader, runs))
+    }
+}
+
+/// Min and max of integer or decimal statistics as unscaled i128
+///
+/// Decimals stored as (fixed-length) byte arrays are big-endian two's
+/// complement.
+fn statistics_bounds(stats: &Statistics) -> Option<(i128, i128)> {
+    let signed_be = |bytes: &[u8]| -> Option<i128> {
+        if bytes.is_empty() || bytes.len() > 16 {
+            return None;
+        }
+        let fill = if bytes[0] & 0x80 != 0 { 0xFF } else { 0 };
+        let mut buf = [fill; 16];
+        buf[16 - bytes.len()..].copy_from_slice(bytes);
+        Some(i128::from_be_bytes(buf))
     };
-    
-    // Apply the row group filter - consumes builder
-    builder = builder.with_row_groups(row_groups_to_read);
+    match stats {
+        Statistics::Int32(s) => Some((*s.min_opt()? as i128, *s.max_opt()? as i128)),
+        Statistics::Int64(s) => Some((*s.min_opt()? as i128, *s.max_opt()? as i128)),
+        Statistics::FixedLenByteArray(s) => Some((signed_be(s.min_opt()?.data())?, signed_be(s.max_opt()?.data())?)),
+        Statistics::ByteArray(s) => Some((signed_be(s.min_opt()?.data())?, signed_be(s.max_opt()?.data())?)),
+        _ => None,
+    }
+}
 
-    // Get schema again from new builder for projection
-    let parquet_schema = builder.parquet_schema();
-    
-    // Create projection mask
-    let projection = ProjectionMask::roots(parquet_schema, projection_indices.clone());
-    
-    // Build reader with projection and reasonable batch size
-    let reader = builder
-        .with_projection(projection)
-        .with_batch_size(8192)
-        .build()?;
-    
-    Ok(LineitemReader {
-        inner: reader,
-        schema: arrow_schema,
-    })
+/// Decode a PLAIN-encoded BYTE_ARRAY buffer (u32 length prefix + bytes)
+fn decode_plain_byte_arrays(buf: &[u8], num_values: usize) -> Result<Vec<Vec<u8>>, GooseError> {
+    let truncated = || GooseError::InvalidData("truncated dictionary page".to_string());
+    let mut values = Vec::with_capacity(num_values);
+    let mut pos = 0;
+    for _ in 0..num_values {
+        let len_bytes = buf.get(pos..pos + 4).ok_or_else(truncated)?;
+        let len = u32::from_le_bytes(len_bytes.try_into().unwrap()) as usize;
+        pos += 4;
+        values.push(buf.get(pos..pos + len).ok_or_else(truncated)?.to_vec());
+        pos += len;
+    }
+    Ok(values)
+}
+
+/// The single value of a chunk whose statistics prove it constant
+///
+/// Needs no NULLs and equal min and max that are both exact: writers may
+/// truncate string bounds, and two truncated bounds can match while the
+/// chunk holds different values.
+fn constant_value(stats: &Statistics) -> Option<&[u8]> {
+    if stats.null_count_opt() != Some(0) || !stats.min_is_exact() || !stats.max_is_exact() {
+        return None;
+    }
+    let (min, max) = (stats.min_bytes_opt()?, stats.max_bytes_opt()?);
+    (min == max).then_some(min)
+}
+
+/// Whether the chunk metadata proves every data page dictionary encoded
+///
+/// Page encoding statistics answer it directly. Without them the chunk's
+/// encodings do, unless they list PLAIN next to RLE_DICTIONARY: writers
+/// that encode the dictionary page itself as PLAIN (parquet-rs among them)
+/// list the same set whether or not they fell back to PLAIN data pages, so
+/// `None` leaves it to the page headers.
+fn dictionary_only(chunk: &ColumnChunkMetaData) -> Option<bool> {
+    let is_dictionary = |e: &Encoding| matches!(e, Encoding::PLAIN_DICTIONARY | Encoding::RLE_DICTIONARY);
+    if let Some(stats) = chunk.page_encoding_stats() {
+        let mut data_pages = stats.iter().filter(|s| matches!(s.page_type, PageType::DATA_PAGE | PageType::DATA_PAGE_V2));
+        return Some(data_pages.all(|s| is_dictionary(&s.encoding)));
+    }
+
+    let encodings = chunk.encodings();
+    if !encodings.iter().any(is_dictionary) {
+        return Some(false);
+    }
+    // RLE and BIT_PACKED only encode levels
+    #[allow(deprecated)]
+    let values: Vec<&Encoding> = encodings
+        .iter()
+        .filter(|e| !is_dictionary(e) && !matches!(e, Encoding::RLE | Encoding::BIT_PACKED))
+        .collect();
+    match values.as_slice() {
+        [] => Some(true),
+        // A PLAIN_DICTIONARY dictionary page leaves PLAIN to the data pages
+        [Encoding::PLAIN] if !encodings.contains(&Encoding::PLAIN_DICTIONARY) => None,
+        _ => Some(false),
+    }
+}
+
+/// Whether every data page of a chunk is dictionary encoded, from the page
+/// headers alone: the page bodies are skipped, not read or decompressed
+fn data_pages_dictionary_only(file: &File, chunk: &ColumnChunkMetaData) -> Result<bool, GooseError> {
+    let (start, length) = chunk.byte_range();
+    let mut reader = BufReader::new(file);
+    reader.seek(SeekFrom::Start(start))?;
+    while reader.stream_position()? < start + length {
+        let header = PageHeader::read_from_in_protocol(&mut TCompactInputProtocol::new(&mut reader))
+            .map_err(|e| GooseError::InvalidData(format!("bad page header: {}", e)))?;
+        let encoding = match header.type_ {
+            format::PageType::DATA_PAGE => header.data_page_header.map(|h| h.encoding),
+            format::PageType::DATA_PAGE_V2 => header.data_page_header_v2.map(|h| h.encoding),
+            _ => None,
+        };
+        if encoding.is_some_and(|e| e != format::Encoding::RLE_DICTIONARY && e != format::Encoding::PLAIN_DICTIONARY) {
+            return Ok(false);
+        }
+        reader.seek_relative(header.compressed_page_size as i64)?;
+    }
+    Ok(true)
 }
 
+/// Record batches of a scan, file after file
 pub struct LineitemReader {
-    inner: parquet::arrow::arrow_reader::ParquetRecordBatchReader,
+    pending: VecDeque<(FilePlan, Vec<usize>)>,
+    /// Cutoff of the pushed-down ship-date predicate (`FilterStrategy::RowFilter`)
+    row_filter: Option<i32>,
+    current: Option<(ParquetRecordBatchReader, FilePlan)>,
+    /// Selected rows of the current file not yet returned, as `(rows, all pass)`
+    runs: VecDeque<(usize, bool)>,
+    all_pass: bool,
     schema: SchemaRef,
 }
 
+impl LineitemReader {
+    /// Whether row group statistics or the page index proved that every
+    /// row of the batch last returned by `next` has `l_shipdate <= cutoff`,
+    /// so the ship-date filter can be skipped for it
+    ///
+    /// Always true under `FilterStrategy::RowFilter`.
+    pub fn all_pass(&self) -> bool {
+        self.all_pass
+    }
+
+    /// Consume `rows` rows from the selected runs
+    fn advance_runs(&mut self, mut rows: usize) {
+        self.all_pass = true;
+        while rows > 0 {
+            let Some((run, pass)) = self.runs.front_mut() else {
+                self.all_pass = false;
+                return;
+            };
+            let n = rows.min(*run);
+            self.all_pass &= *pass;
+            *run -= n;
+            rows -= n;
+            if *run == 0 {
+                self.runs.pop_front();
+            }
+        }
+    }
+}
+
 impl Iterator for LineitemReader {
-    type Item = Result<RecordBatch, ArrowError>;
-    
+    type Item = Result<RecordBatch, GooseError>;
+
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next()
+        loop {
+            if let Some((reader, file)) = &mut self.current {
+                match reader.next() {
+                    Some(batch) => {
+                        let batch = batch.map_err(GooseError::from).and_then(|b| file.with_partition_columns(b));
+                        match &batch {
+                            Ok(batch) => self.advance_runs(batch.num_rows()),
+                            Err(_) => self.all_pass = false,
+                        }
+                        return Some(batch);
+                    }
+                    None => self.current = None,
+                }
+            }
+            let (file, row_groups) = self.pending.pop_front()?;
+            match file.open(row_groups, self.row_filter) {
+                Ok((reader, runs)) => {
+                    self.current = Some((reader, file));
+                    self.runs = runs;
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
     }
 }
 
@@ -131,4 +915,296 @@ mod tests {
         let days = (date - epoch).num_days() as i32;
         assert_eq!(days, FILTER_DATE_DAYS);
     }
+
+    #[test]
+    fn test_column_domain() {
+        use crate::test_util::{sample_rows, temp_path, write_lineitem_with_props};
+        use parquet::file::properties::WriterProperties;
+
+        let path = temp_path("column_domain");
+        let rows = sample_rows(5_000);
+        let props = WriterProperties::builder().set_max_row_group_size(1_000).build();
+        write_lineitem_with_props(&path, &rows, props);
+        let plan = plan_lineitem(path.to_str().unwrap(), &Q1Params::default()).unwrap();
+
+        let flags = plan.column_domain("l_returnflag").unwrap().unwrap();
+        assert_eq!(flags, vec![b"A".to_vec(), b"N".to_vec(), b"R".to_vec()]);
+        let statuses = plan.column_domain("l_linestatus").unwrap().unwrap();
+        assert_eq!(statuses, vec![b"F".to_vec(), b"O".to_vec()]);
+        assert_eq!(plan.column_domain("l_quantity").unwrap(), None);
+
+        let props = WriterProperties::builder().set_dictionary_enabled(false).build();
+        write_lineitem_with_props(&path, &rows, props);
+        let plan = plan_lineitem(path.to_str().unwrap(), &Q1Params::default()).unwrap();
+        assert_eq!(plan.column_domain("l_returnflag").unwrap(), None);
+
+        // A dictionary that overflows its page falls back to PLAIN data pages
+        let props = WriterProperties::builder()
+            .set_dictionary_page_size_limit(2)
+            .set_data_page_row_count_limit(100)
+            .set_write_batch_size(100)
+            .build();
+        write_lineitem_with_props(&path, &rows, props);
+        let plan = plan_lineitem(path.to_str().unwrap(), &Q1Params::default()).unwrap();
+        assert_eq!(plan.column_domain("l_returnflag").unwrap(), None);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_dictionary_only_metadata() {
+        use parquet::file::page_encoding_stats::PageEncodingStats;
+        use parquet::schema::types::{ColumnDescriptor, ColumnPath, Type};
+
+        let column = Type::primitive_type_builder("c", PhysicalType::BYTE_ARRAY).build().unwrap();
+        let descriptor = Arc::new(ColumnDescriptor::new(Arc::new(column), 0, 0, ColumnPath::from("c")));
+        let chunk = |encodings: &[Encoding]| ColumnChunkMetaData::builder(descriptor.clone()).set_encodings(encodings.to_vec());
+        let stats = |page_type, encoding| PageEncodingStats { page_type, encoding, count: 1 };
+
+        // Version 1 dictionary pages are PLAIN_DICTIONARY, so PLAIN is a data page
+        let v1 = [Encoding::PLAIN_DICTIONARY, Encoding::RLE];
+        assert_eq!(dictionary_only(&chunk(&v1).build().unwrap()), Some(true));
+        let fallback = [Encoding::PLAIN_DICTIONARY, Encoding::PLAIN, Encoding::RLE];
+        assert_eq!(dictionary_only(&chunk(&fallback).build().unwrap()), Some(false));
+        assert_eq!(dictionary_only(&chunk(&[Encoding::PLAIN, Encoding::RLE]).build().unwrap()), Some(false));
+        let delta = [Encoding::PLAIN, Encoding::RLE_DICTIONARY, Encoding::DELTA_BYTE_ARRAY];
+        assert_eq!(dictionary_only(&chunk(&delta).build().unwrap()), Some(false));
+
+        // A PLAIN dictionary page is ambiguous, unless encoding stats settle it
+        let v2 = [Encoding::PLAIN, Encoding::RLE, Encoding::RLE_DICTIONARY];
+        assert_eq!(dictionary_only(&chunk(&v2).build().unwrap()), None);
+        let dictionary_page = stats(PageType::DICTIONARY_PAGE, Encoding::PLAIN);
+        let proven = chunk(&v2).set_page_encoding_stats(vec![dictionary_page.clone(), stats(PageType::DATA_PAGE, Encoding::RLE_DICTIONARY)]);
+        assert_eq!(dictionary_only(&proven.build().unwrap()), Some(true));
+        let fell_back = chunk(&v2).set_page_encoding_stats(vec![dictionary_page, stats(PageType::DATA_PAGE, Encoding::PLAIN)]);
+        assert_eq!(dictionary_only(&fell_back.build().unwrap()), Some(false));
+    }
+
+    #[test]
+    fn test_constant_value() {
+        use parquet::data_type::ByteArray;
+        use parquet::file::statistics::ValueStatistics;
+
+        let stats = |min: &str, max: &str, nulls| {
+            ValueStatistics::new(Some(ByteArray::from(min)), Some(ByteArray::from(max)), None, Some(nulls), false)
+        };
+        assert_eq!(constant_value(&stats("A", "A", 0).into()), Some(&b"A"[..]));
+        assert_eq!(constant_value(&stats("A", "N", 0).into()), None);
+        assert_eq!(constant_value(&stats("A", "A", 1).into()), None);
+
+        // Truncated bounds only bracket the values
+        assert_eq!(constant_value(&stats("AIR", "AIR", 0).with_min_is_exact(false).into()), None);
+        assert_eq!(constant_value(&stats("AIR", "AIR", 0).with_max_is_exact(false).into()), None);
+    }
+
+    #[test]
+    fn test_dictionary_keys() {
+        use crate::test_util::{sample_rows, temp_path, write_lineitem_with_props};
+        use arrow::array::AsArray;
+        use parquet::file::properties::WriterProperties;
+
+        let path = temp_path("dictionary_keys");
+        let rows = sample_rows(3_000);
+        let dictionary = DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8));
+        // Dictionary-encoded pages, and plain pages the reader re-encodes
+        for enabled in [true, false] {
+            let props = WriterProperties::builder().set_dictionary_enabled(enabled).set_max_row_group_size(1_000).build();
+            write_lineitem_with_props(&path, &rows, props);
+            let plan = plan_lineitem(path.to_str().unwrap(), &Q1Params::with_date(i32::MAX)).unwrap();
+            assert_eq!(plan.schema().field_with_name("l_returnflag").unwrap().data_type(), &dictionary);
+
+            let mut flags = Vec::new();
+            for batch in plan.read().unwrap() {
+                let column = batch.unwrap().column_by_name("l_linestatus").unwrap().clone();
+                assert_eq!(column.data_type(), &dictionary);
+                // Codes index the row group's handful of distinct values
+                assert!(column.as_any_dictionary().values().len() <= 2);
+                let values = cast(&column, &DataType::Utf8).unwrap();
+                flags.extend(values.as_string::<i32>().iter().map(|v| v.unwrap().to_string()));
+            }
+            assert_eq!(flags, rows.iter().map(|r| r.linestatus.to_string()).collect::<Vec<_>>());
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_partition_min_shipdate() {
+        let partition = |pairs: &[(&str, &str)]| -> Vec<(String, Option<String>)> {
+            pairs.iter().map(|(k, v)| (k.to_string(), Some(v.to_string()))).collect()
+        };
+        let days = |text| parse_date(text).unwrap();
+        assert_eq!(partition_min_shipdate(&partition(&[("l_shipyear", "1998"), ("l_shipmonth", "09")])), Some(days("1998-09-01")));
+        assert_eq!(partition_min_shipdate(&partition(&[("l_shipyear", "1998")])), Some(days("1998-01-01")));
+        assert_eq!(partition_min_shipdate(&partition(&[("l_shipdate", "1998-09-03")])), Some(days("1998-09-03")));
+        assert_eq!(partition_min_shipdate(&partition(&[("l_shipmonth", "09")])), None);
+        assert_eq!(partition_min_shipdate(&partition(&[("l_shipyear", "soon")])), None);
+    }
+
+    #[test]
+    fn test_page_index_pruning() {
+        use crate::test_util::{sample_rows, temp_path, write_lineitem_with_props};
+        use parquet::file::properties::WriterProperties;
+
+        // Ship dates grow with the row number; 100-row pages
+        let path = temp_path("page_index");
+        let rows = sample_rows(10_000);
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(5_000)
+            .set_data_page_row_count_limit(100)
+            .set_write_batch_size(100)
+            .build();
+        write_lineitem_with_props(&path, &rows, props);
+        let path_str = path.to_str().unwrap();
+
+        let cutoff = rows[2_550].shipdate;
+        let params = Q1Params::with_date(cutoff);
+        let plan = plan_lineitem(path_str, &params).unwrap();
+        assert_eq!(plan.row_groups().len(), 1);
+        let (pages, skipped, all_pass) = plan.page_counts();
+        assert_eq!(pages, 50);
+        assert!(skipped >= 23 && all_pass >= 25, "{} skipped, {} all-pass", skipped, all_pass);
+
+        let (mut decoded, mut selected) = (0, 0);
+        let mut reader = plan.read().unwrap();
+        while let Some(batch) = reader.next() {
+            let batch = batch.unwrap();
+            let mask = crate::filter::create_date_filter_mask(&batch, &params).unwrap();
+            if reader.all_pass() {
+                assert_eq!(mask.true_count(), batch.num_rows());
+            }
+            decoded += batch.num_rows();
+            selected += mask.true_count();
+        }
+        assert_eq!(selected, rows.iter().filter(|r| r.shipdate <= cutoff).count());
+        assert!(decoded <= 2_700, "decoded {} rows", decoded);
+
+        // Only the last page of the first row group needs the filter
+        let plan = plan_lineitem(path_str, &Q1Params::with_date(rows[4_999].shipdate - 1)).unwrap();
+        let mut reader = plan.read_row_groups(vec![RowGroupId { file: 0, row_group: 0 }]).unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        assert_eq!(batch.num_rows(), 5_000);
+        assert!(!reader.all_pass());
+
+        let plan = plan_lineitem(path_str, &Q1Params::with_date(i32::MAX)).unwrap();
+        let mut reader = plan.read().unwrap();
+        reader.next().unwrap().unwrap();
+        assert!(reader.all_pass());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_row_group_all_pass() {
+        use crate::test_util::{sample_rows, temp_path, write_lineitem_with_props};
+        use parquet::file::properties::{EnabledStatistics, WriterProperties};
+
+        // Chunk statistics only: no page index to fall back on
+        let path = temp_path("row_group_all_pass");
+        let rows = sample_rows(5_000);
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(1_000)
+            .set_statistics_enabled(EnabledStatistics::Chunk)
+            .build();
+        write_lineitem_with_props(&path, &rows, props);
+        let path_str = path.to_str().unwrap();
+
+        let params = Q1Params::with_date(rows[2_500].shipdate);
+        let plan = plan_lineitem(path_str, &params).unwrap();
+        assert_eq!(plan.page_counts(), (0, 0, 0));
+        assert_eq!(plan.row_groups().len(), 3);
+        assert_eq!(plan.num_row_groups_all_pass(), 2);
+
+        for (row_group, all_pass) in [(0, true), (1, true), (2, false)] {
+            let mut reader = plan.read_row_groups(vec![RowGroupId { file: 0, row_group }]).unwrap();
+            reader.next().unwrap().unwrap();
+            assert_eq!(reader.all_pass(), all_pass);
+        }
+        // A batch spanning a qualifying and a partial row group needs the filter
+        let mut reader = plan.read().unwrap();
+        assert_eq!(reader.next().unwrap().unwrap().num_rows(), 3_000);
+        assert!(!reader.all_pass());
+
+        let results = crate::query::execute_tpch_q1(path_str, &params).unwrap();
+        let expected = rows.iter().filter(|r| r.shipdate <= params.cutoff_days()).count() as u64;
+        assert_eq!(results.iter().map(|r| r.count).sum::<u64>(), expected);
+        let explain = crate::query::explain_tpch_q1(path_str, &params).unwrap();
+        assert!(explain.contains("row_groups=3/5 (2 pruned by l_shipdate <= 1995-07-03, 2 all-pass)"), "{}", explain);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_column_bounds() {
+        use crate::integer_sum::Bounds;
+        use crate::test_util::{sample_rows, temp_path, write_lineitem};
+
+        let path = temp_path("column_bounds");
+        let rows = sample_rows(5_000);
+        write_lineitem(&path, &rows, 1_000);
+        let path_str = path.to_str().unwrap();
+
+        // Only the three surviving row groups count
+        let plan = plan_lineitem(path_str, &Q1Params::with_date(rows[2_500].shipdate)).unwrap();
+        assert_eq!(plan.num_rows(), 3_000);
+        let surviving = &rows[..3_000];
+        let expected = |f: fn(&crate::test_util::Row) -> i128| Bounds {
+            min: surviving.iter().map(f).min().unwrap(),
+            max: surviving.iter().map(f).max().unwrap(),
+        };
+        assert_eq!(plan.column_bounds("l_extendedprice"), Some(expected(|r| r.extendedprice)));
+        assert_eq!(plan.column_bounds("l_discount"), Some(expected(|r| r.discount)));
+        assert_eq!(plan.column_bounds("l_orderkey"), Some(Bounds { min: 1, max: 750 }));
+        assert_eq!(plan.column_bounds("l_missing"), None);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_row_filter() {
+        use crate::test_util::{sample_rows, temp_path, write_lineitem};
+
+        let path = temp_path("row_filter");
+        let rows = sample_rows(10_000);
+        write_lineitem(&path, &rows, 2_000);
+        let path_str = path.to_str().unwrap();
+
+        for cutoff in [rows[5_500].shipdate, rows[0].shipdate - 1, i32::MAX] {
+            let params = Q1Params::with_date(cutoff);
+            let expected = rows.iter().filter(|r| r.shipdate <= cutoff).count();
+            let mut reader = read_lineitem_with_filter(path_str, &params, FilterStrategy::RowFilter).unwrap();
+            let mut decoded = 0;
+            while let Some(batch) = reader.next() {
+                let batch = batch.unwrap();
+                assert!(reader.all_pass());
+                let mask = crate::filter::create_date_filter_mask(&batch, &params).unwrap();
+                assert_eq!(mask.true_count(), batch.num_rows());
+                assert_eq!(batch.num_columns(), REQUIRED_COLUMNS.len());
+                decoded += batch.num_rows();
+            }
+            assert_eq!(decoded, expected);
+        }
+
+        let plan = plan_lineitem(path_str, &Q1Params::default()).unwrap();
+        assert_eq!(plan.filter(), FilterStrategy::Mask);
+        assert_eq!(plan.with_filter(FilterStrategy::RowFilter).filter(), FilterStrategy::RowFilter);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_bad_files_are_errors() {
+        use crate::test_util::{sample_rows, temp_path, write_lineitem};
+
+        let params = Q1Params::default();
+        assert!(matches!(plan_lineitem("/nonexistent/lineitem.parquet", &params), Err(GooseError::Io(_))));
+
+        let path = temp_path("not_parquet");
+        std::fs::write(&path, b"l_returnflag,l_linestatus\nA,F\n").unwrap();
+        assert!(matches!(plan_lineitem(path.to_str().unwrap(), &params), Err(GooseError::Parquet(_))));
+
+        write_lineitem(&path, &sample_rows(10), 10);
+        let plan = plan_scan(path.to_str().unwrap(), &params, &["l_returnflag", "l_comment"]);
+        assert!(matches!(plan, Err(GooseError::MissingColumn(c)) if c == "l_comment"));
+        std::fs::remove_file(path).unwrap();
+    }
 }
@@ -0,0 +1,459 @@
+//! Name resolution and semantic checks
+//!
+//! The binder turns a parsed `Query` into a `BoundQuery` against the file
+//! schema: column names are resolved, constant date arithmetic is folded,
+//! the `l_shipdate` cutoff is split off the WHERE clause so the reader can
+//! prune row groups with it, and aggregate calls are pulled out of the
+//! SELECT list. After binding, SELECT expressions of an aggregate query
+//! only reference the aggregation output: `__group{i}` for the i-th GROUP BY
+//! expression and `__agg{i}` for the i-th aggregate.
+
+use arrow::array::RecordBatch;
+use arrow::datatypes::{DataType, SchemaRef};
+
+use super::eval::evaluate;
+use super::parser::{parse_date, BinaryOp, Expr, Literal, Query, SelectItem};
+use crate::hash_aggregator::AggregateFunction;
+use crate::error::GooseError;
+use crate::quantile::QuantileMode;
+
+/// An aggregate call pulled out of the SELECT list
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundAggregate {
+    pub func: AggregateFunction,
+    /// Input expression over scan columns (None for COUNT(*))
+    pub arg: Option<Expr>,
+}
+
+/// A query resolved against a Parquet file
+#[derive(Debug, Clone)]
+pub struct BoundQuery {
+    pub path: String,
+    /// Full file schema
+    pub schema: SchemaRef,
+    /// Columns the scan must read, in file order
+    pub scan_columns: Vec<String>,
+    /// Inclusive `l_shipdate` cutoff (days since epoch) used for pruning
+    pub cutoff: Option<i32>,
+    /// Remaining WHERE conjuncts
+    pub predicate: Option<Expr>,
+    pub group_by: Vec<Expr>,
+    pub aggregates: Vec<BoundAggregate>,
+    /// Output expressions and names
+    pub projection: Vec<(Expr, String)>,
+    /// (output column, descending)
+    pub order_by: Vec<(usize, bool)>,
+    pub limit: Option<usize>,
+}
+
+impl BoundQuery {
+    /// Whether the query aggregates (GROUP BY or aggregate calls)
+    pub fn is_aggregate(&self) -> bool {
+        !self.group_by.is_empty() || !self.aggregates.is_empty()
+    }
+}
+
+/// Column name of the i-th GROUP BY expression in the aggregation output
+pub fn group_column(i: usize) -> String {
+    format!("__group{}", i)
+}
+
+/// Column name of the i-th aggregate in the aggregation output
+pub fn aggregate_column(i: usize) -> String {
+    format!("__agg{}", i)
+}
+
+/// Bind a parsed query to the file at `path` with the given schema
+pub fn bind(query: &Query, path: &str, schema: SchemaRef) -> Result<BoundQuery, GooseError> {
+    let binder = Binder { schema: &schema };
+
+    // SELECT list, with * expanded
+    let mut select = Vec::new();
+    for item in &query.select {
+        match item {
+            SelectItem::Wildcard => {
+                for field in schema.fields() {
+                    select.push((Expr::Column(field.name().clone()), field.name().clone()));
+                }
+            }
+            SelectItem::Expr { expr, alias } => {
+                let name = match (alias, expr) {
+                    (Some(alias), _) => alias.clone(),
+                    (None, Expr::Column(name)) => name.clone(),
+                    (None, expr) => expr.to_string(),
+                };
+                select.push((binder.resolve(expr)?, name));
+            }
+        }
+    }
+
+    // WHERE: split off the ship-date cutoff
+    let mut cutoff = None;
+    let mut residual = Vec::new();
+    if let Some(selection) = &query.selection {
+        let selection = binder.resolve(selection)?;
+        if contains_aggregate(&selection) {
+            return Err(GooseError::Sql("aggregate functions are not allowed in WHERE".to_string()));
+        }
+        for conjunct in split_conjuncts(selection) {
+            match shipdate_cutoff(&conjunct) {
+                Some(days) => cutoff = Some(cutoff.map_or(days, |c: i32| c.min(days))),
+                None => residual.push(conjunct),
+            }
+        }
+    }
+    let predicate = residual.into_iter().reduce(|a, b| Expr::binary(BinaryOp::And, a, b));
+
+    let group_by = query
+        .group_by
+        .iter()
+        .map(|expr| binder.resolve(expr))
+        .collect::<Result<Vec<_>, _>>()?;
+    if group_by.iter().any(contains_aggregate) {
+        return Err(GooseError::Sql("aggregate functions are not allowed in GROUP BY".to_string()));
+    }
+
+    // Pull aggregates out of the SELECT list
+    let mut aggregates = Vec::new();
+    let mut projection = Vec::new();
+    for (expr, name) in &select {
+        projection.push((extract_aggregates(expr, &group_by, &mut aggregates)?, name.clone()));
+    }
+
+    // SUM, AVG, the variances and the quantiles need numbers; arguments are
+    // typed by evaluating them over an empty batch
+    let empty = RecordBatch::new_empty(schema.clone());
+    for aggregate in &aggregates {
+        if let Some(arg) = &aggregate.arg {
+            let data_type = evaluate(arg, &empty)?.data_type().clone();
+            if !aggregate.func.accepts(&data_type) {
+                return Err(GooseError::Sql(format!("{} has type {}, the aggregate needs a number", arg, data_type)));
+            }
+        }
+    }
+
+    if !group_by.is_empty() || !aggregates.is_empty() {
+        if query.select.contains(&SelectItem::Wildcard) {
+            return Err(GooseError::Sql("SELECT * cannot be combined with GROUP BY or aggregates".to_string()));
+        }
+        for (expr, _) in &projection {
+            let mut columns = Vec::new();
+            expr.columns(&mut columns);
+            if let Some(column) = columns.iter().find(|c| !c.starts_with("__")) {
+                return Err(GooseError::Sql(format!("column {} must appear in GROUP BY or be used in an aggregate", column)));
+            }
+        }
+    }
+
+    // ORDER BY refers to output columns by alias, position or expression
+    let mut order_by = Vec::new();
+    for item in &query.order_by {
+        let index = match &item.expr {
+            Expr::Literal(Literal::Number(n)) => {
+                let position = *n as usize;
+                if n.fract() != 0.0 || position == 0 || position > select.len() {
+                    return Err(GooseError::Sql(format!("ORDER BY position {} is out of range", n)));
+                }
+                position - 1
+            }
+            expr => {
+                let by_name = match expr {
+                    Expr::Column(name) => select.iter().position(|(_, out)| out.eq_ignore_ascii_case(name)),
+                    _ => None,
+                };
+                match by_name {
+                    Some(index) => index,
+                    None => {
+                        let resolved = binder.resolve(expr)?;
+                        select
+                            .iter()
+                            .position(|(e, _)| *e == resolved)
+                            .ok_or_else(|| GooseError::Sql(format!("ORDER BY {} must appear in the SELECT list", expr)))?
+                    }
+                }
+            }
+        };
+        order_by.push((index, item.descending));
+    }
+
+    // Everything the scan reads, in file order
+    let mut referenced = Vec::new();
+    select.iter().for_each(|(e, _)| e.columns(&mut referenced));
+    group_by.iter().for_each(|e| e.columns(&mut referenced));
+    if let Some(predicate) = &predicate {
+        predicate.columns(&mut referenced);
+    }
+    if cutoff.is_some() {
+        referenced.push("l_shipdate".to_string());
+    }
+    let scan_columns = schema
+        .fields()
+        .iter()
+        .map(|f| f.name().clone())
+        .filter(|name| referenced.contains(name))
+        .collect();
+
+    Ok(BoundQuery {
+        path: path.to_string(),
+        schema: schema.clone(),
+        scan_columns,
+        cutoff,
+        predicate,
+        group_by,
+        aggregates,
+        projection,
+        order_by,
+        limit: query.limit,
+    })
+}
+
+struct Binder<'a> {
+    schema: &'a SchemaRef,
+}
+
+impl Binder<'_> {
+    /// Resolve column names and fold constant date expressions
+    fn resolve(&self, expr: &Expr) -> Result<Expr, GooseError> {
+        Ok(match expr {
+            Expr::Column(name) => {
+                let field = self
+                    .schema
+                    .fields()
+                    .iter()
+                    .find(|f| f.name().eq_ignore_ascii_case(name))
+                    .ok_or_else(|| GooseError::MissingColumn(name.to_string()))?;
+                Expr::Column(field.name().clone())
+            }
+            Expr::Literal(_) => expr.clone(),
+            Expr::Binary { op, left, right } => {
+                let (left, right) = (self.resolve(left)?, self.resolve(right)?);
+                match (op, &left, &right) {
+                    (BinaryOp::Plus, Expr::Literal(Literal::Date(d)), Expr::Literal(Literal::IntervalDays(n)))
+                    | (BinaryOp::Plus, Expr::Literal(Literal::IntervalDays(n)), Expr::Literal(Literal::Date(d))) => {
+                        Expr::Literal(Literal::Date(d + n))
+                    }
+                    (BinaryOp::Minus, Expr::Literal(Literal::Date(d)), Expr::Literal(Literal::IntervalDays(n))) => {
+                        Expr::Literal(Literal::Date(d - n))
+                    }
+                    _ => Expr::binary(*op, left, right),
+                }
+            }
+            Expr::Unary { op, expr } => Expr::Unary {
+                op: *op,
+                expr: Box::new(self.resolve(expr)?),
+            },
+            Expr::Function { name, args, star, distinct } => Expr::Function {
+                name: name.clone(),
+                args: args.iter().map(|a| self.resolve(a)).collect::<Result<_, _>>()?,
+                star: *star,
+                distinct: *distinct,
+            },
+            Expr::Cast { expr, data_type } => match (self.resolve(expr)?, data_type) {
+                (Expr::Literal(Literal::String(text)), DataType::Date32) => Expr::Literal(Literal::Date(parse_date(&text)?)),
+                (expr, data_type) => Expr::Cast {
+                    expr: Box::new(expr),
+                    data_type: data_type.clone(),
+                },
+            },
+        })
+    }
+}
+
+/// Split `a AND b AND c` into its conjuncts
+fn split_conjuncts(expr: Expr) -> Vec<Expr> {
+    match expr {
+        Expr::Binary { op: BinaryOp::And, left, right } => {
+            let mut conjuncts = split_conjuncts(*left);
+            conjuncts.extend(split_conjuncts(*right));
+            conjuncts
+        }
+        expr => vec![expr],
+    }
+}
+
+/// Inclusive cutoff of an `l_shipdate <= DATE` style conjunct
+fn shipdate_cutoff(expr: &Expr) -> Option<i32> {
+    let Expr::Binary { op, left, right } = expr else { return None };
+    let date = |e: &Expr| match e {
+        Expr::Literal(Literal::Date(d)) => Some(*d),
+        Expr::Literal(Literal::String(s)) => parse_date(s).ok(),
+        _ => None,
+    };
+    let is_shipdate = |e: &Expr| matches!(e, Expr::Column(name) if name == "l_shipdate");
+
+    match (op, is_shipdate(left), is_shipdate(right)) {
+        (BinaryOp::LtEq, true, false) => date(right),
+        (BinaryOp::Lt, true, false) => date(right).map(|d| d - 1),
+        (BinaryOp::GtEq, false, true) => date(left),
+        (BinaryOp::Gt, false, true) => date(left).map(|d| d - 1),
+        _ => None,
+    }
+}
+
+/// Map an aggregate function name to the hash aggregator's function
+fn aggregate_function(name: &str, star: bool, distinct: bool) -> Option<AggregateFunction> {
+    if distinct {
+        return (name == "count" && !star).then_some(AggregateFunction::CountDistinct);
+    }
+    match (name, star) {
+        ("sum", false) => Some(AggregateFunction::Sum),
+        ("avg", false) => Some(AggregateFunction::Avg),
+        ("count", false) => Some(AggregateFunction::Count),
+        ("count", true) => Some(AggregateFunction::CountStar),
+        ("min", false) => Some(AggregateFunction::Min),
+        ("max", false) => Some(AggregateFunction::Max),
+        ("var_samp" | "variance", false) => Some(AggregateFunction::VarSamp),
+        ("var_pop", false) => Some(AggregateFunction::VarPop),
+        ("stddev_samp" | "stddev", false) => Some(AggregateFunction::StddevSamp),
+        ("stddev_pop", false) => Some(AggregateFunction::StddevPop),
+        ("approx_count_distinct", false) => Some(AggregateFunction::ApproxCountDistinct),
+        ("median", false) => Some(AggregateFunction::Quantile { fraction: 0.5, mode: QuantileMode::Exact }),
+        _ => None,
+    }
+}
+
+/// `quantile_cont(col, fraction)` / `approx_quantile(col, fraction)`, with
+/// the fraction a number literal in `0..=1`
+fn quantile_function(name: &str, fraction: &Expr) -> Result<AggregateFunction, GooseError> {
+    let mode = if name == "approx_quantile" { QuantileMode::Approx } else { QuantileMode::Exact };
+    match fraction {
+        Expr::Literal(Literal::Number(fraction)) if (0.0..=1.0).contains(fraction) => {
+            Ok(AggregateFunction::Quantile { fraction: *fraction, mode })
+        }
+        _ => Err(GooseError::Sql(format!("{} needs a fraction between 0 and 1, found {}", name, fraction))),
+    }
+}
+
+fn contains_aggregate(expr: &Expr) -> bool {
+    match expr {
+        Expr::Function { .. } => true,
+        Expr::Column(_) | Expr::Literal(_) => false,
+        Expr::Binary { left, right, .. } => contains_aggregate(left) || contains_aggregate(right),
+        Expr::Unary { expr, .. } | Expr::Cast { expr, .. } => contains_aggregate(expr),
+    }
+}
+
+/// Replace GROUP BY expressions and aggregate calls with references to the
+/// aggregation output, collecting the (deduplicated) aggregates
+fn extract_aggregates(
+    expr: &Expr,
+    group_by: &[Expr],
+    aggregates: &mut Vec<BoundAggregate>,
+) -> Result<Expr, GooseError> {
+    if let Some(i) = group_by.iter().position(|g| g == expr) {
+        return Ok(Expr::Column(group_column(i)));
+    }
+
+    Ok(match expr {
+        Expr::Function { name, args, star, distinct } => {
+            let (func, args) = match (name.as_str(), args.as_slice()) {
+                ("quantile_cont" | "approx_quantile", [arg, fraction]) if !*distinct => {
+                    (quantile_function(name, fraction)?, std::slice::from_ref(arg))
+                }
+                ("quantile_cont" | "approx_quantile", _) => {
+                    return Err(GooseError::Sql(format!("{} takes a column and a fraction", name)))
+                }
+                _ => {
+                    let func = aggregate_function(name, *star, *distinct);
+                    (func.ok_or_else(|| GooseError::Sql(format!("unsupported function {}", expr)))?, args.as_slice())
+                }
+            };
+            let arg = match (args, star) {
+                (_, true) => None,
+                ([arg], false) if !contains_aggregate(arg) => Some(arg.clone()),
+                ([_], false) => return Err(GooseError::Sql(format!("nested aggregate in {}", expr))),
+                _ => return Err(GooseError::Sql(format!("{} takes exactly one argument", name))),
+            };
+            let aggregate = BoundAggregate { func, arg };
+            let index = match aggregates.iter().position(|a| *a == aggregate) {
+                Some(index) => index,
+                None => {
+                    aggregates.push(aggregate);
+                    aggregates.len() - 1
+                }
+            };
+            Expr::Column(aggregate_column(index))
+        }
+        Expr::Column(_) | Expr::Literal(_) => expr.clone(),
+        Expr::Binary { op, left, right } => Expr::binary(
+            *op,
+            extract_aggregates(left, group_by, aggregates)?,
+            extract_aggregates(right, group_by, aggregates)?,
+        ),
+        Expr::Unary { op, expr } => Expr::Unary {
+            op: *op,
+            expr: Box::new(extract_aggregates(expr, group_by, aggregates)?),
+        },
+        Expr::Cast { expr, data_type } => Expr::Cast {
+            expr: Box::new(extract_aggregates(expr, group_by, aggregates)?),
+            data_type: data_type.clone(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::parser::parse;
+    use arrow::datatypes::{Field, Schema};
+    use std::sync::Arc;
+
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("l_quantity", DataType::Float64, false),
+            Field::new("l_extendedprice", DataType::Float64, false),
+            Field::new("l_returnflag", DataType::Utf8, false),
+            Field::new("l_shipdate", DataType::Date32, false),
+        ]))
+    }
+
+    fn bind_sql(sql: &str) -> Result<BoundQuery, GooseError> {
+        bind(&parse(sql)?, "t.parquet", schema())
+    }
+
+    #[test]
+    fn test_bind_aggregate_query() {
+        let bound = bind_sql(
+            "SELECT L_RETURNFLAG, sum(l_quantity) AS q, sum(l_quantity) / count(*) AS a \
+             FROM t WHERE l_shipdate < date '1998-12-01' - interval '90' day AND l_quantity > 1 \
+             GROUP BY l_returnflag ORDER BY a DESC, 1",
+        )
+        .unwrap();
+
+        assert_eq!(bound.cutoff, Some(crate::reader::FILTER_DATE_DAYS - 1));
+        assert_eq!(bound.predicate.unwrap().to_string(), "(l_quantity > 1)");
+        assert_eq!(bound.scan_columns, vec!["l_quantity", "l_returnflag", "l_shipdate"]);
+        assert_eq!(bound.aggregates.len(), 2);
+        assert_eq!(bound.projection[0], (Expr::Column("__group0".into()), "l_returnflag".into()));
+        assert_eq!(bound.projection[2].0.to_string(), "(__agg0 / __agg1)");
+        assert_eq!(bound.order_by, vec![(2, true), (0, false)]);
+    }
+
+    #[test]
+    fn test_bind_errors() {
+        assert!(bind_sql("SELECT l_tax FROM t").is_err());
+        assert!(bind_sql("SELECT l_quantity, count(*) FROM t").is_err());
+        assert!(bind_sql("SELECT * FROM t GROUP BY l_returnflag").is_err());
+        assert!(bind_sql("SELECT l_quantity FROM t WHERE sum(l_quantity) > 1").is_err());
+        assert!(bind_sql("SELECT mode(l_quantity) FROM t").is_err());
+        assert!(bind_sql("SELECT quantile_cont(l_quantity, 1.5) FROM t").is_err());
+        assert!(bind_sql("SELECT quantile_cont(l_quantity, l_tax) FROM t").is_err());
+        assert!(bind_sql("SELECT approx_quantile(l_quantity) FROM t").is_err());
+        assert!(bind_sql("SELECT median(l_quantity), quantile_cont(l_quantity, 0.95) FROM t").is_ok());
+        assert!(bind_sql("SELECT l_quantity FROM t ORDER BY l_extendedprice").is_err());
+        assert!(bind_sql("SELECT l_quantity FROM t ORDER BY 2").is_err());
+        assert!(bind_sql("SELECT sum(l_returnflag) FROM t").is_err());
+        assert!(bind_sql("SELECT l_returnflag, stddev(l_shipdate) FROM t GROUP BY l_returnflag").is_err());
+        assert!(bind_sql("SELECT median(l_returnflag) FROM t").is_err());
+        assert!(bind_sql("SELECT count(l_returnflag), min(l_returnflag), max(l_shipdate) FROM t").is_ok());
+    }
+
+    #[test]
+    fn test_bind_wildcard() {
+        let bound = bind_sql("SELECT * FROM t WHERE l_shipdate <= CAST('1998-09-02' AS date)").unwrap();
+        assert_eq!(bound.projection.len(), 4);
+        assert_eq!(bound.cutoff, Some(crate::reader::FILTER_DATE_DAYS));
+        assert!(bound.predicate.is_none());
+        assert!(!bound.is_aggregate());
+    }
+}
@@ -0,0 +1,178 @@
+//! Vectorized evaluation of scalar expressions over record batches
+
+use std::sync::Arc;
+
+use arrow::array::{Array, ArrayRef, BooleanArray, Date32Array, Float64Array, RecordBatch, Scalar, StringArray, UInt32Array};
+use arrow::compute::kernels::{cmp, numeric};
+use arrow::compute::{and_kleene, cast, not, or_kleene, take};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+
+use super::parser::{BinaryOp, Expr, Literal, UnaryOp};
+use crate::error::GooseError;
+
+/// Evaluate the SELECT list over a batch
+pub(crate) fn project(projection: &[(Expr, String)], batch: &RecordBatch) -> Result<RecordBatch, GooseError> {
+    let columns = projection
+        .iter()
+        .map(|(expr, _)| evaluate(expr, batch))
+        .collect::<Result<Vec<_>, _>>()?;
+    let fields: Vec<Field> = projection
+        .iter()
+        .zip(&columns)
+        .map(|((_, name), column)| Field::new(name, column.data_type().clone(), true))
+        .collect();
+    let schema: SchemaRef = Arc::new(Schema::new(fields));
+    Ok(RecordBatch::try_new_with_options(
+        schema,
+        columns,
+        &arrow::array::RecordBatchOptions::new().with_row_count(Some(batch.num_rows())),
+    )?)
+}
+
+/// Result of evaluating an expression: a column, or a single value
+enum Value {
+    Array(ArrayRef),
+    /// One-element array
+    Scalar(ArrayRef),
+}
+
+impl Value {
+    fn array(&self) -> &ArrayRef {
+        match self {
+            Value::Array(array) | Value::Scalar(array) => array,
+        }
+    }
+
+    fn map(self, f: impl FnOnce(&ArrayRef) -> Result<ArrayRef, GooseError>) -> Result<Value, GooseError> {
+        Ok(match self {
+            Value::Array(array) => Value::Array(f(&array)?),
+            Value::Scalar(array) => Value::Scalar(f(&array)?),
+        })
+    }
+
+    /// Repeat a scalar to `num_rows`
+    fn into_array(self, num_rows: usize) -> Result<ArrayRef, GooseError> {
+        match self {
+            Value::Array(array) => Ok(array),
+            Value::Scalar(array) => Ok(take(array.as_ref(), &UInt32Array::from(vec![0; num_rows]), None)?),
+        }
+    }
+}
+
+/// Evaluate an expression to a column of `batch.num_rows()` values
+pub(crate) fn evaluate(expr: &Expr, batch: &RecordBatch) -> Result<ArrayRef, GooseError> {
+    eval(expr, batch)?.into_array(batch.num_rows())
+}
+
+fn eval(expr: &Expr, batch: &RecordBatch) -> Result<Value, GooseError> {
+    match expr {
+        Expr::Column(name) => Ok(Value::Array(crate::utils::get_column(batch, name)?.clone())),
+        Expr::Literal(literal) => Ok(Value::Scalar(match literal {
+            Literal::Number(n) => Arc::new(Float64Array::from(vec![*n])),
+            Literal::String(s) => Arc::new(StringArray::from(vec![s.as_str()])),
+            Literal::Date(d) => Arc::new(Date32Array::from(vec![*d])),
+            Literal::IntervalDays(_) => return Err(GooseError::Sql("INTERVAL is only supported in date literal arithmetic".to_string())),
+        })),
+        Expr::Unary { op: UnaryOp::Neg, expr } => {
+            eval(expr, batch)?.map(|a| Ok(numeric::neg(&cast(a, &DataType::Float64)?)?))
+        }
+        Expr::Unary { op: UnaryOp::Not, expr } => eval(expr, batch)?.map(|a| Ok(Arc::new(not(as_boolean(a)?)?))),
+        Expr::Cast { expr, data_type } => eval(expr, batch)?.map(|a| Ok(cast(a, data_type)?)),
+        Expr::Function { name, .. } => Err(GooseError::Sql(format!("function {} is not allowed here", name))),
+        Expr::Binary { op, left, right } => {
+            let (left, right) = (eval(left, batch)?, eval(right, batch)?);
+            let scalar = matches!((&left, &right), (Value::Scalar(_), Value::Scalar(_)));
+
+            let result: ArrayRef = if op.is_arithmetic() {
+                let l = cast(left.array(), &DataType::Float64)?;
+                let r = cast(right.array(), &DataType::Float64)?;
+                let (l, r) = (datum(&left, l), datum(&right, r));
+                match op {
+                    BinaryOp::Plus => numeric::add(l.as_ref(), r.as_ref())?,
+                    BinaryOp::Minus => numeric::sub(l.as_ref(), r.as_ref())?,
+                    BinaryOp::Multiply => numeric::mul(l.as_ref(), r.as_ref())?,
+                    _ => numeric::div(l.as_ref(), r.as_ref())?,
+                }
+            } else if op.is_comparison() {
+                let common = comparison_type(left.array().data_type(), right.array().data_type());
+                let l = cast(left.array(), &common)?;
+                let r = cast(right.array(), &common)?;
+                let (l, r) = (datum(&left, l), datum(&right, r));
+                Arc::new(match op {
+                    BinaryOp::Eq => cmp::eq(l.as_ref(), r.as_ref())?,
+                    BinaryOp::NotEq => cmp::neq(l.as_ref(), r.as_ref())?,
+                    BinaryOp::Lt => cmp::lt(l.as_ref(), r.as_ref())?,
+                    BinaryOp::LtEq => cmp::lt_eq(l.as_ref(), r.as_ref())?,
+                    BinaryOp::Gt => cmp::gt(l.as_ref(), r.as_ref())?,
+                    _ => cmp::gt_eq(l.as_ref(), r.as_ref())?,
+                })
+            } else {
+                let num_rows = if scalar { 1 } else { batch.num_rows() };
+                let l = left.into_array(num_rows)?;
+                let r = right.into_array(num_rows)?;
+                Arc::new(match op {
+                    BinaryOp::And => and_kleene(as_boolean(&l)?, as_boolean(&r)?)?,
+                    _ => or_kleene(as_boolean(&l)?, as_boolean(&r)?)?,
+                })
+            };
+
+            Ok(if scalar { Value::Scalar(result) } else { Value::Array(result) })
+        }
+    }
+}
+
+/// Wrap an evaluated operand for the arrow kernels
+fn datum(value: &Value, array: ArrayRef) -> Box<dyn arrow::array::Datum> {
+    match value {
+        Value::Array(_) => Box::new(array),
+        Value::Scalar(_) => Box::new(Scalar::new(array)),
+    }
+}
+
+/// Type both sides of a comparison are cast to
+fn comparison_type(left: &DataType, right: &DataType) -> DataType {
+    let is_string = |t: &DataType| match t {
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => true,
+        DataType::Dictionary(_, value) => matches!(value.as_ref(), DataType::Utf8 | DataType::LargeUtf8),
+        _ => false,
+    };
+    if *left == DataType::Date32 || *right == DataType::Date32 {
+        DataType::Date32
+    } else if is_string(left) && is_string(right) {
+        DataType::Utf8
+    } else {
+        DataType::Float64
+    }
+}
+
+fn as_boolean(array: &ArrayRef) -> Result<&BooleanArray, GooseError> {
+    array
+        .as_any()
+        .downcast_ref::<BooleanArray>()
+        .ok_or_else(|| GooseError::type_mismatch("expression", "Boolean", array.data_type()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::parser::parse_expr;
+
+    #[test]
+    fn test_evaluate() {
+        let batch = RecordBatch::try_from_iter(vec![
+            ("a", Arc::new(Float64Array::from(vec![1.0, 2.0, 3.0])) as ArrayRef),
+            ("d", Arc::new(Date32Array::from(vec![10470, 10471, 10472])) as ArrayRef),
+        ])
+        .unwrap();
+
+        let sum = evaluate(&parse_expr("a * (1 - 0.5) + 1").unwrap(), &batch).unwrap();
+        assert_eq!(sum.as_any().downcast_ref::<Float64Array>().unwrap().values().to_vec(), vec![1.5, 2.0, 2.5]);
+
+        let mask = evaluate(&parse_expr("d <= '1998-09-02' and not a = 1").unwrap(), &batch).unwrap();
+        let mask = mask.as_any().downcast_ref::<BooleanArray>().unwrap();
+        assert_eq!(mask.iter().collect::<Vec<_>>(), vec![Some(false), Some(true), Some(false)]);
+
+        // Constants broadcast to the batch length
+        assert_eq!(evaluate(&parse_expr("1 + 1").unwrap(), &batch).unwrap().len(), 3);
+    }
+}
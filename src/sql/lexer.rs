@@ -0,0 +1,172 @@
+//! SQL tokenizer
+
+use crate::error::GooseError;
+
+/// A lexical token
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    /// Identifier or keyword, lowercased unless it was double-quoted
+    Ident(String),
+    /// Double-quoted identifier (never a keyword)
+    QuotedIdent(String),
+    Number(f64),
+    /// Single-quoted string literal
+    String(String),
+    Comma,
+    LParen,
+    RParen,
+    Star,
+    Plus,
+    Minus,
+    Slash,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    Semicolon,
+}
+
+impl Token {
+    /// Whether this token is the given keyword (case-insensitive)
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Token::Ident(s) if s.eq_ignore_ascii_case(keyword))
+    }
+}
+
+/// Split SQL text into tokens
+pub fn tokenize(sql: &str) -> Result<Vec<Token>, GooseError> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        // Operators and punctuation
+        let symbol = match (c, next) {
+            ('<', Some('=')) => Some((Token::LtEq, 2)),
+            ('<', Some('>')) | ('!', Some('=')) => Some((Token::NotEq, 2)),
+            ('>', Some('=')) => Some((Token::GtEq, 2)),
+            ('<', _) => Some((Token::Lt, 1)),
+            ('>', _) => Some((Token::Gt, 1)),
+            ('=', _) => Some((Token::Eq, 1)),
+            (',', _) => Some((Token::Comma, 1)),
+            ('(', _) => Some((Token::LParen, 1)),
+            (')', _) => Some((Token::RParen, 1)),
+            ('*', _) => Some((Token::Star, 1)),
+            ('+', _) => Some((Token::Plus, 1)),
+            ('-', n) if n != Some('-') => Some((Token::Minus, 1)),
+            ('/', _) => Some((Token::Slash, 1)),
+            (';', _) => Some((Token::Semicolon, 1)),
+            _ => None,
+        };
+        if let Some((token, len)) = symbol {
+            tokens.push(token);
+            i += len;
+            continue;
+        }
+
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '-' => {
+                // Line comment
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '\'' => {
+                // '' inside a string is an escaped quote
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(GooseError::Sql("unterminated string literal".to_string())),
+                        Some('\'') if chars.get(i + 1) == Some(&'\'') => {
+                            value.push('\'');
+                            i += 2;
+                        }
+                        Some('\'') => {
+                            i += 1;
+                            break;
+                        }
+                        Some(&ch) => {
+                            value.push(ch);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(Token::String(value));
+            }
+            '"' => {
+                let start = i + 1;
+                let end = chars[start..]
+                    .iter()
+                    .position(|&ch| ch == '"')
+                    .ok_or_else(|| GooseError::Sql("unterminated quoted identifier".to_string()))?;
+                tokens.push(Token::QuotedIdent(chars[start..start + end].iter().collect()));
+                i = start + end + 1;
+            }
+            c if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit())) => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let value = text.parse::<f64>().map_err(|_| GooseError::Sql(format!("invalid number '{}'", text)))?;
+                tokens.push(Token::Number(value));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                tokens.push(Token::Ident(text.to_lowercase()));
+            }
+            other => return Err(GooseError::Sql(format!("unexpected character '{}'", other))),
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("SELECT sum(l_tax) AS \"Tax\" -- total\nFROM t WHERE d <= '1998-09-02' AND x <> 1.5;").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Ident("select".into()),
+                Token::Ident("sum".into()),
+                Token::LParen,
+                Token::Ident("l_tax".into()),
+                Token::RParen,
+                Token::Ident("as".into()),
+                Token::QuotedIdent("Tax".into()),
+                Token::Ident("from".into()),
+                Token::Ident("t".into()),
+                Token::Ident("where".into()),
+                Token::Ident("d".into()),
+                Token::LtEq,
+                Token::String("1998-09-02".into()),
+                Token::Ident("and".into()),
+                Token::Ident("x".into()),
+                Token::NotEq,
+                Token::Number(1.5),
+                Token::Semicolon,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        assert!(tokenize("select 'abc").is_err());
+    }
+}
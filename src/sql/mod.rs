@@ -0,0 +1,388 @@
+//! SQL frontend for ad-hoc Q1-style queries
+//!
+//! Supports a single-table `SELECT` with `WHERE`, `GROUP BY`, `ORDER BY`,
+//! `LIMIT`, arithmetic, comparisons and the aggregates `SUM`, `AVG` and
+//! `COUNT` over a Parquet file. Queries are parsed (`parser`), resolved
+//! against the file schema (`binder`), turned into a `plan::LogicalPlan`
+//! and lowered onto the existing reader → filter → aggregator pipeline:
+//!
+//! - an `l_shipdate <= DATE` conjunct becomes the reader's row group
+//!   pruning cutoff and the `create_date_filter_mask` filter,
+//! - Q1-shaped queries (grouped by `l_returnflag, l_linestatus` with Q1's
+//!   aggregates) run through `execute_q1_group_by` and its perfect hash,
+//! - everything else is aggregated by `HashAggregator`.
+//!
+//! Arithmetic is evaluated in `Float64`, like the default Q1 path.
+
+pub mod binder;
+pub(crate) mod eval;
+pub mod lexer;
+pub mod parser;
+
+use std::collections::HashMap;
+
+use arrow::array::RecordBatch;
+
+use crate::params::Q1Params;
+use crate::plan::{LogicalPlan, PhysicalPlan};
+use crate::reader::plan_scan;
+use crate::error::GooseError;
+use parser::TableRef;
+
+/// Table names usable in `FROM`, mapped to Parquet file paths
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    tables: HashMap<String, String>,
+}
+
+impl Catalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `path` under `name` (case-insensitive)
+    pub fn register(&mut self, name: &str, path: &str) {
+        self.tables.insert(name.to_lowercase(), path.to_string());
+    }
+
+    /// Path registered for `name`
+    pub fn path(&self, name: &str) -> Option<&str> {
+        self.tables.get(&name.to_lowercase()).map(String::as_str)
+    }
+}
+
+/// TPC-H Q1 over the `lineitem` table with the cutoff from `params`
+pub fn tpch_q1_sql(params: &Q1Params) -> String {
+    format!(
+        "SELECT l_returnflag, l_linestatus, \
+         sum(l_quantity) AS sum_qty, \
+         sum(l_extendedprice) AS sum_base_price, \
+         sum(l_extendedprice * (1 - l_discount)) AS sum_disc_price, \
+         sum(l_extendedprice * (1 - l_discount) * (1 + l_tax)) AS sum_charge, \
+         avg(l_quantity) AS avg_qty, \
+         avg(l_extendedprice) AS avg_price, \
+         avg(l_discount) AS avg_disc, \
+         count(*) AS count_order \
+         FROM lineitem \
+         WHERE l_shipdate <= DATE '{}' \
+         GROUP BY l_returnflag, l_linestatus \
+         ORDER BY l_returnflag, l_linestatus",
+        parser::format_date(params.cutoff_days())
+    )
+}
+
+/// Parse and bind a query against the catalog
+pub fn plan_sql(sql: &str, catalog: &Catalog) -> Result<binder::BoundQuery, GooseError> {
+    let query = parser::parse(sql)?;
+    let path = match &query.from {
+        TableRef::Named(name) => catalog.path(name).ok_or_else(|| GooseError::Sql(format!("Table {} not found", name)))?,
+        TableRef::Path(path) => path.as_str(),
+    };
+
+    // Only the footer is read here; the scan is planned again once the
+    // projection is known
+    let schema = plan_scan(path, &Q1Params::default(), &[])?.schema().clone();
+    binder::bind(&query, path, schema)
+}
+
+/// Parse, bind and plan a query down to its physical operators
+pub fn physical_plan(sql: &str, catalog: &Catalog) -> Result<PhysicalPlan, GooseError> {
+    LogicalPlan::from_query(&plan_sql(sql, catalog)?).create_physical_plan()
+}
+
+/// Execute a single SELECT statement and return the result as one batch
+///
+/// `FROM` names a table registered in `catalog` or a quoted Parquet path.
+pub fn execute_sql(sql: &str, catalog: &Catalog) -> Result<RecordBatch, GooseError> {
+    physical_plan(sql, catalog)?.execute()
+}
+
+/// Operator tree `execute_sql` would run, as printed by `PhysicalPlan::explain`
+pub fn explain_sql(sql: &str, catalog: &Catalog) -> Result<String, GooseError> {
+    Ok(physical_plan(sql, catalog)?.explain())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregator::record_batch_to_results;
+    use crate::query::execute_tpch_q1;
+    use crate::test_util::{sample_rows, temp_path, write_lineitem};
+    use arrow::array::{Array, AsArray, Float64Array, StringArray, UInt64Array};
+    use arrow::datatypes::{DataType, Date32Type, Float64Type, UInt64Type};
+
+    /// Q1 as written in README.md
+    const Q1_SQL: &str = "
+        SELECT
+            l_returnflag,
+            l_linestatus,
+            sum(l_quantity) AS sum_qty,
+            sum(l_extendedprice) AS sum_base_price,
+            sum(l_extendedprice * (1 - l_discount)) AS sum_disc_price,
+            sum(l_extendedprice * (1 - l_discount) * (1 + l_tax)) AS sum_charge,
+            avg(l_quantity) AS avg_qty,
+            avg(l_extendedprice) AS avg_price,
+            avg(l_discount) AS avg_disc,
+            count(*) AS count_order
+        FROM
+            lineitem
+        WHERE
+            l_shipdate <= CAST('1998-09-02' AS date)
+        GROUP BY
+            l_returnflag,
+            l_linestatus
+        ORDER BY
+            l_returnflag,
+            l_linestatus;";
+
+    fn catalog(name: &str) -> Catalog {
+        let path = temp_path(name);
+        write_lineitem(&path, &sample_rows(5_000), 1_000);
+        let mut catalog = Catalog::new();
+        catalog.register("lineitem", path.to_str().unwrap());
+        catalog
+    }
+
+    #[test]
+    fn test_q1_sql_matches_execute_tpch_q1() {
+        let catalog = catalog("sql-q1");
+        let path = catalog.path("lineitem").unwrap().to_string();
+
+        let expected = execute_tpch_q1(&path, &Q1Params::default()).unwrap();
+        let out = execute_sql(Q1_SQL, &catalog).unwrap();
+        assert_eq!(out.schema().field(2).name(), "sum_qty");
+        assert_eq!(format!("{:?}", record_batch_to_results(&out).unwrap()), format!("{:?}", expected));
+        let generated = execute_sql(&tpch_q1_sql(&Q1Params::default()), &catalog).unwrap();
+        assert_eq!(generated, out);
+
+        // Same answer through the hash aggregator, forced by a no-op predicate
+        let general = execute_sql(&Q1_SQL.replace("WHERE", "WHERE l_quantity >= 0 AND"), &catalog).unwrap();
+        let general = record_batch_to_results(&general).unwrap();
+        assert_eq!(general.len(), expected.len());
+        for (g, e) in general.iter().zip(&expected) {
+            assert_eq!((g.returnflag, g.linestatus, g.count), (e.returnflag, e.linestatus, e.count));
+            assert!((g.sum_charge.unwrap() - e.sum_charge.unwrap()).abs() < 1e-6 * e.sum_charge.unwrap().abs());
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_ad_hoc_query() {
+        let catalog = catalog("sql-adhoc");
+        let path = catalog.path("lineitem").unwrap().to_string();
+
+        let out = execute_sql(
+            "SELECT l_returnflag AS flag, count(*) AS n, sum(l_quantity) / count(*) AS mean_qty \
+             FROM lineitem WHERE l_shipdate < date '1995-01-01' AND l_linestatus = 'F' \
+             GROUP BY l_returnflag ORDER BY flag DESC LIMIT 2",
+            &catalog,
+        )
+        .unwrap();
+
+        let rows = sample_rows(5_000);
+        let cutoff = parser::parse_date("1995-01-01").unwrap();
+        assert_eq!(out.num_rows(), 2);
+        let flags = out.column(0).as_any().downcast_ref::<StringArray>().unwrap();
+        let counts = out.column(1).as_any().downcast_ref::<UInt64Array>().unwrap();
+        let means = out.column(2).as_any().downcast_ref::<Float64Array>().unwrap();
+        for (i, flag) in ["R", "N"].iter().enumerate() {
+            let selected: Vec<_> = rows
+                .iter()
+                .filter(|r| r.shipdate < cutoff && r.linestatus == "F" && r.returnflag == *flag)
+                .collect();
+            let total: f64 = selected.iter().map(|r| r.quantity as f64 / 100.0).sum();
+            assert_eq!(flags.value(i), *flag);
+            assert_eq!(counts.value(i), selected.len() as u64);
+            assert!((means.value(i) - total / selected.len() as f64).abs() < 1e-9);
+        }
+
+        // Plain projection from a quoted path
+        let sql = format!("SELECT l_quantity * 2 AS q2 FROM '{}' WHERE l_shipdate <= '1992-01-05' LIMIT 3", path);
+        let out = execute_sql(&sql, &Catalog::new()).unwrap();
+        assert_eq!(out.num_rows(), 3);
+        assert_eq!(out.column(0).as_any().downcast_ref::<Float64Array>().unwrap().value(0), rows[0].quantity as f64 / 50.0);
+
+        let out = execute_sql("SELECT count(*) FROM lineitem WHERE l_shipdate < '1900-01-01'", &catalog).unwrap();
+        assert_eq!(out.num_rows(), 1);
+        assert_eq!(out.column(0).as_any().downcast_ref::<UInt64Array>().unwrap().value(0), 0);
+
+        assert!(execute_sql("SELECT count(*) FROM orders", &catalog).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_count_distinct_matches_q1_distinct_orders() {
+        use crate::distinct::DistinctMode;
+        use crate::query::{execute_tpch_q1_with_options, ExecutionOptions};
+
+        let catalog = catalog("sql-distinct");
+        let path = catalog.path("lineitem").unwrap().to_string();
+
+        let out = execute_sql(
+            "SELECT l_returnflag, l_linestatus, count(DISTINCT l_orderkey) AS orders, approx_count_distinct(l_orderkey) \
+             FROM lineitem WHERE l_shipdate <= date '1998-09-02' \
+             GROUP BY l_returnflag, l_linestatus ORDER BY l_returnflag, l_linestatus",
+            &catalog,
+        )
+        .unwrap();
+        let exact = out.column(2).as_any().downcast_ref::<UInt64Array>().unwrap();
+        let approx = out.column(3).as_any().downcast_ref::<UInt64Array>().unwrap();
+
+        // Against the rows, and against the perfect-hash path on 4 threads
+        let rows = sample_rows(5_000);
+        let options = ExecutionOptions { num_threads: 4, distinct_orders: Some(DistinctMode::Exact), ..ExecutionOptions::default() };
+        let results = execute_tpch_q1_with_options(&path, &Q1Params::default(), &options).unwrap();
+        assert_eq!(out.num_rows(), results.len());
+        for (row, result) in results.iter().enumerate() {
+            let mut orders: Vec<i64> = rows
+                .iter()
+                .filter(|r| {
+                    r.shipdate <= Q1Params::default().cutoff_days()
+                        && Some(r.returnflag.as_bytes()[0]) == result.returnflag
+                        && Some(r.linestatus.as_bytes()[0]) == result.linestatus
+                })
+                .map(|r| r.orderkey)
+                .collect();
+            orders.sort_unstable();
+            orders.dedup();
+            assert_eq!(result.distinct, Some(orders.len() as u64));
+            assert_eq!(exact.value(row), orders.len() as u64);
+            assert!(approx.value(row).abs_diff(orders.len() as u64) as f64 <= 0.05 * orders.len() as f64);
+        }
+
+        // Strings count as they are
+        let out = execute_sql(
+            "SELECT l_returnflag, count(DISTINCT l_linestatus) FROM lineitem GROUP BY l_returnflag ORDER BY l_returnflag",
+            &catalog,
+        )
+        .unwrap();
+        let flags = out.column(0).as_string::<i32>();
+        let statuses = out.column(1).as_primitive::<UInt64Type>();
+        for row in 0..out.num_rows() {
+            let mut expected: Vec<&str> = rows.iter().filter(|r| r.returnflag == flags.value(row)).map(|r| r.linestatus).collect();
+            expected.sort_unstable();
+            expected.dedup();
+            assert_eq!(statuses.value(row), expected.len() as u64);
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_quantiles_match_sorted_prices() {
+        use crate::quantile::{quantile_of_sorted, QuantileMode};
+        use crate::query::{execute_tpch_q1_with_options, ExecutionOptions};
+
+        let catalog = catalog("sql-quantiles");
+        let path = catalog.path("lineitem").unwrap().to_string();
+
+        let out = execute_sql(
+            "SELECT l_returnflag, l_linestatus, median(l_extendedprice), quantile_cont(l_extendedprice, 0.95), \
+             approx_quantile(l_extendedprice, 0.99) \
+             FROM lineitem WHERE l_shipdate <= date '1998-09-02' \
+             GROUP BY l_returnflag, l_linestatus ORDER BY l_returnflag, l_linestatus",
+            &catalog,
+        )
+        .unwrap();
+        let column = |i: usize| out.column(i).as_any().downcast_ref::<Float64Array>().unwrap().clone();
+        let (median, p95, p99) = (column(2), column(3), column(4));
+
+        // Against the sorted rows, and against the perfect-hash path on 4 threads
+        let rows = sample_rows(5_000);
+        let options = ExecutionOptions { num_threads: 4, price_quantiles: Some(QuantileMode::Exact), ..ExecutionOptions::default() };
+        let results = execute_tpch_q1_with_options(&path, &Q1Params::default(), &options).unwrap();
+        assert_eq!(out.num_rows(), results.len());
+        for (row, result) in results.iter().enumerate() {
+            let mut prices: Vec<f64> = rows
+                .iter()
+                .filter(|r| {
+                    r.shipdate <= Q1Params::default().cutoff_days()
+                        && Some(r.returnflag.as_bytes()[0]) == result.returnflag
+                        && Some(r.linestatus.as_bytes()[0]) == result.linestatus
+                })
+                .map(|r| r.extendedprice as f64 / 100.0)
+                .collect();
+            prices.sort_unstable_by(f64::total_cmp);
+            let sketch = result.price_quantiles.as_ref().unwrap();
+            for (q, sql) in [(0.5, median.value(row)), (0.95, p95.value(row))] {
+                let expected = quantile_of_sorted(&prices, q).unwrap();
+                assert_eq!(sketch.quantile(q), Some(expected));
+                assert!((sql - expected).abs() < 1e-9 * expected);
+            }
+            // The digest is within a fraction of a percentile of the true rank
+            let rank = prices.partition_point(|&p| p < p99.value(row)) as f64 / prices.len() as f64;
+            assert!((rank - 0.99).abs() < 0.005, "p99 {} at rank {}", p99.value(row), rank);
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_dispersion_matches_q1_moments() {
+        use crate::query::{execute_tpch_q1_with_options, ExecutionOptions};
+
+        let catalog = catalog("sql-moments");
+        let path = catalog.path("lineitem").unwrap().to_string();
+
+        let out = execute_sql(
+            "SELECT l_returnflag, l_linestatus, min(l_extendedprice), max(l_extendedprice), \
+             variance(l_discount), stddev_pop(l_quantity) \
+             FROM lineitem WHERE l_shipdate <= date '1998-09-02' \
+             GROUP BY l_returnflag, l_linestatus ORDER BY l_returnflag, l_linestatus",
+            &catalog,
+        )
+        .unwrap();
+
+        // The perfect-hash path merges per-thread moments to the same values
+        let options = ExecutionOptions { num_threads: 4, moments: true, ..ExecutionOptions::default() };
+        let results = execute_tpch_q1_with_options(&path, &Q1Params::default(), &options).unwrap();
+        assert_eq!(out.num_rows(), results.len());
+        // MIN and MAX keep the decimal input type
+        assert!(matches!(out.schema().field(2).data_type(), DataType::Decimal128(..)));
+        let column = |i: usize| arrow::compute::cast(out.column(i), &DataType::Float64).unwrap().as_primitive::<Float64Type>().clone();
+        for (row, result) in results.iter().enumerate() {
+            let moments = result.moments.unwrap();
+            assert_eq!(column(2).value(row), moments.price.min().unwrap());
+            assert_eq!(column(3).value(row), moments.price.max().unwrap());
+            let close = |a: f64, b: f64| (a - b).abs() <= 1e-9 * b.abs();
+            assert!(close(column(4).value(row), moments.discount.var_samp().unwrap()));
+            assert!(close(column(5).value(row), moments.quantity.stddev_pop().unwrap()));
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_count_min_max_over_any_column() {
+        let catalog = catalog("sql-any-type");
+        let path = catalog.path("lineitem").unwrap().to_string();
+
+        let out = execute_sql(
+            "SELECT l_returnflag, count(l_linestatus), min(l_linestatus), max(l_shipdate) \
+             FROM lineitem GROUP BY l_returnflag ORDER BY l_returnflag",
+            &catalog,
+        )
+        .unwrap();
+        assert_eq!(out.schema().field(2).data_type(), &DataType::Utf8);
+        assert_eq!(out.schema().field(3).data_type(), &DataType::Date32);
+
+        let rows = sample_rows(5_000);
+        let flags = out.column(0).as_string::<i32>();
+        let counts = out.column(1).as_primitive::<UInt64Type>();
+        let min_status = out.column(2).as_string::<i32>();
+        let max_date = out.column(3).as_primitive::<Date32Type>();
+        for row in 0..out.num_rows() {
+            let selected: Vec<_> = rows.iter().filter(|r| r.returnflag == flags.value(row)).collect();
+            assert_eq!(counts.value(row), selected.len() as u64);
+            assert_eq!(min_status.value(row), selected.iter().map(|r| r.linestatus).min().unwrap());
+            assert_eq!(max_date.value(row), selected.iter().map(|r| r.shipdate).max().unwrap());
+        }
+
+        // Arithmetic aggregates are refused at bind time
+        for sql in ["SELECT sum(l_returnflag) FROM lineitem", "SELECT avg(l_shipdate) FROM lineitem"] {
+            assert!(matches!(execute_sql(sql, &catalog), Err(GooseError::Sql(_))), "{}", sql);
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
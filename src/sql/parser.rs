@@ -0,0 +1,646 @@
+//! Recursive-descent parser for the supported SELECT subset
+//!
+//! ```text
+//! query  := SELECT item (, item)* FROM table [WHERE expr]
+//!           [GROUP BY expr (, expr)*] [ORDER BY expr [ASC|DESC] (, ...)*]
+//!           [LIMIT n] [;]
+//! expr   := or ; or := and (OR and)* ; and := not (AND not)*
+//! not    := NOT not | cmp ; cmp := add [(= | <> | < | <= | > | >=) add]
+//! add    := mul ((+ | -) mul)* ; mul := unary ((* | /) unary)*
+//! unary  := - unary | primary
+//! primary:= number | 'string' | DATE 'yyyy-mm-dd' | INTERVAL 'n' DAY
+//!         | CAST(expr AS type) | name(args) | name(*) | column | (expr)
+//! ```
+
+use std::fmt;
+
+use arrow::datatypes::DataType;
+
+use super::lexer::{tokenize, Token};
+use crate::error::GooseError;
+
+/// Literal value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Number(f64),
+    String(String),
+    /// Days since epoch
+    Date(i32),
+    /// `INTERVAL 'n' DAY`
+    IntervalDays(i32),
+}
+
+/// Binary operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(self, BinaryOp::Plus | BinaryOp::Minus | BinaryOp::Multiply | BinaryOp::Divide)
+    }
+
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Eq | BinaryOp::NotEq | BinaryOp::Lt | BinaryOp::LtEq | BinaryOp::Gt | BinaryOp::GtEq
+        )
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Plus => "+",
+            BinaryOp::Minus => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Eq => "=",
+            BinaryOp::NotEq => "<>",
+            BinaryOp::Lt => "<",
+            BinaryOp::LtEq => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::GtEq => ">=",
+            BinaryOp::And => "AND",
+            BinaryOp::Or => "OR",
+        }
+    }
+}
+
+/// Unary operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+/// Scalar or aggregate expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Column(String),
+    Literal(Literal),
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Unary {
+        op: UnaryOp,
+        expr: Box<Expr>,
+    },
+    /// Function call; `args` is empty for `name(*)`
+    Function {
+        name: String,
+        args: Vec<Expr>,
+        star: bool,
+        /// `name(DISTINCT args)`
+        distinct: bool,
+    },
+    Cast {
+        expr: Box<Expr>,
+        data_type: DataType,
+    },
+}
+
+impl Expr {
+    pub fn binary(op: BinaryOp, left: Expr, right: Expr) -> Self {
+        Expr::Binary {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    /// Visit every column name referenced by the expression
+    pub fn columns(&self, out: &mut Vec<String>) {
+        match self {
+            Expr::Column(name) => {
+                if !out.contains(name) {
+                    out.push(name.clone());
+                }
+            }
+            Expr::Literal(_) => {}
+            Expr::Binary { left, right, .. } => {
+                left.columns(out);
+                right.columns(out);
+            }
+            Expr::Unary { expr, .. } | Expr::Cast { expr, .. } => expr.columns(out),
+            Expr::Function { args, .. } => args.iter().for_each(|a| a.columns(out)),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Column(name) => write!(f, "{}", name),
+            Expr::Literal(Literal::Number(n)) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            Expr::Literal(Literal::Number(n)) => write!(f, "{}", n),
+            Expr::Literal(Literal::String(s)) => write!(f, "'{}'", s.replace('\'', "''")),
+            Expr::Literal(Literal::Date(days)) => write!(f, "DATE '{}'", format_date(*days)),
+            Expr::Literal(Literal::IntervalDays(days)) => write!(f, "INTERVAL '{}' DAY", days),
+            Expr::Binary { op, left, right } => write!(f, "({} {} {})", left, op.symbol(), right),
+            Expr::Unary { op: UnaryOp::Neg, expr } => write!(f, "(- {})", expr),
+            Expr::Unary { op: UnaryOp::Not, expr } => write!(f, "(NOT {})", expr),
+            Expr::Function { name, star: true, .. } => write!(f, "{}(*)", name),
+            Expr::Function { name, args, distinct, .. } => {
+                let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+                let distinct = if *distinct { "DISTINCT " } else { "" };
+                write!(f, "{}({}{})", name, distinct, args.join(", "))
+            }
+            Expr::Cast { expr, data_type } => write!(f, "CAST({} AS {})", expr, data_type),
+        }
+    }
+}
+
+/// Format days since epoch as yyyy-mm-dd (proleptic Gregorian)
+pub fn format_date(days: i32) -> String {
+    // Howard Hinnant's civil_from_days
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Parse yyyy-mm-dd into days since epoch
+pub fn parse_date(text: &str) -> Result<i32, GooseError> {
+    let invalid = || GooseError::Sql(format!("invalid date '{}'", text));
+    let mut parts = text.trim().splitn(3, '-');
+    let mut next = || -> Result<i64, GooseError> { parts.next().and_then(|p| p.parse().ok()).ok_or_else(invalid) };
+    let (year, month, day) = (next()?, next()?, next()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid());
+    }
+
+    // Howard Hinnant's days_from_civil
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    if format_date(days as i32) != format!("{:04}-{:02}-{:02}", year, month, day) {
+        return Err(invalid());
+    }
+    Ok(days as i32)
+}
+
+/// An item of the SELECT list
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectItem {
+    Wildcard,
+    Expr { expr: Expr, alias: Option<String> },
+}
+
+/// An ORDER BY item
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderByItem {
+    pub expr: Expr,
+    pub descending: bool,
+}
+
+/// FROM target
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableRef {
+    /// Name registered in the catalog
+    Named(String),
+    /// `FROM 'path/to/file.parquet'`
+    Path(String),
+}
+
+/// A parsed SELECT statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub select: Vec<SelectItem>,
+    pub from: TableRef,
+    pub selection: Option<Expr>,
+    pub group_by: Vec<Expr>,
+    pub order_by: Vec<OrderByItem>,
+    pub limit: Option<usize>,
+}
+
+/// Words that end an expression or alias position
+const RESERVED: &[&str] = &[
+    "select", "from", "where", "group", "by", "order", "limit", "and", "or", "not", "as", "asc", "desc", "distinct",
+];
+
+/// Parse a single SELECT statement
+pub fn parse(sql: &str) -> Result<Query, GooseError> {
+    let mut parser = Parser { tokens: tokenize(sql)?, pos: 0 };
+    let query = parser.parse_query()?;
+    parser.consume(&Token::Semicolon);
+    if let Some(token) = parser.peek() {
+        return Err(GooseError::Sql(format!("unexpected {:?} after end of query", token)));
+    }
+    Ok(query)
+}
+
+/// Parse a standalone expression (used to build reference expressions)
+pub fn parse_expr(sql: &str) -> Result<Expr, GooseError> {
+    let mut parser = Parser { tokens: tokenize(sql)?, pos: 0 };
+    let expr = parser.parse_expr()?;
+    if let Some(token) = parser.peek() {
+        return Err(GooseError::Sql(format!("unexpected {:?} after expression", token)));
+    }
+    Ok(expr)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn consume(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn consume_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek().is_some_and(|t| t.is_keyword(keyword)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), GooseError> {
+        if self.consume(token) {
+            Ok(())
+        } else {
+            Err(GooseError::Sql(format!("expected {:?}, found {:?}", token, self.peek())))
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), GooseError> {
+        if self.consume_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(GooseError::Sql(format!("expected {}, found {:?}", keyword.to_uppercase(), self.peek())))
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, GooseError> {
+        match self.next() {
+            Some(Token::Ident(name)) if !RESERVED.contains(&name.as_str()) => Ok(name),
+            Some(Token::QuotedIdent(name)) => Ok(name),
+            other => Err(GooseError::Sql(format!("expected identifier, found {:?}", other))),
+        }
+    }
+
+    fn parse_query(&mut self) -> Result<Query, GooseError> {
+        self.expect_keyword("select")?;
+
+        let mut select = Vec::new();
+        loop {
+            if self.consume(&Token::Star) {
+                select.push(SelectItem::Wildcard);
+            } else {
+                let expr = self.parse_expr()?;
+                // AS is optional before an alias
+                let has_alias = self.consume_keyword("as")
+                    || matches!(self.peek(), Some(Token::Ident(name)) if !RESERVED.contains(&name.as_str()))
+                    || matches!(self.peek(), Some(Token::QuotedIdent(_)));
+                let alias = if has_alias { Some(self.identifier()?) } else { None };
+                select.push(SelectItem::Expr { expr, alias });
+            }
+            if !self.consume(&Token::Comma) {
+                break;
+            }
+        }
+
+        self.expect_keyword("from")?;
+        let from = match self.peek() {
+            Some(Token::String(path)) => {
+                let path = path.clone();
+                self.pos += 1;
+                TableRef::Path(path)
+            }
+            _ => TableRef::Named(self.identifier()?),
+        };
+
+        let selection = if self.consume_keyword("where") {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+
+        let mut group_by = Vec::new();
+        if self.consume_keyword("group") {
+            self.expect_keyword("by")?;
+            loop {
+                group_by.push(self.parse_expr()?);
+                if !self.consume(&Token::Comma) {
+                    break;
+                }
+            }
+        }
+
+        let mut order_by = Vec::new();
+        if self.consume_keyword("order") {
+            self.expect_keyword("by")?;
+            loop {
+                let expr = self.parse_expr()?;
+                let descending = if self.consume_keyword("desc") {
+                    true
+                } else {
+                    self.consume_keyword("asc");
+                    false
+                };
+                order_by.push(OrderByItem { expr, descending });
+                if !self.consume(&Token::Comma) {
+                    break;
+                }
+            }
+        }
+
+        let limit = if self.consume_keyword("limit") {
+            match self.next() {
+                Some(Token::Number(n)) if n >= 0.0 && n.fract() == 0.0 => Some(n as usize),
+                other => return Err(GooseError::Sql(format!("expected row count after LIMIT, found {:?}", other))),
+            }
+        } else {
+            None
+        };
+
+        Ok(Query {
+            select,
+            from,
+            selection,
+            group_by,
+            order_by,
+            limit,
+        })
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, GooseError> {
+        let mut left = self.parse_and()?;
+        while self.consume_keyword("or") {
+            left = Expr::binary(BinaryOp::Or, left, self.parse_and()?);
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, GooseError> {
+        let mut left = self.parse_not()?;
+        while self.consume_keyword("and") {
+            left = Expr::binary(BinaryOp::And, left, self.parse_not()?);
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, GooseError> {
+        if self.consume_keyword("not") {
+            return Ok(Expr::Unary {
+                op: UnaryOp::Not,
+                expr: Box::new(self.parse_not()?),
+            });
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, GooseError> {
+        let left = self.parse_additive()?;
+        let op = match self.peek() {
+            Some(Token::Eq) => BinaryOp::Eq,
+            Some(Token::NotEq) => BinaryOp::NotEq,
+            Some(Token::Lt) => BinaryOp::Lt,
+            Some(Token::LtEq) => BinaryOp::LtEq,
+            Some(Token::Gt) => BinaryOp::Gt,
+            Some(Token::GtEq) => BinaryOp::GtEq,
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        Ok(Expr::binary(op, left, self.parse_additive()?))
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr, GooseError> {
+        let mut left = self.parse_multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => BinaryOp::Plus,
+                Some(Token::Minus) => BinaryOp::Minus,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            left = Expr::binary(op, left, self.parse_multiplicative()?);
+        }
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr, GooseError> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Star) => BinaryOp::Multiply,
+                Some(Token::Slash) => BinaryOp::Divide,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            left = Expr::binary(op, left, self.parse_unary()?);
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, GooseError> {
+        if self.consume(&Token::Minus) {
+            return Ok(match self.parse_unary()? {
+                Expr::Literal(Literal::Number(n)) => Expr::Literal(Literal::Number(-n)),
+                expr => Expr::Unary {
+                    op: UnaryOp::Neg,
+                    expr: Box::new(expr),
+                },
+            });
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, GooseError> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Literal(Literal::Number(n))),
+            Some(Token::String(s)) => Ok(Expr::Literal(Literal::String(s))),
+            Some(Token::LParen) => {
+                let expr = self.parse_expr()?;
+                self.expect(&Token::RParen)?;
+                Ok(expr)
+            }
+            Some(Token::QuotedIdent(name)) => Ok(Expr::Column(name)),
+            Some(Token::Ident(word)) => match word.as_str() {
+                "date" if matches!(self.peek(), Some(Token::String(_))) => {
+                    let Some(Token::String(text)) = self.next() else { unreachable!() };
+                    Ok(Expr::Literal(Literal::Date(parse_date(&text)?)))
+                }
+                "interval" => self.parse_interval(),
+                "cast" => {
+                    self.expect(&Token::LParen)?;
+                    let expr = self.parse_expr()?;
+                    self.expect_keyword("as")?;
+                    let data_type = self.parse_type()?;
+                    self.expect(&Token::RParen)?;
+                    Ok(Expr::Cast {
+                        expr: Box::new(expr),
+                        data_type,
+                    })
+                }
+                _ if RESERVED.contains(&word.as_str()) => Err(GooseError::Sql(format!("unexpected keyword {}", word.to_uppercase()))),
+                _ if self.consume(&Token::LParen) => {
+                    if self.consume(&Token::Star) {
+                        self.expect(&Token::RParen)?;
+                        return Ok(Expr::Function { name: word, args: vec![], star: true, distinct: false });
+                    }
+                    let distinct = self.consume_keyword("distinct");
+                    let mut args = Vec::new();
+                    if !self.consume(&Token::RParen) {
+                        loop {
+                            args.push(self.parse_expr()?);
+                            if !self.consume(&Token::Comma) {
+                                break;
+                            }
+                        }
+                        self.expect(&Token::RParen)?;
+                    }
+                    Ok(Expr::Function { name: word, args, star: false, distinct })
+                }
+                _ => Ok(Expr::Column(word)),
+            },
+            other => Err(GooseError::Sql(format!("unexpected {:?} in expression", other))),
+        }
+    }
+
+    /// `INTERVAL 'n' DAY [(p)]`
+    fn parse_interval(&mut self) -> Result<Expr, GooseError> {
+        let days = match self.next() {
+            Some(Token::String(text)) => text.trim().parse::<i32>().map_err(|_| GooseError::Sql(format!("invalid interval '{}'", text)))?,
+            Some(Token::Number(n)) if n.fract() == 0.0 => n as i32,
+            other => return Err(GooseError::Sql(format!("expected interval length, found {:?}", other))),
+        };
+        if !(self.consume_keyword("day") || self.consume_keyword("days")) {
+            return Err(GooseError::Sql("only DAY intervals are supported".to_string()));
+        }
+        // Optional leading field precision, as written in the TPC-H spec
+        if self.consume(&Token::LParen) {
+            self.next();
+            self.expect(&Token::RParen)?;
+        }
+        Ok(Expr::Literal(Literal::IntervalDays(days)))
+    }
+
+    fn parse_type(&mut self) -> Result<DataType, GooseError> {
+        let name = self.identifier()?;
+        let data_type = match name.as_str() {
+            "date" => DataType::Date32,
+            "double" | "float" | "real" | "float8" => DataType::Float64,
+            "int" | "integer" | "bigint" | "int8" => DataType::Int64,
+            "varchar" | "text" | "string" | "char" => DataType::Utf8,
+            "decimal" | "numeric" => {
+                // Precision and scale are accepted but arithmetic runs in f64
+                if self.consume(&Token::LParen) {
+                    while !self.consume(&Token::RParen) {
+                        self.next().ok_or_else(|| GooseError::Sql("unterminated type arguments".to_string()))?;
+                    }
+                }
+                return Ok(DataType::Float64);
+            }
+            other => return Err(GooseError::Sql(format!("unsupported type {}", other))),
+        };
+        // Length / precision arguments are ignored
+        if self.consume(&Token::LParen) {
+            while !self.consume(&Token::RParen) {
+                self.next().ok_or_else(|| GooseError::Sql("unterminated type arguments".to_string()))?;
+            }
+        }
+        Ok(data_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(parse_date("1970-01-01").unwrap(), 0);
+        assert_eq!(parse_date("1998-09-02").unwrap(), crate::reader::FILTER_DATE_DAYS);
+        assert_eq!(format_date(crate::params::Q1_BASE_DATE_DAYS), "1998-12-01");
+        assert_eq!(format_date(-1), "1969-12-31");
+        assert!(parse_date("1998-02-30").is_err());
+        assert!(parse_date("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_parse_q1() {
+        let query = parse(
+            "SELECT l_returnflag, sum(l_extendedprice * (1 - l_discount)) AS sum_disc_price, count(*) \
+             FROM lineitem \
+             WHERE l_shipdate <= date '1998-12-01' - interval '90' day (3) \
+             GROUP BY l_returnflag ORDER BY l_returnflag DESC LIMIT 2;",
+        )
+        .unwrap();
+
+        assert_eq!(query.from, TableRef::Named("lineitem".into()));
+        assert_eq!(query.select.len(), 3);
+        let SelectItem::Expr { expr, alias } = &query.select[1] else { panic!() };
+        assert_eq!(alias.as_deref(), Some("sum_disc_price"));
+        assert_eq!(expr.to_string(), "sum((l_extendedprice * (1 - l_discount)))");
+        assert_eq!(
+            query.selection.unwrap().to_string(),
+            "(l_shipdate <= (DATE '1998-12-01' - INTERVAL '90' DAY))"
+        );
+        assert_eq!(query.group_by, vec![Expr::Column("l_returnflag".into())]);
+        assert!(query.order_by[0].descending);
+        assert_eq!(query.limit, Some(2));
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(parse_expr("1 + 2 * 3 - 4").unwrap().to_string(), "((1 + (2 * 3)) - 4)");
+        assert_eq!(
+            parse_expr("a = 1 or b < 2 and not c > 3").unwrap().to_string(),
+            "((a = 1) OR ((b < 2) AND (NOT (c > 3))))"
+        );
+    }
+
+    #[test]
+    fn test_distinct_call() {
+        assert_eq!(parse_expr("COUNT(DISTINCT l_orderkey)").unwrap().to_string(), "count(DISTINCT l_orderkey)");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("SELECT FROM t").is_err());
+        assert!(parse("SELECT a FROM t WHERE").is_err());
+        assert!(parse("SELECT a FROM t extra").is_err());
+        assert!(parse("SELECT a FROM t LIMIT -1").is_err());
+    }
+}
@@ -0,0 +1,309 @@
+//! Floating-point summation strategies: plain, compensated and pairwise
+//!
+//! Plain summation rounds after every add, so its result depends on the
+//! order of the values, and with it on how rows are split over the ILP
+//! accumulator sets and threads. `Kahan` carries the rounding error of each
+//! add along (Neumaier's variant, which also holds when an addend is larger
+//! than the running sum); `Pairwise` adds values in a balanced tree, so the
+//! error grows with `log n` instead of `n`. Every `SumState` merges.
+//!
+//! `Aggregator` is generic over `FloatSum`, so the strategy is the type of
+//! its accumulators: `f64` keeps the plain adds of the unrolled loop,
+//! `KahanSum` and `PairwiseSum` replace them. `SumState` picks one at run
+//! time, for `HashAggregator`.
+
+use std::fmt;
+
+/// How floating-point sums are accumulated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Summation {
+    /// One rounding per add; the fastest
+    #[default]
+    Plain,
+    /// Kahan-Babuška-Neumaier compensated summation
+    Kahan,
+    /// Cascade (pairwise) summation over blocks of `PAIRWISE_BLOCK` values
+    Pairwise,
+}
+
+/// Accumulator type of a running f64 sum under one `Summation`
+pub trait FloatSum: Clone + Default + fmt::Debug + Send + 'static {
+    /// Which strategy this is
+    const SUMMATION: Summation;
+
+    fn add(&mut self, value: f64);
+
+    /// Combine with the sum of another, disjoint set of values
+    fn merge(&mut self, other: &Self);
+
+    fn value(&self) -> f64;
+}
+
+impl FloatSum for f64 {
+    const SUMMATION: Summation = Summation::Plain;
+
+    #[inline(always)]
+    fn add(&mut self, value: f64) {
+        *self += value;
+    }
+
+    #[inline(always)]
+    fn merge(&mut self, other: &Self) {
+        *self += *other;
+    }
+
+    #[inline(always)]
+    fn value(&self) -> f64 {
+        *self
+    }
+}
+
+impl FloatSum for KahanSum {
+    const SUMMATION: Summation = Summation::Kahan;
+
+    #[inline(always)]
+    fn add(&mut self, value: f64) {
+        KahanSum::add(self, value)
+    }
+
+    fn merge(&mut self, other: &Self) {
+        KahanSum::merge(self, other)
+    }
+
+    fn value(&self) -> f64 {
+        KahanSum::value(self)
+    }
+}
+
+impl FloatSum for PairwiseSum {
+    const SUMMATION: Summation = Summation::Pairwise;
+
+    #[inline(always)]
+    fn add(&mut self, value: f64) {
+        PairwiseSum::add(self, value)
+    }
+
+    fn merge(&mut self, other: &Self) {
+        PairwiseSum::merge(self, other)
+    }
+
+    fn value(&self) -> f64 {
+        PairwiseSum::value(self)
+    }
+}
+
+/// A running sum under a `Summation` chosen at run time
+#[derive(Debug, Clone, PartialEq)]
+pub enum SumState {
+    Plain(f64),
+    Kahan(KahanSum),
+    Pairwise(PairwiseSum),
+}
+
+impl Default for SumState {
+    fn default() -> Self {
+        SumState::Plain(0.0)
+    }
+}
+
+impl SumState {
+    pub fn new(summation: Summation) -> Self {
+        match summation {
+            Summation::Plain => SumState::Plain(0.0),
+            Summation::Kahan => SumState::Kahan(KahanSum::default()),
+            Summation::Pairwise => SumState::Pairwise(PairwiseSum::default()),
+        }
+    }
+
+    #[inline]
+    pub fn add(&mut self, value: f64) {
+        match self {
+            SumState::Plain(sum) => *sum += value,
+            SumState::Kahan(sum) => sum.add(value),
+            SumState::Pairwise(sum) => sum.add(value),
+        }
+    }
+
+    /// Combine with the sum of another, disjoint set of values
+    ///
+    /// States of different kinds merge by adding the other's value.
+    pub fn merge(&mut self, other: &SumState) {
+        match (&mut *self, other) {
+            (SumState::Kahan(sum), SumState::Kahan(other)) => sum.merge(other),
+            (SumState::Pairwise(sum), SumState::Pairwise(other)) => sum.merge(other),
+            _ => self.add(other.value()),
+        }
+    }
+
+    pub fn value(&self) -> f64 {
+        match self {
+            SumState::Plain(sum) => *sum,
+            SumState::Kahan(sum) => sum.value(),
+            SumState::Pairwise(sum) => sum.value(),
+        }
+    }
+}
+
+/// Neumaier-compensated sum: `sum + compensation` is the running total
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct KahanSum {
+    sum: f64,
+    /// Rounding error lost from `sum` so far
+    compensation: f64,
+}
+
+impl KahanSum {
+    #[inline]
+    pub fn add(&mut self, value: f64) {
+        let total = self.sum + value;
+        // Recover the low-order bits of whichever operand was smaller
+        self.compensation += if self.sum.abs() >= value.abs() {
+            (self.sum - total) + value
+        } else {
+            (value - total) + self.sum
+        };
+        self.sum = total;
+    }
+
+    pub fn merge(&mut self, other: &KahanSum) {
+        self.add(other.sum);
+        self.compensation += other.compensation;
+    }
+
+    pub fn value(&self) -> f64 {
+        self.sum + self.compensation
+    }
+}
+
+/// Values summed plainly before their block joins the tree
+pub const PAIRWISE_BLOCK: u32 = 64;
+
+/// Streaming pairwise sum
+///
+/// Values are summed plainly in blocks of `PAIRWISE_BLOCK`. Finished blocks
+/// go into a binary counter: `partials[k]` holds the sum of `2^k` blocks
+/// while bit `k` of `blocks` is set, and pushing a block carries like
+/// binary addition, so only sums of equal weight are ever added together.
+/// Merging adds two counters the same way.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PairwiseSum {
+    partials: Vec<f64>,
+    blocks: u64,
+    /// The block being filled
+    block: f64,
+    block_len: u32,
+}
+
+impl PairwiseSum {
+    #[inline]
+    pub fn add(&mut self, value: f64) {
+        self.block += value;
+        self.block_len += 1;
+        if self.block_len >= PAIRWISE_BLOCK {
+            self.finish_block();
+        }
+    }
+
+    fn finish_block(&mut self) {
+        let block = std::mem::take(&mut self.block);
+        self.block_len = 0;
+        self.push(0, block);
+    }
+
+    /// Add the sum of `2^level` blocks to the counter
+    fn push(&mut self, level: usize, mut sum: f64) {
+        let mut carry = level;
+        while self.blocks >> carry & 1 == 1 {
+            sum += std::mem::take(&mut self.partials[carry]);
+            carry += 1;
+        }
+        if self.partials.len() <= carry {
+            self.partials.resize(carry + 1, 0.0);
+        }
+        self.partials[carry] = sum;
+        self.blocks += 1 << level;
+    }
+
+    pub fn merge(&mut self, other: &PairwiseSum) {
+        for (level, &partial) in other.partials.iter().enumerate() {
+            if other.blocks >> level & 1 == 1 {
+                self.push(level, partial);
+            }
+        }
+        // The partly filled blocks make one block, possibly a long one
+        self.block += other.block;
+        self.block_len += other.block_len;
+        if self.block_len >= PAIRWISE_BLOCK {
+            self.finish_block();
+        }
+    }
+
+    /// The total, adding the smallest partial sums first
+    pub fn value(&self) -> f64 {
+        self.partials.iter().fold(self.block, |total, &partial| total + partial)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `n` times `value`, rounded once
+    fn exact_multiple(value: f64, n: u64) -> f64 {
+        // 0.1 is 0x1999999999999A * 2^-56; the product fits an i128
+        let mantissa = (value * 2f64.powi(56)) as i128;
+        (mantissa * n as i128) as f64 / 2f64.powi(56)
+    }
+
+    fn sum(summation: Summation, values: impl IntoIterator<Item = f64>) -> SumState {
+        let mut state = SumState::new(summation);
+        values.into_iter().for_each(|v| state.add(v));
+        state
+    }
+
+    #[test]
+    fn test_accuracy() {
+        let n = 1_000_000;
+        let exact = exact_multiple(0.1, n);
+        let ulp = |x: f64| f64::from_bits(x.to_bits() + 1) - x;
+        let error = |s: Summation| (sum(s, (0..n).map(|_| 0.1)).value() - exact).abs() / ulp(exact);
+        assert!(error(Summation::Plain) > 100.0, "plain is off by {} ulp", error(Summation::Plain));
+        assert_eq!(error(Summation::Kahan), 0.0);
+        // Equal values make every block off by the same few ulp
+        assert!(error(Summation::Pairwise) <= 16.0, "pairwise is off by {} ulp", error(Summation::Pairwise));
+
+        // Cancellation: only the compensated sum keeps the ones
+        let cancelling = || (0..1000).flat_map(|_| [1e16, 1.0, -1e16, 1.0]);
+        assert_eq!(sum(Summation::Kahan, cancelling()).value(), 2000.0);
+        assert_ne!(sum(Summation::Plain, cancelling()).value(), 2000.0);
+    }
+
+    #[test]
+    fn test_merge() {
+        let values: Vec<f64> = (0..10_007).map(|i| (i % 97) as f64 * 0.01 + 0.1).collect();
+        for summation in [Summation::Plain, Summation::Kahan, Summation::Pairwise] {
+            let whole = sum(summation, values.iter().copied());
+            // Split like the ILP accumulator sets: every fourth value
+            let mut parts: Vec<SumState> = (0..4).map(|k| sum(summation, values.iter().skip(k).step_by(4).copied())).collect();
+            let (first, rest) = parts.split_first_mut().unwrap();
+            rest.iter().for_each(|part| first.merge(part));
+            assert!((first.value() - whole.value()).abs() <= 1e-12 * whole.value(), "{:?}", summation);
+            if summation == Summation::Kahan {
+                assert_eq!(first.value(), whole.value());
+            }
+        }
+
+        // The counter after merging holds every block exactly once
+        let mut a = PairwiseSum::default();
+        let mut b = PairwiseSum::default();
+        (0..PAIRWISE_BLOCK * 3 + 5).for_each(|_| a.add(1.0));
+        (0..PAIRWISE_BLOCK * 5 + 60).for_each(|_| b.add(1.0));
+        a.merge(&b);
+        assert_eq!((a.blocks, a.block_len, a.value()), (9, 0, (PAIRWISE_BLOCK * 9 + 1) as f64));
+
+        // Mixed kinds fall back to adding the value
+        let mut plain = sum(Summation::Plain, [1.0, 2.0]);
+        plain.merge(&sum(Summation::Kahan, [3.0]));
+        assert_eq!(plain, SumState::Plain(6.0));
+    }
+}
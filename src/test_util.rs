@@ -0,0 +1,107 @@
+//! Helpers for writing small lineitem Parquet files in unit tests
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use arrow::array::{Date32Array, Decimal128Array, Int64Array, RecordBatch, StringArray};
+use arrow::datatypes::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use parquet::file::properties::WriterProperties;
+
+/// One lineitem row restricted to the Q1 columns and `l_orderkey`
+///
+/// Decimal values are stored unscaled with scale 2, as in TPC-H.
+#[derive(Debug, Clone)]
+pub struct Row {
+    pub orderkey: i64,
+    pub returnflag: &'static str,
+    pub linestatus: &'static str,
+    pub quantity: i128,
+    pub extendedprice: i128,
+    pub discount: i128,
+    pub tax: i128,
+    pub shipdate: i32,
+}
+
+/// Deterministic pseudo-random rows with ship dates straddling the Q1 cutoff
+pub fn sample_rows(n: usize) -> Vec<Row> {
+    let mut seed: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    (0..n)
+        .map(|i| {
+            let r = next();
+            let shipdate = 8036 + (i as i64 * 2557 / n.max(1) as i64) as i32;
+            Row {
+                // Four lines per order
+                orderkey: 1 + i as i64 / 4,
+                returnflag: ["A", "N", "R"][(r % 3) as usize],
+                linestatus: if shipdate > 9298 { "O" } else { "F" },
+                quantity: 100 + (r >> 8) as i128 % 4900,
+                extendedprice: 90_000 + (r >> 16) as i128 % 10_000_000,
+                discount: (r >> 32) as i128 % 11,
+                tax: (r >> 40) as i128 % 9,
+                shipdate,
+            }
+        })
+        .collect()
+}
+
+/// Unique path under the system temp directory
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("goose-db-{}-{}.parquet", name, std::process::id()))
+}
+
+/// Write rows as a lineitem Parquet file with the given row group size
+pub fn write_lineitem(path: &PathBuf, rows: &[Row], row_group_size: usize) {
+    let props = WriterProperties::builder()
+        .set_max_row_group_size(row_group_size)
+        .build();
+    write_lineitem_with_props(path, rows, props);
+}
+
+/// Write rows as a lineitem Parquet file with explicit writer properties
+pub fn write_lineitem_with_props(path: &PathBuf, rows: &[Row], props: WriterProperties) {
+    let decimal = DataType::Decimal128(15, 2);
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("l_orderkey", DataType::Int64, false),
+        Field::new("l_quantity", decimal.clone(), false),
+        Field::new("l_extendedprice", decimal.clone(), false),
+        Field::new("l_discount", decimal.clone(), false),
+        Field::new("l_tax", decimal.clone(), false),
+        Field::new("l_returnflag", DataType::Utf8, false),
+        Field::new("l_linestatus", DataType::Utf8, false),
+        Field::new("l_shipdate", DataType::Date32, false),
+    ]));
+
+    let dec = |f: fn(&Row) -> i128| {
+        Arc::new(
+            Decimal128Array::from(rows.iter().map(f).collect::<Vec<_>>())
+                .with_precision_and_scale(15, 2)
+                .unwrap(),
+        )
+    };
+    let batch = RecordBatch::try_new(
+        schema.clone(),
+        vec![
+            Arc::new(Int64Array::from(rows.iter().map(|r| r.orderkey).collect::<Vec<_>>())),
+            dec(|r| r.quantity),
+            dec(|r| r.extendedprice),
+            dec(|r| r.discount),
+            dec(|r| r.tax),
+            Arc::new(StringArray::from(rows.iter().map(|r| r.returnflag).collect::<Vec<_>>())),
+            Arc::new(StringArray::from(rows.iter().map(|r| r.linestatus).collect::<Vec<_>>())),
+            Arc::new(Date32Array::from(rows.iter().map(|r| r.shipdate).collect::<Vec<_>>())),
+        ],
+    )
+    .unwrap();
+
+    let file = std::fs::File::create(path).unwrap();
+    let mut writer = ArrowWriter::try_new(file, schema, Some(props)).unwrap();
+    writer.write(&batch).unwrap();
+    writer.close().unwrap();
+}
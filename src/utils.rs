@@ -1,18 +1,35 @@
 //! Utility functions for data processing
 
-use arrow::array::{Array, Float64Array, RecordBatch};
+use arrow::array::{Array, ArrayRef, Decimal128Array, Float64Array, RecordBatch, StringArray};
 use arrow::compute::cast;
+use crate::error::GooseError;
+
+/// Get a column by name
+pub fn get_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a ArrayRef, GooseError> {
+    let idx = batch.schema().index_of(name).map_err(|_| GooseError::MissingColumn(name.to_string()))?;
+    Ok(batch.column(idx))
+}
+
+/// Get a Utf8 column by name
+pub fn get_string_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a StringArray, GooseError> {
+    let col = get_column(batch, name)?;
+    col.as_any()
+        .downcast_ref::<StringArray>()
+        .ok_or_else(|| GooseError::type_mismatch(name, "Utf8", col.data_type()))
+}
+
+/// Get a group key column by name (see `keys::is_key_type`)
+pub fn get_key_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a ArrayRef, GooseError> {
+    let col = get_column(batch, name)?;
+    if !crate::keys::is_key_type(col.data_type()) {
+        return Err(GooseError::type_mismatch(name, crate::keys::KEY_TYPES, col.data_type()));
+    }
+    Ok(col)
+}
 
 /// Optimized helper to get a Decimal128 column by name and convert to Float64 using Arrow cast kernel
-pub fn get_f64_column(batch: &RecordBatch, name: &str) -> Result<Float64Array, Box<dyn std::error::Error>> {
-    let idx = batch
-        .schema()
-        .fields()
-        .iter()
-        .position(|f| f.name() == name)
-        .ok_or_else(|| format!("Column {} not found", name))?;
-    
-    let col = batch.column(idx);
+pub fn get_f64_column(batch: &RecordBatch, name: &str) -> Result<Float64Array, GooseError> {
+    let col = get_column(batch, name)?;
     
     // Check if it's already F64, otherwise try to cast
     if col.data_type() == &arrow::datatypes::DataType::Float64 {
@@ -25,3 +42,22 @@ pub fn get_f64_column(batch: &RecordBatch, name: &str) -> Result<Float64Array, B
     
     Ok(cast_array.as_any().downcast_ref::<Float64Array>().unwrap().clone())
 }
+
+/// Get a column by name as Decimal128 without losing precision
+///
+/// Integer columns are widened to scale 0; floating-point columns are
+/// rejected because they cannot be represented exactly.
+pub fn get_decimal_column(batch: &RecordBatch, name: &str) -> Result<Decimal128Array, GooseError> {
+    let col = get_column(batch, name)?;
+
+    match col.data_type() {
+        arrow::datatypes::DataType::Decimal128(_, _) => {
+            Ok(col.as_any().downcast_ref::<Decimal128Array>().unwrap().clone())
+        }
+        dt if dt.is_integer() => {
+            let cast_array = cast(col, &arrow::datatypes::DataType::Decimal128(38, 0))?;
+            Ok(cast_array.as_any().downcast_ref::<Decimal128Array>().unwrap().clone())
+        }
+        dt => Err(GooseError::type_mismatch(name, "Decimal128 or integer", dt)),
+    }
+}
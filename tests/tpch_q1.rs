@@ -0,0 +1,388 @@
+//! End-to-end Q1 correctness over generated data
+//!
+//! Results are checked against the TPC-H answer sets and against a naive,
+//! row-at-a-time reference over the same file, for every execution mode:
+//! f64 sums single- and multi-threaded, `RowFilter` pushdown, and exact
+//! decimal sums. Edge cases cover an empty file, a cutoff that prunes every
+//! row group, and cutoffs that leave whole batches with all-false masks.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use arrow::array::{AsArray, RecordBatch};
+use arrow::compute::{concat_batches, sort_to_indices, take_record_batch};
+use arrow::datatypes::{Date32Type, Decimal128Type};
+use goose_db::aggregator::{AggregationMode, QueryResult};
+use goose_db::dbgen::{generate, write_table, Scale, Table};
+use goose_db::decimal_aggregator::Decimal;
+use goose_db::params::Q1Params;
+use goose_db::query::{execute_tpch_q1, execute_tpch_q1_profiled, execute_tpch_q1_with_options, ExecutionOptions};
+use goose_db::reader::FilterStrategy;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::file::properties::WriterProperties;
+
+/// TPC-H clause 2.1.3.5: SUM results within $100 of the answer set
+const SUM_TOLERANCE: f64 = 100.0;
+
+/// TPC-H clause 2.1.3.5: AVG results, rounded to cents, within 1%
+const AVG_RELATIVE_TOLERANCE: f64 = 0.01;
+
+/// f64 results against the exact reference: summation order only
+const FLOAT_RELATIVE_TOLERANCE: f64 = 1e-9;
+
+/// One row of a Q1 answer set, with values as printed there
+struct Answer {
+    returnflag: u8,
+    linestatus: u8,
+    /// sum_qty, sum_base_price, sum_disc_price, sum_charge
+    sums: [&'static str; 4],
+    /// avg_qty, avg_price, avg_disc
+    avgs: [&'static str; 3],
+    count: u64,
+}
+
+/// The official TPC-H validation output of Q1 (SF 1, DELTA = 90)
+const SF1_ANSWERS: [Answer; 4] = [
+    Answer {
+        returnflag: b'A',
+        linestatus: b'F',
+        sums: ["37734107.00", "56586554400.73", "53758257134.87", "55909065222.83"],
+        avgs: ["25.52", "38273.13", "0.05"],
+        count: 1_478_493,
+    },
+    Answer {
+        returnflag: b'N',
+        linestatus: b'F',
+        sums: ["991417.00", "1487504710.38", "1413082168.05", "1469649223.19"],
+        avgs: ["25.52", "38284.47", "0.05"],
+        count: 38_854,
+    },
+    Answer {
+        returnflag: b'N',
+        linestatus: b'O',
+        sums: ["74476040.00", "111701729697.74", "106118230307.61", "110367043872.50"],
+        avgs: ["25.50", "38249.12", "0.05"],
+        count: 2_920_374,
+    },
+    Answer {
+        returnflag: b'R',
+        linestatus: b'F',
+        sums: ["37719753.00", "56568041380.90", "53741292684.60", "55889619119.83"],
+        avgs: ["25.51", "38250.85", "0.05"],
+        count: 1_478_870,
+    },
+];
+
+/// Q1 over dbgen's SF 0.01 data, with the sums at full precision
+const SF001_ANSWERS: [Answer; 4] = [
+    Answer {
+        returnflag: b'A',
+        linestatus: b'F',
+        sums: ["380456.00", "532348211.65", "505822441.4861", "526165934.000839"],
+        avgs: ["25.58", "35785.71", "0.05"],
+        count: 14_876,
+    },
+    Answer {
+        returnflag: b'N',
+        linestatus: b'F',
+        sums: ["8971.00", "12384801.37", "11798257.2080", "12282485.056933"],
+        avgs: ["25.78", "35588.51", "0.05"],
+        count: 348,
+    },
+    Answer {
+        returnflag: b'N',
+        linestatus: b'O',
+        sums: ["742802.00", "1041502841.45", "989737518.6346", "1029418531.523350"],
+        avgs: ["25.45", "35691.13", "0.05"],
+        count: 29_181,
+    },
+    Answer {
+        returnflag: b'R',
+        linestatus: b'F',
+        sums: ["381449.00", "534594445.35", "507996454.4067", "528524219.358903"],
+        avgs: ["25.60", "35874.01", "0.05"],
+        count: 14_902,
+    },
+];
+
+/// Every way of running Q1 whose results must agree
+fn execution_modes() -> Vec<(&'static str, ExecutionOptions)> {
+    let exact = AggregationMode::ExactDecimal;
+    vec![
+        ("float", ExecutionOptions::default()),
+        ("float/4 threads", ExecutionOptions { num_threads: 4, ..ExecutionOptions::default() }),
+        ("row filter", ExecutionOptions { filter: FilterStrategy::RowFilter, ..ExecutionOptions::default() }),
+        ("exact", ExecutionOptions { mode: exact, ..ExecutionOptions::default() }),
+        ("exact/4 threads", ExecutionOptions { num_threads: 4, mode: exact, ..ExecutionOptions::default() }),
+    ]
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("goose-db-it-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Generated lineitem at `factor` in a fresh directory
+fn generated_lineitem(name: &str, factor: f64) -> (PathBuf, PathBuf) {
+    let dir = temp_dir(name);
+    let (path, _) = generate(&dir, factor, &[Table::Lineitem]).unwrap().remove(0);
+    (dir, path)
+}
+
+fn read_all(path: &Path) -> Vec<RecordBatch> {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap().build().unwrap();
+    reader.collect::<Result<_, _>>().unwrap()
+}
+
+/// `batches` sorted by ship date, in row groups of `row_group_size`, so
+/// the footer statistics can prune
+fn write_sorted_by_shipdate(batches: &[RecordBatch], path: &Path, row_group_size: usize) {
+    let batch = concat_batches(&batches[0].schema(), batches).unwrap();
+    let indices = sort_to_indices(batch.column_by_name("l_shipdate").unwrap(), None, None).unwrap();
+    let sorted = take_record_batch(&batch, &indices).unwrap();
+    let props = WriterProperties::builder().set_max_row_group_size(row_group_size).build();
+    let mut writer = ArrowWriter::try_new(File::create(path).unwrap(), sorted.schema(), Some(props)).unwrap();
+    writer.write(&sorted).unwrap();
+    writer.close().unwrap();
+}
+
+/// Exact sums and count of one group
+#[derive(Debug, Default)]
+struct Reference {
+    qty: i128,
+    base_price: i128,
+    disc_price: i128,
+    charge: i128,
+    discount: i128,
+    count: u64,
+}
+
+/// Q1 computed row by row in i128, straight from the file
+///
+/// Relies on the generator's scales: every measure is Decimal128(15, 2).
+fn naive_q1(path: &Path, params: &Q1Params) -> BTreeMap<(u8, u8), Reference> {
+    let mut groups: BTreeMap<(u8, u8), Reference> = BTreeMap::new();
+    for batch in read_all(path) {
+        let column = |name: &str| batch.column_by_name(name).unwrap();
+        let decimal = |name: &str| column(name).as_primitive::<Decimal128Type>().values().clone();
+        let (qty, price, discount, tax) = (decimal("l_quantity"), decimal("l_extendedprice"), decimal("l_discount"), decimal("l_tax"));
+        let shipdate = column("l_shipdate").as_primitive::<Date32Type>();
+        let (flags, statuses) = (column("l_returnflag").as_string::<i32>(), column("l_linestatus").as_string::<i32>());
+        for row in 0..batch.num_rows() {
+            if shipdate.value(row) > params.cutoff_days() {
+                continue;
+            }
+            let key = (flags.value(row).as_bytes()[0], statuses.value(row).as_bytes()[0]);
+            let group = groups.entry(key).or_default();
+            let disc_price = price[row] * (100 - discount[row]);
+            group.qty += qty[row];
+            group.base_price += price[row];
+            group.disc_price += disc_price;
+            group.charge += disc_price * (100 + tax[row]);
+            group.discount += discount[row];
+            group.count += 1;
+        }
+    }
+    groups
+}
+
+fn assert_close(actual: Option<f64>, expected: f64, what: &str) {
+    let actual = actual.unwrap_or_else(|| panic!("{} is NULL", what));
+    let tolerance = FLOAT_RELATIVE_TOLERANCE * expected.abs().max(1.0);
+    assert!((actual - expected).abs() <= tolerance, "{}: {} vs {}", what, actual, expected);
+}
+
+/// Every field of `results` against the naive reference
+fn check_reference(results: &[QueryResult], reference: &BTreeMap<(u8, u8), Reference>, mode: &str) {
+    let keys: Vec<(u8, u8)> = results.iter().map(|r| (r.returnflag.unwrap(), r.linestatus.unwrap())).collect();
+    assert_eq!(keys, reference.keys().copied().collect::<Vec<_>>(), "{}: groups", mode);
+
+    for (r, expected) in results.iter().zip(reference.values()) {
+        let what = |field: &str| format!("{} {}{} {}", mode, r.returnflag.unwrap() as char, r.linestatus.unwrap() as char, field);
+        assert_eq!(r.count, expected.count, "{}", what("count"));
+        let n = expected.count as f64;
+        let exact = [
+            Decimal::new(expected.qty, 2),
+            Decimal::new(expected.base_price, 2),
+            Decimal::new(expected.disc_price, 4),
+            Decimal::new(expected.charge, 6),
+        ];
+        let floats = [r.sum_qty, r.sum_base_price, r.sum_disc_price, r.sum_charge];
+        for ((field, float), exact) in ["sum_qty", "sum_base_price", "sum_disc_price", "sum_charge"].iter().zip(floats).zip(exact) {
+            assert_close(float, exact.to_f64(), &what(field));
+        }
+        assert_close(r.avg_qty, exact[0].to_f64() / n, &what("avg_qty"));
+        assert_close(r.avg_price, exact[1].to_f64() / n, &what("avg_price"));
+        assert_close(r.avg_disc, Decimal::new(expected.discount, 2).to_f64() / n, &what("avg_disc"));
+
+        if let Some(sums) = r.exact {
+            assert_eq!([sums.sum_qty, sums.sum_base_price, sums.sum_disc_price, sums.sum_charge], exact, "{}", what("exact sums"));
+            assert_eq!(sums.sum_discount, Decimal::new(expected.discount, 2), "{}", what("exact sum_discount"));
+        }
+    }
+}
+
+/// `value` rounded half away from zero to `scale` digits, as printed
+fn round_to(value: Decimal, scale: i8) -> String {
+    let divisor = 10i128.pow((value.scale - scale).max(0) as u32);
+    let rounded = (value.value.abs() + divisor / 2) / divisor * value.value.signum();
+    Decimal::new(rounded, scale).to_string()
+}
+
+/// Digits after the decimal point of a printed answer
+fn scale_of(printed: &str) -> i8 {
+    printed.split_once('.').map_or(0, |(_, digits)| digits.len() as i8)
+}
+
+/// `results` against an answer set: counts exactly, exact sums digit for
+/// digit at the answer's precision, f64 sums and averages within the TPC-H
+/// validation tolerances
+fn check_answers(results: &[QueryResult], answers: &[Answer], mode: &str) {
+    assert_eq!(results.len(), answers.len(), "{}: groups", mode);
+    for (r, answer) in results.iter().zip(answers) {
+        let what = |field: &str| format!("{} {}{} {}", mode, answer.returnflag as char, answer.linestatus as char, field);
+        assert_eq!((r.returnflag, r.linestatus), (Some(answer.returnflag), Some(answer.linestatus)), "{}", what("key"));
+        assert_eq!(r.count, answer.count, "{}", what("count"));
+
+        let floats = [r.sum_qty, r.sum_base_price, r.sum_disc_price, r.sum_charge];
+        for (i, field) in ["sum_qty", "sum_base_price", "sum_disc_price", "sum_charge"].into_iter().enumerate() {
+            let expected: f64 = answer.sums[i].parse().unwrap();
+            let actual = floats[i].unwrap();
+            assert!((actual - expected).abs() <= SUM_TOLERANCE, "{}: {} vs {}", what(field), actual, expected);
+        }
+        let avgs = [r.avg_qty, r.avg_price, r.avg_disc];
+        for (i, field) in ["avg_qty", "avg_price", "avg_disc"].into_iter().enumerate() {
+            let expected: f64 = answer.avgs[i].parse().unwrap();
+            let rounded = (avgs[i].unwrap() * 100.0).round() / 100.0;
+            let tolerance = AVG_RELATIVE_TOLERANCE * expected;
+            assert!((rounded - expected).abs() <= tolerance, "{}: {} vs {}", what(field), rounded, expected);
+        }
+
+        if let Some(sums) = r.exact {
+            let exact = [sums.sum_qty, sums.sum_base_price, sums.sum_disc_price, sums.sum_charge];
+            for (i, field) in ["sum_qty", "sum_base_price", "sum_disc_price", "sum_charge"].into_iter().enumerate() {
+                assert_eq!(round_to(exact[i], scale_of(answer.sums[i])), answer.sums[i], "{}", what(field));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_sf001_answers() {
+    let (dir, path) = generated_lineitem("sf001", 0.01);
+    let path_str = path.to_str().unwrap();
+    let params = Q1Params::default();
+    let reference = naive_q1(&path, &params);
+
+    for (mode, options) in execution_modes() {
+        let results = execute_tpch_q1_with_options(path_str, &params, &options).unwrap();
+        check_answers(&results, &SF001_ANSWERS, mode);
+        check_reference(&results, &reference, mode);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// The official answer set needs SF 1 (6M rows): run with
+/// `cargo test --release --test tpch_q1 -- --ignored`
+#[test]
+#[ignore]
+fn test_sf1_official_answers() {
+    let (dir, path) = generated_lineitem("sf1", 1.0);
+    let path_str = path.to_str().unwrap();
+    for (mode, options) in execution_modes() {
+        let results = execute_tpch_q1_with_options(path_str, &Q1Params::default(), &options).unwrap();
+        check_answers(&results, &SF1_ANSWERS, mode);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cutoffs_match_reference() {
+    let (dir, path) = generated_lineitem("cutoffs", 0.01);
+    let sorted = dir.join("sorted.parquet");
+    write_sorted_by_shipdate(&read_all(&path), &sorted, 4_096);
+
+    // DELTA at both ends of the qualification range, and the last ship date
+    let cutoffs = [Q1Params::with_delta(60), Q1Params::with_delta(120), Q1Params::with_date(10_591)];
+    for file in [&path, &sorted] {
+        for params in cutoffs {
+            let reference = naive_q1(file, &params);
+            for (mode, options) in execution_modes() {
+                let results = execute_tpch_q1_with_options(file.to_str().unwrap(), &params, &options).unwrap();
+                check_reference(&results, &reference, mode);
+            }
+        }
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_empty_file() {
+    let dir = temp_dir("empty");
+    let path = dir.join("lineitem.parquet");
+    // A footer and a schema, but no row groups at all
+    ArrowWriter::try_new(File::create(&path).unwrap(), Table::Lineitem.schema(), None).unwrap().close().unwrap();
+
+    for (mode, options) in execution_modes() {
+        let results = execute_tpch_q1_with_options(path.to_str().unwrap(), &Q1Params::default(), &options).unwrap();
+        assert!(results.is_empty(), "{}: {:?}", mode, results);
+    }
+    let (_, profile) = execute_tpch_q1_profiled(path.to_str().unwrap(), &Q1Params::default(), &ExecutionOptions::default()).unwrap();
+    assert_eq!((profile.row_groups_total, profile.rows_in, profile.rows_out), (0, 0, 0));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_fully_pruned_row_groups() {
+    let dir = temp_dir("pruned");
+    let path = dir.join("lineitem.parquet");
+    let props = WriterProperties::builder().set_max_row_group_size(8_192).build();
+    write_table(Table::Lineitem, &Scale::new(0.01).unwrap(), &path, props).unwrap();
+    let path_str = path.to_str().unwrap();
+
+    // Ship dates start on 1992-01-02 (day 8036): nothing qualifies before it
+    let params = Q1Params::with_date(8_035);
+    for (mode, options) in execution_modes() {
+        let (results, profile) = execute_tpch_q1_profiled(path_str, &params, &options).unwrap();
+        assert!(results.is_empty(), "{}: {:?}", mode, results);
+        assert_eq!(profile.row_groups_total, 8, "{}", mode);
+        assert_eq!(profile.row_groups_skipped, profile.row_groups_total, "{}", mode);
+        assert_eq!(profile.rows_in, 0, "{}", mode);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_all_false_masks() {
+    let (dir, path) = generated_lineitem("all-false", 0.01);
+    let sorted = dir.join("sorted.parquet");
+    write_sorted_by_shipdate(&read_all(&path), &sorted, 4_096);
+
+    // A week of ship dates: a handful of rows per row group in the
+    // unsorted file, and in the sorted one a single surviving row group
+    // split over batches past the cutoff
+    let params = Q1Params::with_date(8_042);
+    for file in [&path, &sorted] {
+        let path_str = file.to_str().unwrap();
+        let reference = naive_q1(file, &params);
+        let qualifying: u64 = reference.values().map(|g| g.count).sum();
+        assert!(qualifying > 0);
+
+        let (_, profile) = execute_tpch_q1_profiled(path_str, &params, &ExecutionOptions::default()).unwrap();
+        // More rows decoded than qualify: those batches carry all-false masks
+        assert!(profile.rows_in > qualifying, "{} rows in, {} qualify", profile.rows_in, qualifying);
+        assert_eq!(profile.rows_selected, qualifying);
+        for (mode, options) in execution_modes() {
+            let results = execute_tpch_q1_with_options(path_str, &params, &options).unwrap();
+            check_reference(&results, &reference, mode);
+        }
+    }
+
+    // The default entry point agrees with the options one
+    let results = execute_tpch_q1(path.to_str().unwrap(), &params).unwrap();
+    check_reference(&results, &naive_q1(&path, &params), "default");
+    std::fs::remove_dir_all(dir).unwrap();
+}